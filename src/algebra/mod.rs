@@ -139,7 +139,7 @@ impl Vector {
 	}
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub struct WavelengthBunch(pub Scalar, pub Scalar, pub Scalar, pub Scalar);
 
 impl ops::Add for WavelengthBunch {
@@ -168,6 +168,89 @@ impl ops::Mul<WavelengthBunch> for WavelengthBunch {
 	}
 }
 
+// complex number, e.g. amplitude of a wave or complex index of refraction
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct Complex {
+	pub re: Scalar,
+	pub im: Scalar,
+}
+
+impl Complex {
+	pub fn new(re: Scalar, im: Scalar) -> Complex {
+		Complex { re, im }
+	}
+
+	pub fn norm_sqr(&self) -> Scalar {
+		self.re * self.re + self.im * self.im
+	}
+
+	// principal square root, with non-negative real part
+	pub fn sqrt(&self) -> Complex {
+		let norm = self.norm_sqr().sqrt();
+		let re = (0.5 * (norm + self.re)).max(0.0).sqrt();
+		let im = (0.5 * (norm - self.re)).max(0.0).sqrt();
+		Complex::new(re, if self.im < 0.0 { -im } else { im })
+	}
+
+	pub fn exp(&self) -> Complex {
+		let (sin, cos) = self.im.sin_cos();
+		Complex::new(cos, sin) * self.re.exp()
+	}
+}
+
+impl From<Scalar> for Complex {
+	fn from(re: Scalar) -> Complex {
+		Complex::new(re, 0.0)
+	}
+}
+
+impl ops::Add for Complex {
+	type Output = Self;
+
+	fn add(self, other: Self) -> Self {
+		Complex::new(self.re + other.re, self.im + other.im)
+	}
+}
+
+impl ops::Sub for Complex {
+	type Output = Self;
+
+	fn sub(self, other: Self) -> Self {
+		Complex::new(self.re - other.re, self.im - other.im)
+	}
+}
+
+impl ops::Mul for Complex {
+	type Output = Self;
+
+	fn mul(self, other: Self) -> Self {
+		Complex::new(
+			self.re * other.re - self.im * other.im,
+			self.re * other.im + self.im * other.re,
+		)
+	}
+}
+
+impl ops::Mul<Scalar> for Complex {
+	type Output = Self;
+
+	fn mul(self, k: Scalar) -> Self {
+		Complex::new(self.re * k, self.im * k)
+	}
+}
+
+impl ops::Div for Complex {
+	type Output = Self;
+
+	fn div(self, other: Self) -> Self {
+		let norm = other.norm_sqr();
+		Complex::new(
+			(self.re * other.re + self.im * other.im) / norm,
+			(self.im * other.re - self.re * other.im) / norm,
+		)
+	}
+}
+
 pub struct Basis {
 	// vectors given in world basis
 	pub u: Vector,
@@ -197,7 +280,7 @@ impl Basis {
 	}
 
 	pub fn basis_to_spherical(&self, vector: Vector) -> (Scalar, Scalar) {
-		let pi: Scalar = 3.1415926536;
+		let pi: Scalar = std::f64::consts::PI;
 		let phi: Scalar = if vector.x > 0.0 {
 			(vector.y / vector.x).atan()
 		} else if vector.x < 0.0 && vector.y >= 0.0 {
@@ -1,10 +1,15 @@
 use crate::algebra;
 
-pub const HC_BY_K: algebra::Scalar = 0.01439;
-pub const TWO_HC2: algebra::Scalar = 1.191e-16;
-pub const WIEN: algebra::Scalar = 2.89777196e-3;
-pub const PI_INV: algebra::Scalar = 0.3183098862;
-pub const PI: algebra::Scalar = 3.1415926536;
+// CODATA 2018 values in SI units
+// source: https://physics.nist.gov/cuu/Constants/
+pub const PLANCK: algebra::Scalar = 6.62607015e-34;
+pub const SPEED_OF_LIGHT: algebra::Scalar = 299792458.0;
+pub const BOLTZMANN: algebra::Scalar = 1.380649e-23;
+pub const HC_BY_K: algebra::Scalar = PLANCK * SPEED_OF_LIGHT / BOLTZMANN;
+pub const TWO_HC2: algebra::Scalar = 2.0 * PLANCK * SPEED_OF_LIGHT * SPEED_OF_LIGHT;
+pub const WIEN: algebra::Scalar = 2.897771955e-3;
+pub const PI_INV: algebra::Scalar = std::f64::consts::FRAC_1_PI;
+pub const PI: algebra::Scalar = std::f64::consts::PI;
 
 // source:
 // https://cie.co.at/datatable/cie-1931-colour-matching-functions-2-degree-observer
@@ -482,3 +487,638 @@ pub const CIE_XYZ_1931_COLOR_MATCH_2_DEG: [(algebra::Scalar, algebra::Scalar, al
 	(0.000001341977, 0.0000004846123, 0.000000000000),
 	(0.000001251141, 0.0000004518100, 0.000000000000),
 ];
+
+// 360 - 830 nm in 5 nm steps
+// source:
+// https://cie.co.at/datatable/cie-1964-colour-matching-functions-10-degree-observer
+pub const CIE_XYZ_1964_COLOR_MATCH_10_DEG: [(algebra::Scalar, algebra::Scalar, algebra::Scalar);
+	95] = [
+	(0.000000, 0.000000, 0.000001),
+	(0.000001, 0.000000, 0.000004),
+	(0.000006, 0.000001, 0.000026),
+	(0.000033, 0.000004, 0.000147),
+	(0.000160, 0.000017, 0.000705),
+	(0.000662, 0.000072, 0.002928),
+	(0.002362, 0.000253, 0.010482),
+	(0.007242, 0.000769, 0.032344),
+	(0.019110, 0.002004, 0.086011),
+	(0.043400, 0.004509, 0.197120),
+	(0.084736, 0.008756, 0.389366),
+	(0.140638, 0.014456, 0.656760),
+	(0.204492, 0.021391, 0.972542),
+	(0.264737, 0.029497, 1.282500),
+	(0.314679, 0.038676, 1.553480),
+	(0.357719, 0.049602, 1.798500),
+	(0.383734, 0.062077, 1.967280),
+	(0.386726, 0.074704, 2.027300),
+	(0.370702, 0.089456, 1.994800),
+	(0.342957, 0.106256, 1.900700),
+	(0.302273, 0.128201, 1.745370),
+	(0.254085, 0.152761, 1.554900),
+	(0.195618, 0.185190, 1.317560),
+	(0.132349, 0.219940, 1.030200),
+	(0.080507, 0.253589, 0.772125),
+	(0.041072, 0.297665, 0.570060),
+	(0.016172, 0.339133, 0.415254),
+	(0.005132, 0.395379, 0.302356),
+	(0.003816, 0.460777, 0.218502),
+	(0.015444, 0.531360, 0.159249),
+	(0.037465, 0.606741, 0.112044),
+	(0.071358, 0.685660, 0.082248),
+	(0.117749, 0.761757, 0.060709),
+	(0.172953, 0.823330, 0.043050),
+	(0.236491, 0.875211, 0.030451),
+	(0.304213, 0.923810, 0.020584),
+	(0.376772, 0.961988, 0.013676),
+	(0.451584, 0.982200, 0.007918),
+	(0.529826, 0.991761, 0.003988),
+	(0.616053, 0.999110, 0.001091),
+	(0.705224, 0.997340, 0.000000),
+	(0.793832, 0.982380, 0.000000),
+	(0.878655, 0.955552, 0.000000),
+	(0.951162, 0.915175, 0.000000),
+	(1.014160, 0.868934, 0.000000),
+	(1.074300, 0.825623, 0.000000),
+	(1.118520, 0.777405, 0.000000),
+	(1.134300, 0.720353, 0.000000),
+	(1.123990, 0.658341, 0.000000),
+	(1.089100, 0.593878, 0.000000),
+	(1.030480, 0.527963, 0.000000),
+	(0.950740, 0.461834, 0.000000),
+	(0.856297, 0.398057, 0.000000),
+	(0.754930, 0.339554, 0.000000),
+	(0.647467, 0.283493, 0.000000),
+	(0.535110, 0.228254, 0.000000),
+	(0.431567, 0.179828, 0.000000),
+	(0.343690, 0.140211, 0.000000),
+	(0.268329, 0.107633, 0.000000),
+	(0.204300, 0.081187, 0.000000),
+	(0.152568, 0.060281, 0.000000),
+	(0.112210, 0.044096, 0.000000),
+	(0.081261, 0.031800, 0.000000),
+	(0.057930, 0.022602, 0.000000),
+	(0.040851, 0.015905, 0.000000),
+	(0.028623, 0.011130, 0.000000),
+	(0.019941, 0.007749, 0.000000),
+	(0.013842, 0.005375, 0.000000),
+	(0.009577, 0.003718, 0.000000),
+	(0.006605, 0.002565, 0.000000),
+	(0.004553, 0.001768, 0.000000),
+	(0.003145, 0.001222, 0.000000),
+	(0.002175, 0.000846, 0.000000),
+	(0.001506, 0.000586, 0.000000),
+	(0.001045, 0.000407, 0.000000),
+	(0.000727, 0.000284, 0.000000),
+	(0.000508, 0.000199, 0.000000),
+	(0.000356, 0.000140, 0.000000),
+	(0.000251, 0.000098, 0.000000),
+	(0.000178, 0.000070, 0.000000),
+	(0.000126, 0.000050, 0.000000),
+	(0.000090, 0.000036, 0.000000),
+	(0.000065, 0.000025, 0.000000),
+	(0.000046, 0.000018, 0.000000),
+	(0.000033, 0.000013, 0.000000),
+	(0.000024, 0.000009, 0.000000),
+	(0.000017, 0.000007, 0.000000),
+	(0.000012, 0.000005, 0.000000),
+	(0.000009, 0.000003, 0.000000),
+	(0.000006, 0.000002, 0.000000),
+	(0.000004, 0.000002, 0.000000),
+	(0.000003, 0.000001, 0.000000),
+	(0.000002, 0.000001, 0.000000),
+	(0.000002, 0.000001, 0.000000),
+	(0.000001, 0.000000, 0.000000),
+];
+
+// CIE daylight basis functions, 300 - 830 nm in 10 nm steps
+// source:
+// https://cie.co.at/datatable/components-relative-spectral-distribution-daylight
+pub const CIE_DAYLIGHT_S0: [algebra::Scalar; 54] = [
+	0.04, 6.0, 29.6, 55.3, 57.3, 61.8, 61.5, 68.8, 63.4,
+	65.8, 94.8, 104.8, 105.9, 96.8, 113.9, 125.6, 125.5, 121.3,
+	121.3, 113.5, 113.1, 110.8, 106.5, 108.8, 105.3, 104.4, 100.0,
+	96.0, 95.1, 89.1, 90.5, 90.3, 88.4, 84.0, 85.1, 81.9,
+	82.6, 84.9, 81.3, 71.9, 74.3, 76.4, 63.3, 71.7, 77.0,
+	65.2, 47.7, 68.6, 65.0, 66.0, 61.0, 53.3, 58.9, 61.9,
+];
+pub const CIE_DAYLIGHT_S1: [algebra::Scalar; 54] = [
+	0.02, 4.5, 22.4, 42.0, 40.6, 41.6, 38.0, 42.4, 38.5,
+	35.0, 43.4, 46.3, 43.9, 37.1, 36.7, 35.9, 32.6, 27.9,
+	24.3, 20.1, 16.2, 13.2, 8.6, 6.1, 4.2, 1.9, 0.0,
+	-1.6, -3.5, -3.5, -5.8, -7.2, -8.6, -9.5, -10.9, -10.7,
+	-12.0, -14.0, -13.6, -12.0, -13.3, -12.9, -10.6, -11.6, -12.2,
+	-10.2, -7.8, -11.2, -10.4, -10.6, -9.7, -8.3, -9.3, -9.8,
+];
+pub const CIE_DAYLIGHT_S2: [algebra::Scalar; 54] = [
+	0.0, 2.0, 4.0, 8.5, 7.8, 6.7, 5.3, 6.1, 2.0,
+	1.2, -1.1, -0.5, -0.7, -1.2, -2.6, -2.9, -2.8, -2.6,
+	-2.6, -1.8, -1.5, -1.3, -1.2, -1.0, -0.5, -0.3, 0.0,
+	0.2, 0.5, 2.1, 3.2, 4.1, 4.7, 5.1, 6.7, 7.3,
+	8.6, 9.8, 10.2, 8.3, 9.6, 8.5, 7.0, 7.6, 8.0,
+	6.7, 5.2, 7.4, 6.8, 7.0, 6.4, 5.5, 6.1, 6.5,
+];
+
+// Preetham sky model coefficients; Perez parameters A - E are linear in turbidity T
+// and given as (factor of T, constant); X and Y denote chromaticity coordinates;
+// zenith chromaticity matrices are multiplied by (T^2, T, 1) from the left
+// and (theta^3, theta^2, theta, 1) from the right
+// source: https://courses.cs.duke.edu/fall01/cps124/resources/p91-preetham.pdf
+pub const PREETHAM_PEREZ_LUMINANCE: [(algebra::Scalar, algebra::Scalar); 5] = [
+	(0.1787, -1.4630),
+	(-0.3554, 0.4275),
+	(-0.0227, 5.3251),
+	(0.1206, -2.5771),
+	(-0.0670, 0.3703),
+];
+pub const PREETHAM_PEREZ_X: [(algebra::Scalar, algebra::Scalar); 5] = [
+	(-0.0193, -0.2592),
+	(-0.0665, 0.0008),
+	(-0.0004, 0.2125),
+	(-0.0641, -0.8989),
+	(-0.0033, 0.0452),
+];
+pub const PREETHAM_PEREZ_Y: [(algebra::Scalar, algebra::Scalar); 5] = [
+	(-0.0167, -0.2608),
+	(-0.0950, 0.0092),
+	(-0.0079, 0.2102),
+	(-0.0441, -1.6537),
+	(-0.0109, 0.0529),
+];
+pub const PREETHAM_ZENITH_X: [[algebra::Scalar; 4]; 3] = [
+	[0.00166, -0.00375, 0.00209, 0.0],
+	[-0.02903, 0.06377, -0.03202, 0.00394],
+	[0.11693, -0.21196, 0.06052, 0.25886],
+];
+pub const PREETHAM_ZENITH_Y: [[algebra::Scalar; 4]; 3] = [
+	[0.00275, -0.00610, 0.00317, 0.0],
+	[-0.04214, 0.08970, -0.04153, 0.00516],
+	[0.15346, -0.26756, 0.06670, 0.26688],
+];
+
+// effective temperature and angular radius of the sun seen from Earth
+pub const SUN_TEMPERATURE: algebra::Scalar = 5778.0;
+pub const SUN_ANGULAR_RADIUS: algebra::Scalar = 4.654e-3;
+// luminous efficacy of 555 nm radiation in lm/W
+pub const LUMINOUS_EFFICACY: algebra::Scalar = 683.0;
+
+// CIE fluorescent illuminants F1 - F12, 380 - 780 nm in 5 nm steps
+// source:
+// https://cie.co.at/datatable/relative-spectral-power-distributions-cie-illuminants-f1-f12
+// tabulated values that happen to resemble PI and TAU
+#[allow(clippy::approx_constant)]
+pub const CIE_ILLUMINANT_F: [[algebra::Scalar; 81]; 12] = [
+	// F1
+	[
+		1.87, 2.36, 2.94, 3.47, 5.17, 19.49, 6.13, 6.24, 7.01,
+		7.79, 8.56, 43.67, 16.94, 10.72, 11.35, 11.89, 12.37, 12.75,
+		13.00, 13.15, 13.23, 13.17, 13.13, 12.85, 12.52, 12.20, 11.83,
+		11.50, 11.22, 11.05, 11.03, 11.18, 11.53, 27.74, 17.05, 13.55,
+		14.33, 15.01, 15.52, 18.29, 19.55, 15.48, 14.91, 14.15, 13.22,
+		12.19, 11.12, 10.03, 8.95, 7.96, 7.02, 6.20, 5.42, 4.73,
+		4.15, 3.64, 3.20, 2.81, 2.47, 2.18, 1.93, 1.72, 1.67,
+		1.43, 1.29, 1.19, 1.08, 0.96, 0.88, 0.81, 0.77, 0.75,
+		0.73, 0.68, 0.69, 0.64, 0.68, 0.69, 0.61, 0.52, 0.43,
+	],
+	// F2
+	[
+		1.18, 1.48, 1.84, 2.15, 3.44, 15.69, 3.85, 3.74, 4.19,
+		4.62, 5.06, 34.98, 11.81, 6.27, 6.63, 6.93, 7.19, 7.40,
+		7.54, 7.62, 7.65, 7.62, 7.62, 7.45, 7.28, 7.15, 7.05,
+		7.04, 7.16, 7.47, 8.04, 8.88, 10.01, 24.88, 16.64, 14.59,
+		16.16, 17.56, 18.62, 21.47, 22.79, 19.29, 18.66, 17.73, 16.54,
+		15.21, 13.80, 12.36, 10.95, 9.65, 8.40, 7.32, 6.31, 5.43,
+		4.68, 4.02, 3.45, 2.96, 2.55, 2.19, 1.89, 1.64, 1.53,
+		1.27, 1.10, 0.99, 0.88, 0.76, 0.68, 0.61, 0.56, 0.54,
+		0.51, 0.47, 0.47, 0.43, 0.46, 0.47, 0.40, 0.33, 0.27,
+	],
+	// F3
+	[
+		0.82, 1.02, 1.26, 1.44, 2.57, 14.36, 2.70, 2.45, 2.73,
+		3.00, 3.28, 31.85, 9.47, 4.02, 4.25, 4.44, 4.59, 4.72,
+		4.80, 4.86, 4.87, 4.85, 4.88, 4.77, 4.67, 4.62, 4.62,
+		4.73, 4.99, 5.48, 6.25, 7.34, 8.78, 23.82, 16.14, 14.59,
+		16.63, 18.49, 19.95, 23.11, 24.69, 21.41, 20.85, 19.93, 18.67,
+		17.22, 15.65, 14.04, 12.45, 10.95, 9.51, 8.27, 7.11, 6.09,
+		5.22, 4.45, 3.80, 3.23, 2.75, 2.33, 1.99, 1.70, 1.55,
+		1.27, 1.09, 0.96, 0.83, 0.71, 0.62, 0.54, 0.49, 0.46,
+		0.43, 0.39, 0.39, 0.35, 0.38, 0.39, 0.33, 0.28, 0.21,
+	],
+	// F4
+	[
+		0.57, 0.70, 0.87, 0.98, 2.01, 13.75, 1.95, 1.59, 1.76,
+		1.93, 2.10, 30.28, 8.03, 2.55, 2.70, 2.82, 2.91, 2.99,
+		3.04, 3.08, 3.09, 3.09, 3.14, 3.06, 3.00, 2.98, 3.01,
+		3.14, 3.41, 3.90, 4.69, 5.81, 7.32, 22.59, 15.11, 13.88,
+		16.33, 18.68, 20.64, 24.28, 26.26, 23.28, 22.94, 22.14, 20.91,
+		19.43, 17.74, 16.00, 14.42, 12.56, 10.93, 9.52, 8.18, 7.01,
+		6.00, 5.11, 4.36, 3.69, 3.13, 2.64, 2.24, 1.91, 1.70,
+		1.39, 1.18, 1.03, 0.88, 0.74, 0.64, 0.54, 0.49, 0.46,
+		0.42, 0.37, 0.37, 0.33, 0.35, 0.36, 0.31, 0.26, 0.19,
+	],
+	// F5
+	[
+		1.87, 2.35, 2.92, 3.45, 5.10, 18.91, 6.00, 6.11, 6.85,
+		7.58, 8.31, 40.76, 16.06, 10.32, 10.91, 11.40, 11.83, 12.17,
+		12.40, 12.54, 12.58, 12.52, 12.47, 12.20, 11.89, 11.61, 11.33,
+		11.10, 10.96, 10.97, 11.16, 11.54, 12.12, 27.78, 17.73, 14.47,
+		15.20, 15.77, 16.10, 18.54, 19.50, 15.39, 14.64, 13.72, 12.69,
+		11.57, 10.45, 9.35, 8.29, 7.32, 6.41, 5.63, 4.90, 4.26,
+		3.72, 3.25, 2.83, 2.49, 2.19, 1.93, 1.71, 1.52, 1.48,
+		1.26, 1.13, 1.05, 0.96, 0.85, 0.78, 0.72, 0.68, 0.67,
+		0.65, 0.61, 0.62, 0.59, 0.62, 0.64, 0.55, 0.47, 0.40,
+	],
+	// F6
+	[
+		1.05, 1.31, 1.63, 1.90, 3.11, 14.80, 3.43, 3.30, 3.68,
+		4.07, 4.45, 32.61, 10.74, 5.48, 5.78, 6.03, 6.25, 6.41,
+		6.52, 6.58, 6.59, 6.56, 6.56, 6.42, 6.28, 6.20, 6.19,
+		6.30, 6.60, 7.12, 7.94, 9.07, 10.49, 25.22, 17.46, 15.63,
+		17.22, 18.53, 19.43, 21.97, 23.01, 19.41, 18.56, 17.42, 16.09,
+		14.64, 13.15, 11.68, 10.25, 8.95, 7.74, 6.69, 5.71, 4.87,
+		4.16, 3.55, 3.02, 2.57, 2.20, 1.87, 1.60, 1.37, 1.29,
+		1.05, 0.91, 0.81, 0.71, 0.61, 0.54, 0.48, 0.44, 0.43,
+		0.40, 0.37, 0.38, 0.35, 0.39, 0.41, 0.33, 0.26, 0.21,
+	],
+	// F7
+	[
+		2.56, 3.18, 3.84, 4.53, 6.15, 19.37, 7.37, 7.05, 7.71,
+		8.41, 9.15, 44.14, 17.52, 11.35, 12.00, 12.58, 13.08, 13.45,
+		13.71, 13.88, 13.95, 13.93, 13.82, 13.64, 13.43, 13.25, 13.08,
+		12.93, 12.78, 12.60, 12.44, 12.33, 12.26, 29.52, 17.05, 12.44,
+		12.58, 12.72, 12.83, 15.46, 16.75, 12.83, 12.67, 12.45, 12.19,
+		11.89, 11.60, 11.35, 11.12, 10.95, 10.76, 10.42, 10.11, 10.04,
+		10.02, 10.11, 9.87, 8.65, 7.27, 6.44, 5.83, 5.41, 5.04,
+		4.57, 4.12, 3.77, 3.46, 3.08, 2.73, 2.47, 2.25, 2.06,
+		1.90, 1.75, 1.62, 1.54, 1.45, 1.32, 1.17, 0.99, 0.81,
+	],
+	// F8
+	[
+		1.21, 1.50, 1.81, 2.13, 3.17, 13.08, 3.83, 3.45, 3.86,
+		4.42, 5.09, 34.10, 12.42, 7.68, 8.60, 9.46, 10.24, 10.84,
+		11.33, 11.71, 11.98, 12.17, 12.28, 12.32, 12.35, 12.44, 12.55,
+		12.68, 12.77, 12.72, 12.60, 12.43, 12.22, 28.96, 16.51, 11.79,
+		11.76, 11.77, 11.84, 14.61, 16.11, 12.34, 12.53, 12.72, 12.92,
+		13.12, 13.34, 13.61, 13.87, 14.07, 14.20, 14.16, 14.13, 14.34,
+		14.50, 14.46, 14.00, 12.58, 10.99, 9.98, 9.22, 8.62, 8.07,
+		7.39, 6.71, 6.16, 5.63, 5.03, 4.46, 4.02, 3.66, 3.36,
+		3.09, 2.85, 2.65, 2.51, 2.37, 2.15, 1.89, 1.61, 1.32,
+	],
+	// F9
+	[
+		0.90, 1.12, 1.36, 1.60, 2.59, 12.80, 3.05, 2.56, 2.86,
+		3.30, 3.82, 32.62, 10.77, 5.84, 6.57, 7.25, 7.86, 8.35,
+		8.75, 9.06, 9.31, 9.48, 9.61, 9.68, 9.74, 9.88, 10.04,
+		10.26, 10.48, 10.63, 10.78, 10.96, 11.18, 27.71, 16.29, 12.28,
+		12.74, 13.21, 13.65, 16.57, 18.14, 14.55, 14.65, 14.66, 14.61,
+		14.50, 14.39, 14.40, 14.47, 14.62, 14.72, 14.55, 14.40, 14.58,
+		14.88, 15.51, 15.47, 13.20, 10.57, 9.18, 8.25, 7.57, 7.03,
+		6.35, 5.72, 5.25, 4.80, 4.29, 3.80, 3.43, 3.12, 2.86,
+		2.64, 2.43, 2.26, 2.14, 2.02, 1.83, 1.61, 1.38, 1.12,
+	],
+	// F10
+	[
+		1.11, 0.63, 0.62, 0.57, 1.48, 12.16, 2.12, 2.70, 3.74,
+		5.14, 6.75, 34.39, 14.86, 10.40, 10.76, 10.67, 10.11, 9.27,
+		8.29, 7.29, 7.91, 16.64, 16.73, 10.44, 5.94, 3.34, 2.35,
+		1.88, 1.59, 1.47, 1.80, 5.71, 40.98, 73.69, 33.61, 8.24,
+		3.38, 2.47, 2.14, 4.86, 11.45, 14.79, 12.16, 8.97, 6.52,
+		8.31, 44.12, 34.55, 12.09, 12.15, 10.52, 4.43, 1.95, 2.19,
+		3.19, 2.77, 2.29, 2.00, 1.52, 1.35, 1.47, 1.79, 1.74,
+		1.02, 1.14, 3.32, 4.49, 2.05, 0.49, 0.24, 0.21, 0.21,
+		0.24, 0.24, 0.21, 0.17, 0.21, 0.22, 0.17, 0.12, 0.09,
+	],
+	// F11
+	[
+		0.91, 0.63, 0.46, 0.37, 1.29, 12.68, 1.59, 1.79, 2.46,
+		3.33, 4.49, 33.94, 12.13, 6.95, 7.19, 7.12, 6.72, 6.13,
+		5.46, 4.79, 5.66, 14.29, 14.96, 8.97, 4.72, 2.33, 1.47,
+		1.10, 0.89, 0.83, 1.18, 4.90, 39.59, 72.84, 32.61, 7.52,
+		2.83, 1.96, 1.67, 4.43, 11.28, 14.76, 12.73, 9.74, 7.33,
+		9.72, 55.27, 42.58, 13.18, 13.16, 12.26, 5.11, 2.07, 2.34,
+		3.58, 3.01, 2.48, 2.14, 1.54, 1.33, 1.46, 1.94, 2.00,
+		1.20, 1.35, 4.10, 5.58, 2.51, 0.57, 0.27, 0.23, 0.21,
+		0.24, 0.24, 0.20, 0.24, 0.32, 0.26, 0.16, 0.12, 0.09,
+	],
+	// F12
+	[
+		0.96, 0.64, 0.40, 0.33, 1.19, 12.48, 1.12, 0.94, 1.08,
+		1.37, 1.78, 29.05, 7.90, 2.65, 2.71, 2.65, 2.49, 2.33,
+		2.10, 1.91, 3.01, 10.83, 11.88, 6.88, 3.43, 1.49, 0.92,
+		0.71, 0.60, 0.63, 1.10, 4.56, 34.40, 65.40, 29.48, 7.16,
+		3.08, 2.47, 2.27, 5.09, 11.96, 15.32, 14.27, 11.86, 9.28,
+		12.31, 68.53, 53.02, 14.67, 14.38, 14.71, 6.46, 2.57, 2.75,
+		4.18, 3.44, 2.81, 2.42, 1.64, 1.36, 1.49, 2.14, 2.34,
+		1.42, 1.61, 5.04, 6.98, 3.19, 0.71, 0.30, 0.26, 0.23,
+		0.28, 0.28, 0.21, 0.17, 0.21, 0.19, 0.15, 0.10, 0.05,
+	],
+];
+
+// spectral reflectance of X-Rite ColorChecker Classic patches, row by row,
+// 380 - 730 nm in 10 nm steps; average of measured charts published by BabelColor
+// source: https://www.babelcolor.com/index_htm_files/ColorChecker_RGB_and_spectra.zip
+pub const COLOR_CHECKER_LAMBDA_MIN: algebra::Scalar = 380.0e-9;
+pub const COLOR_CHECKER_LAMBDA_MAX: algebra::Scalar = 730.0e-9;
+#[allow(clippy::approx_constant)]
+pub const COLOR_CHECKER_REFLECTANCE: [[algebra::Scalar; 36]; 24] = [
+	// Dark Skin
+	[
+		0.055, 0.058, 0.061, 0.062, 0.062, 0.062, 0.062, 0.062, 0.062,
+		0.062, 0.062, 0.063, 0.065, 0.070, 0.076, 0.079, 0.081, 0.084,
+		0.091, 0.103, 0.119, 0.134, 0.143, 0.147, 0.151, 0.158, 0.168,
+		0.179, 0.188, 0.190, 0.186, 0.181, 0.182, 0.187, 0.196, 0.209,
+	],
+	// Light Skin
+	[
+		0.117, 0.143, 0.175, 0.191, 0.196, 0.199, 0.204, 0.213, 0.228,
+		0.251, 0.280, 0.309, 0.329, 0.333, 0.315, 0.286, 0.273, 0.276,
+		0.277, 0.289, 0.339, 0.420, 0.488, 0.525, 0.546, 0.562, 0.578,
+		0.595, 0.612, 0.625, 0.638, 0.656, 0.678, 0.700, 0.717, 0.734,
+	],
+	// Blue Sky
+	[
+		0.130, 0.177, 0.251, 0.306, 0.324, 0.330, 0.333, 0.331, 0.323,
+		0.311, 0.298, 0.285, 0.269, 0.250, 0.231, 0.214, 0.199, 0.185,
+		0.169, 0.157, 0.149, 0.145, 0.142, 0.141, 0.141, 0.141, 0.143,
+		0.147, 0.152, 0.158, 0.165, 0.177, 0.191, 0.207, 0.226, 0.248,
+	],
+	// Foliage
+	[
+		0.051, 0.054, 0.056, 0.057, 0.058, 0.059, 0.060, 0.061, 0.062,
+		0.063, 0.065, 0.067, 0.075, 0.101, 0.145, 0.178, 0.184, 0.170,
+		0.149, 0.133, 0.122, 0.115, 0.109, 0.105, 0.104, 0.106, 0.109,
+		0.112, 0.114, 0.114, 0.112, 0.112, 0.115, 0.120, 0.125, 0.130,
+	],
+	// Blue Flower
+	[
+		0.144, 0.198, 0.294, 0.375, 0.408, 0.421, 0.426, 0.426, 0.419,
+		0.403, 0.379, 0.346, 0.311, 0.281, 0.254, 0.229, 0.214, 0.208,
+		0.202, 0.194, 0.193, 0.200, 0.214, 0.230, 0.241, 0.254, 0.279,
+		0.313, 0.348, 0.366, 0.366, 0.359, 0.358, 0.365, 0.377, 0.398,
+	],
+	// Bluish Green
+	[
+		0.136, 0.179, 0.247, 0.297, 0.320, 0.337, 0.355, 0.381, 0.419,
+		0.466, 0.510, 0.546, 0.567, 0.574, 0.569, 0.551, 0.524, 0.488,
+		0.445, 0.400, 0.350, 0.299, 0.252, 0.221, 0.204, 0.196, 0.191,
+		0.188, 0.191, 0.199, 0.212, 0.223, 0.232, 0.233, 0.229, 0.227,
+	],
+	// Orange
+	[
+		0.054, 0.054, 0.053, 0.054, 0.054, 0.055, 0.055, 0.055, 0.056,
+		0.057, 0.058, 0.061, 0.068, 0.089, 0.125, 0.154, 0.174, 0.199,
+		0.248, 0.335, 0.444, 0.538, 0.587, 0.595, 0.591, 0.587, 0.584,
+		0.584, 0.590, 0.603, 0.620, 0.639, 0.655, 0.663, 0.663, 0.667,
+	],
+	// Purplish Blue
+	[
+		0.122, 0.164, 0.229, 0.286, 0.327, 0.361, 0.388, 0.400, 0.392,
+		0.362, 0.316, 0.260, 0.209, 0.168, 0.138, 0.117, 0.104, 0.096,
+		0.090, 0.086, 0.084, 0.084, 0.084, 0.083, 0.083, 0.085, 0.090,
+		0.098, 0.109, 0.123, 0.143, 0.169, 0.205, 0.244, 0.287, 0.332,
+	],
+	// Moderate Red
+	[
+		0.096, 0.115, 0.131, 0.135, 0.133, 0.132, 0.130, 0.128, 0.125,
+		0.120, 0.115, 0.110, 0.105, 0.100, 0.095, 0.093, 0.092, 0.093,
+		0.096, 0.108, 0.156, 0.265, 0.399, 0.500, 0.556, 0.579, 0.588,
+		0.591, 0.593, 0.594, 0.598, 0.602, 0.607, 0.609, 0.609, 0.610,
+	],
+	// Purple
+	[
+		0.092, 0.116, 0.146, 0.169, 0.178, 0.173, 0.158, 0.139, 0.119,
+		0.101, 0.087, 0.075, 0.066, 0.060, 0.056, 0.053, 0.051, 0.051,
+		0.052, 0.052, 0.051, 0.052, 0.058, 0.073, 0.096, 0.119, 0.141,
+		0.166, 0.194, 0.227, 0.265, 0.309, 0.355, 0.396, 0.436, 0.478,
+	],
+	// Yellow Green
+	[
+		0.061, 0.061, 0.062, 0.063, 0.064, 0.066, 0.069, 0.075, 0.085,
+		0.105, 0.139, 0.192, 0.271, 0.376, 0.476, 0.531, 0.549, 0.546,
+		0.528, 0.504, 0.471, 0.428, 0.381, 0.347, 0.327, 0.318, 0.312,
+		0.310, 0.314, 0.327, 0.345, 0.363, 0.376, 0.381, 0.378, 0.379,
+	],
+	// Orange Yellow
+	[
+		0.063, 0.063, 0.063, 0.064, 0.064, 0.064, 0.065, 0.066, 0.067,
+		0.068, 0.071, 0.076, 0.087, 0.125, 0.206, 0.305, 0.383, 0.431,
+		0.469, 0.518, 0.568, 0.607, 0.628, 0.637, 0.640, 0.642, 0.645,
+		0.648, 0.651, 0.653, 0.657, 0.664, 0.673, 0.680, 0.684, 0.688,
+	],
+	// Blue
+	[
+		0.066, 0.079, 0.102, 0.146, 0.200, 0.244, 0.282, 0.309, 0.308,
+		0.278, 0.231, 0.178, 0.130, 0.094, 0.070, 0.054, 0.046, 0.042,
+		0.039, 0.038, 0.038, 0.038, 0.038, 0.039, 0.039, 0.040, 0.041,
+		0.042, 0.044, 0.045, 0.046, 0.046, 0.048, 0.052, 0.057, 0.065,
+	],
+	// Green
+	[
+		0.052, 0.053, 0.054, 0.055, 0.057, 0.059, 0.061, 0.066, 0.075,
+		0.093, 0.125, 0.178, 0.246, 0.307, 0.337, 0.334, 0.317, 0.293,
+		0.262, 0.230, 0.198, 0.165, 0.135, 0.115, 0.104, 0.098, 0.094,
+		0.092, 0.093, 0.097, 0.102, 0.108, 0.113, 0.115, 0.114, 0.114,
+	],
+	// Red
+	[
+		0.050, 0.049, 0.048, 0.047, 0.047, 0.047, 0.047, 0.047, 0.046,
+		0.045, 0.044, 0.044, 0.045, 0.046, 0.047, 0.048, 0.049, 0.050,
+		0.054, 0.060, 0.072, 0.104, 0.178, 0.312, 0.467, 0.581, 0.644,
+		0.675, 0.690, 0.698, 0.706, 0.715, 0.724, 0.730, 0.734, 0.738,
+	],
+	// Yellow
+	[
+		0.058, 0.054, 0.052, 0.052, 0.053, 0.054, 0.056, 0.059, 0.067,
+		0.081, 0.107, 0.152, 0.225, 0.336, 0.462, 0.559, 0.616, 0.650,
+		0.672, 0.694, 0.710, 0.723, 0.731, 0.739, 0.746, 0.752, 0.758,
+		0.764, 0.769, 0.771, 0.776, 0.782, 0.790, 0.796, 0.799, 0.804,
+	],
+	// Magenta
+	[
+		0.145, 0.195, 0.283, 0.346, 0.362, 0.354, 0.334, 0.306, 0.276,
+		0.248, 0.218, 0.190, 0.168, 0.149, 0.127, 0.107, 0.100, 0.102,
+		0.104, 0.109, 0.137, 0.200, 0.290, 0.400, 0.516, 0.615, 0.687,
+		0.732, 0.760, 0.774, 0.783, 0.793, 0.803, 0.812, 0.817, 0.825,
+	],
+	// Cyan
+	[
+		0.108, 0.141, 0.192, 0.236, 0.261, 0.286, 0.317, 0.353, 0.390,
+		0.426, 0.446, 0.444, 0.423, 0.385, 0.337, 0.283, 0.231, 0.185,
+		0.146, 0.118, 0.101, 0.090, 0.082, 0.076, 0.074, 0.073, 0.073,
+		0.074, 0.076, 0.077, 0.076, 0.075, 0.074, 0.073, 0.072, 0.072,
+	],
+	// White
+	[
+		0.189, 0.255, 0.423, 0.660, 0.811, 0.862, 0.877, 0.884, 0.891,
+		0.896, 0.899, 0.904, 0.907, 0.909, 0.911, 0.910, 0.911, 0.914,
+		0.913, 0.916, 0.915, 0.916, 0.914, 0.915, 0.918, 0.919, 0.921,
+		0.923, 0.924, 0.922, 0.922, 0.925, 0.927, 0.930, 0.930, 0.933,
+	],
+	// Neutral 8
+	[
+		0.171, 0.232, 0.365, 0.507, 0.567, 0.583, 0.588, 0.590, 0.591,
+		0.590, 0.588, 0.588, 0.589, 0.589, 0.591, 0.590, 0.590, 0.590,
+		0.589, 0.591, 0.590, 0.590, 0.587, 0.585, 0.583, 0.580, 0.578,
+		0.576, 0.574, 0.572, 0.571, 0.569, 0.568, 0.568, 0.566, 0.566,
+	],
+	// Neutral 6.5
+	[
+		0.144, 0.192, 0.272, 0.331, 0.350, 0.357, 0.361, 0.363, 0.363,
+		0.361, 0.359, 0.358, 0.358, 0.359, 0.360, 0.360, 0.361, 0.361,
+		0.360, 0.360, 0.359, 0.358, 0.355, 0.352, 0.350, 0.348, 0.345,
+		0.343, 0.340, 0.338, 0.335, 0.334, 0.332, 0.331, 0.331, 0.330,
+	],
+	// Neutral 5
+	[
+		0.105, 0.131, 0.163, 0.180, 0.186, 0.190, 0.193, 0.194, 0.194,
+		0.192, 0.191, 0.191, 0.191, 0.192, 0.192, 0.192, 0.192, 0.192,
+		0.192, 0.193, 0.192, 0.192, 0.191, 0.189, 0.188, 0.186, 0.184,
+		0.182, 0.181, 0.179, 0.178, 0.176, 0.174, 0.173, 0.172, 0.171,
+	],
+	// Neutral 3.5
+	[
+		0.068, 0.077, 0.084, 0.087, 0.089, 0.090, 0.092, 0.092, 0.091,
+		0.090, 0.090, 0.090, 0.090, 0.090, 0.090, 0.090, 0.090, 0.090,
+		0.090, 0.090, 0.090, 0.089, 0.089, 0.088, 0.087, 0.086, 0.086,
+		0.085, 0.084, 0.084, 0.083, 0.083, 0.082, 0.081, 0.081, 0.081,
+	],
+	// Black
+	[
+		0.031, 0.032, 0.032, 0.033, 0.033, 0.033, 0.033, 0.033, 0.032,
+		0.032, 0.032, 0.032, 0.032, 0.032, 0.032, 0.032, 0.032, 0.032,
+		0.032, 0.032, 0.032, 0.032, 0.032, 0.032, 0.032, 0.032, 0.032,
+		0.032, 0.032, 0.032, 0.032, 0.032, 0.032, 0.032, 0.032, 0.032,
+	],
+];
+
+// complex index of refraction of metals as (wavelength in nm, n, k), sorted by wavelength;
+// values are held constant outside of tabulated range
+
+// P. B. Johnson, R. W. Christy, measured at photon energies 1.39 - 3.50 eV
+// source: https://refractiveindex.info/?shelf=main&book=Au&page=Johnson
+pub const CONDUCTOR_AU: [(algebra::Scalar, algebra::Scalar, algebra::Scalar); 18] = [
+	(354.2, 1.50, 1.866),
+	(367.9, 1.48, 1.895),
+	(381.5, 1.46, 1.933),
+	(397.4, 1.47, 1.952),
+	(413.3, 1.46, 1.958),
+	(430.5, 1.45, 1.948),
+	(450.9, 1.38, 1.914),
+	(471.4, 1.31, 1.849),
+	(495.9, 1.04, 1.833),
+	(520.9, 0.62, 2.081),
+	(548.6, 0.43, 2.455),
+	(582.1, 0.29, 2.863),
+	(616.8, 0.21, 3.272),
+	(659.5, 0.14, 3.697),
+	(704.5, 0.13, 4.103),
+	(756.0, 0.14, 4.542),
+	(821.1, 0.16, 5.083),
+	(892.0, 0.17, 5.663),
+];
+// source: https://refractiveindex.info/?shelf=main&book=Ag&page=Johnson
+pub const CONDUCTOR_AG: [(algebra::Scalar, algebra::Scalar, algebra::Scalar); 18] = [
+	(354.2, 0.10, 1.419),
+	(367.9, 0.07, 1.657),
+	(381.5, 0.05, 1.864),
+	(397.4, 0.05, 2.070),
+	(413.3, 0.05, 2.275),
+	(430.5, 0.04, 2.462),
+	(450.9, 0.04, 2.657),
+	(471.4, 0.05, 2.869),
+	(495.9, 0.05, 3.093),
+	(520.9, 0.05, 3.324),
+	(548.6, 0.06, 3.586),
+	(582.1, 0.05, 3.858),
+	(616.8, 0.06, 4.152),
+	(659.5, 0.05, 4.483),
+	(704.5, 0.04, 4.838),
+	(756.0, 0.03, 5.242),
+	(821.1, 0.04, 5.727),
+	(892.0, 0.04, 6.312),
+];
+// source: https://refractiveindex.info/?shelf=main&book=Cu&page=Johnson
+pub const CONDUCTOR_CU: [(algebra::Scalar, algebra::Scalar, algebra::Scalar); 18] = [
+	(354.2, 1.37, 1.899),
+	(367.9, 1.36, 1.954),
+	(381.5, 1.33, 2.034),
+	(397.4, 1.32, 2.145),
+	(413.3, 1.28, 2.222),
+	(430.5, 1.25, 2.305),
+	(450.9, 1.24, 2.397),
+	(471.4, 1.25, 2.483),
+	(495.9, 1.22, 2.564),
+	(520.9, 1.18, 2.608),
+	(548.6, 1.02, 2.577),
+	(582.1, 0.70, 2.704),
+	(616.8, 0.30, 3.205),
+	(659.5, 0.22, 3.747),
+	(704.5, 0.21, 4.205),
+	(756.0, 0.24, 4.665),
+	(821.1, 0.26, 5.180),
+	(892.0, 0.30, 5.768),
+];
+// A. D. Rakić et al., Lorentz-Drude model fitted to measured data, evaluated at the photon
+// energies of the Johnson-Christy tables above
+// source: https://doi.org/10.1364/AO.37.005271
+pub const CONDUCTOR_AL: [(algebra::Scalar, algebra::Scalar, algebra::Scalar); 18] = [
+	(354.2, 0.352, 4.148),
+	(367.9, 0.381, 4.315),
+	(381.5, 0.410, 4.481),
+	(397.4, 0.447, 4.675),
+	(413.3, 0.487, 4.868),
+	(430.5, 0.533, 5.076),
+	(450.9, 0.592, 5.321),
+	(471.4, 0.659, 5.566),
+	(495.9, 0.746, 5.853),
+	(520.9, 0.843, 6.138),
+	(548.6, 0.960, 6.443),
+	(582.1, 1.108, 6.795),
+	(616.8, 1.266, 7.143),
+	(659.5, 1.466, 7.560),
+	(704.5, 1.724, 8.005),
+	(756.0, 2.211, 8.417),
+	(821.1, 2.708, 8.159),
+	(892.0, 2.086, 8.150),
+];
+// source: https://doi.org/10.1364/AO.37.005271
+pub const CONDUCTOR_CR: [(algebra::Scalar, algebra::Scalar, algebra::Scalar); 18] = [
+	(354.2, 1.435, 3.205),
+	(367.9, 1.530, 3.318),
+	(381.5, 1.628, 3.424),
+	(397.4, 1.744, 3.540),
+	(413.3, 1.863, 3.647),
+	(430.5, 1.994, 3.754),
+	(450.9, 2.152, 3.867),
+	(471.4, 2.311, 3.967),
+	(495.9, 2.499, 4.069),
+	(520.9, 2.688, 4.154),
+	(548.6, 2.891, 4.228),
+	(582.1, 3.122, 4.291),
+	(616.8, 3.344, 4.330),
+	(659.5, 3.586, 4.350),
+	(704.5, 3.804, 4.347),
+	(756.0, 4.007, 4.327),
+	(821.1, 4.199, 4.292),
+	(892.0, 4.341, 4.259),
+];
+// coarse values rounded to 400 - 700 nm in 50 nm steps from P. B. Johnson, R. W. Christy
+// source: https://refractiveindex.info/?shelf=main&book=Ti&page=Johnson
+pub const CONDUCTOR_TI: [(algebra::Scalar, algebra::Scalar, algebra::Scalar); 7] = [
+	(400.0, 1.88, 2.68),
+	(450.0, 1.97, 2.82),
+	(500.0, 2.08, 2.95),
+	(550.0, 2.16, 3.01),
+	(600.0, 2.22, 3.05),
+	(650.0, 2.36, 3.18),
+	(700.0, 2.54, 3.43),
+];
+// source: https://refractiveindex.info/?shelf=main&book=Fe&page=Johnson
+pub const CONDUCTOR_FE: [(algebra::Scalar, algebra::Scalar, algebra::Scalar); 7] = [
+	(400.0, 2.04, 2.83),
+	(450.0, 2.47, 2.86),
+	(500.0, 2.80, 2.95),
+	(550.0, 2.90, 3.00),
+	(600.0, 2.92, 3.05),
+	(650.0, 2.92, 3.17),
+	(700.0, 2.91, 3.33),
+];
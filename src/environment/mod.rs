@@ -0,0 +1,603 @@
+use crate::algebra;
+use crate::constants;
+use crate::sampler;
+use crate::spectrum;
+use crate::upsampling;
+use std::io;
+use std::path;
+use std::sync;
+
+type RgbPixel = (algebra::Scalar, algebra::Scalar, algebra::Scalar);
+
+// equirectangular environment map; z axis points up, upper image row is the zenith
+// and `rotation` (in radians) turns the map counterclockwise around the z axis
+pub struct EnvironmentMap {
+	pub width: usize,
+	pub height: usize,
+	pub rotation: algebra::Scalar,
+	pixels: Vec<RgbPixel>,
+	distribution: sampler::Distribution2D,
+}
+
+impl EnvironmentMap {
+	pub fn new(
+		width: usize,
+		height: usize,
+		pixels: Vec<RgbPixel>,
+		rotation: algebra::Scalar,
+	) -> EnvironmentMap {
+		if width == 0 || height == 0 {
+			panic!(
+				"EnvironmentMap dimensions must be greater than 0, got {}x{}",
+				width, height
+			);
+		}
+		if pixels.len() != width * height {
+			panic!(
+				"EnvironmentMap of size {}x{} expected {} pixels, got {}",
+				width,
+				height,
+				width * height,
+				pixels.len()
+			);
+		}
+		// luminance weighted by solid angle covered by each row
+		let mut weights: Vec<algebra::Scalar> = Vec::with_capacity(width * height);
+		for j in 0..height {
+			let sin_theta =
+				((j as algebra::Scalar + 0.5) / height as algebra::Scalar * constants::PI).sin();
+			for i in 0..width {
+				let (r, g, b) = pixels[j * width + i];
+				weights.push((0.2126 * r + 0.7152 * g + 0.0722 * b).max(0.0) * sin_theta);
+			}
+		}
+		EnvironmentMap {
+			width,
+			height,
+			rotation,
+			pixels,
+			distribution: sampler::Distribution2D::new(&weights, width, height),
+		}
+	}
+
+	pub fn from_hdr_file<P: AsRef<path::Path>>(
+		path: P,
+		rotation: algebra::Scalar,
+	) -> io::Result<EnvironmentMap> {
+		let bytes = std::fs::read(path)?;
+		EnvironmentMap::from_hdr_bytes(&bytes, rotation)
+	}
+
+	// reads Radiance RGBE (*.hdr) image, both flat and run-length encoded
+	// source: https://www.graphics.cornell.edu/~bjw/rgbe.html
+	pub fn from_hdr_bytes(bytes: &[u8], rotation: algebra::Scalar) -> io::Result<EnvironmentMap> {
+		let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+		let mut pos: usize = 0;
+		let next_line = |pos: &mut usize| -> io::Result<String> {
+			let start = *pos;
+			while *pos < bytes.len() && bytes[*pos] != b'\n' {
+				*pos += 1;
+			}
+			if *pos >= bytes.len() {
+				return Err(invalid("unexpected end of HDR header"));
+			}
+			*pos += 1;
+			Ok(String::from_utf8_lossy(&bytes[start..*pos - 1]).into_owned())
+		};
+
+		// header
+		let magic = next_line(&mut pos)?;
+		if !magic.starts_with("#?") {
+			return Err(invalid("missing HDR signature"));
+		}
+		loop {
+			let line = next_line(&mut pos)?;
+			if line.is_empty() {
+				break;
+			}
+			if let Some(format) = line.strip_prefix("FORMAT=") {
+				if format != "32-bit_rle_rgbe" {
+					return Err(invalid("unsupported HDR pixel format"));
+				}
+			}
+		}
+		let resolution = next_line(&mut pos)?;
+		let tokens: Vec<&str> = resolution.split_whitespace().collect();
+		if tokens.len() != 4 || tokens[0] != "-Y" || tokens[2] != "+X" {
+			return Err(invalid("unsupported HDR image orientation"));
+		}
+		let height: usize = tokens[1]
+			.parse()
+			.map_err(|_| invalid("invalid HDR image height"))?;
+		let width: usize = tokens[3]
+			.parse()
+			.map_err(|_| invalid("invalid HDR image width"))?;
+		if width == 0 || height == 0 {
+			return Err(invalid("empty HDR image"));
+		}
+
+		// pixel data
+		let mut pixels: Vec<RgbPixel> = Vec::with_capacity(width * height);
+		let mut scanline: Vec<[u8; 4]> = vec![[0; 4]; width];
+		for _ in 0..height {
+			let rle = (8..32768).contains(&width)
+				&& pos + 4 <= bytes.len()
+				&& bytes[pos] == 2
+				&& bytes[pos + 1] == 2
+				&& bytes[pos + 2] & 0x80 == 0;
+			if rle {
+				if ((bytes[pos + 2] as usize) << 8 | bytes[pos + 3] as usize) != width {
+					return Err(invalid("HDR scanline width mismatch"));
+				}
+				pos += 4;
+				for channel in 0..4 {
+					let mut i: usize = 0;
+					while i < width {
+						let count = *bytes.get(pos).ok_or_else(|| invalid("truncated HDR data"))?;
+						pos += 1;
+						if count > 128 {
+							let run = (count - 128) as usize;
+							let value =
+								*bytes.get(pos).ok_or_else(|| invalid("truncated HDR data"))?;
+							pos += 1;
+							if run == 0 || i + run > width {
+								return Err(invalid("corrupt HDR run length"));
+							}
+							for px in scanline.iter_mut().skip(i).take(run) {
+								px[channel] = value;
+							}
+							i += run;
+						} else {
+							let run = count as usize;
+							if run == 0 || i + run > width || pos + run > bytes.len() {
+								return Err(invalid("corrupt HDR run length"));
+							}
+							for (px, value) in scanline
+								.iter_mut()
+								.skip(i)
+								.take(run)
+								.zip(&bytes[pos..pos + run])
+							{
+								px[channel] = *value;
+							}
+							pos += run;
+							i += run;
+						}
+					}
+				}
+			} else {
+				if pos + 4 * width > bytes.len() {
+					return Err(invalid("truncated HDR data"));
+				}
+				for (i, px) in scanline.iter_mut().enumerate() {
+					px.copy_from_slice(&bytes[pos + 4 * i..pos + 4 * i + 4]);
+				}
+				pos += 4 * width;
+			}
+			for px in &scanline {
+				pixels.push(rgbe_to_rgb(*px));
+			}
+		}
+		Ok(EnvironmentMap::new(width, height, pixels, rotation))
+	}
+
+	pub fn return_rgb(&self, dir: algebra::Vector) -> RgbPixel {
+		let (u, v) = self.direction_to_uv(dir);
+		let i = ((u * self.width as algebra::Scalar) as usize).min(self.width - 1);
+		let j = ((v * self.height as algebra::Scalar) as usize).min(self.height - 1);
+		self.pixels[j * self.width + i]
+	}
+
+	pub fn return_radiance(&self, dir: algebra::Vector, lambda: algebra::Scalar) -> algebra::Scalar {
+		upsampling::linear_rgb_to_spectrum(self.return_rgb(dir), lambda)
+	}
+
+	// importance samples direction proportionally to the luminance of the map;
+	// returns direction and pdf with respect to solid angle
+	pub fn sample_direction(
+		&self,
+		random: (algebra::Scalar, algebra::Scalar),
+	) -> (algebra::Vector, algebra::Scalar) {
+		let ((u, v), pdf_uv) = self.distribution.sample(random);
+		let theta = v * constants::PI;
+		let phi = u * 2.0 * constants::PI + self.rotation;
+		let sin_theta = theta.sin();
+		let dir = algebra::Vector::new(
+			sin_theta * phi.cos(),
+			sin_theta * phi.sin(),
+			theta.cos(),
+		);
+		if sin_theta == 0.0 {
+			return (dir, 0.0);
+		}
+		(
+			dir,
+			pdf_uv / (2.0 * constants::PI * constants::PI * sin_theta),
+		)
+	}
+
+	pub fn pdf(&self, dir: algebra::Vector) -> algebra::Scalar {
+		let (u, v) = self.direction_to_uv(dir);
+		let sin_theta = (v * constants::PI).sin();
+		if sin_theta == 0.0 {
+			return 0.0;
+		}
+		self.distribution.pdf((u, v)) / (2.0 * constants::PI * constants::PI * sin_theta)
+	}
+
+	fn direction_to_uv(&self, dir: algebra::Vector) -> (algebra::Scalar, algebra::Scalar) {
+		let dir = dir.normalize();
+		let theta = dir.z.clamp(-1.0, 1.0).acos();
+		let phi = (dir.y.atan2(dir.x) - self.rotation).rem_euclid(2.0 * constants::PI);
+		(
+			phi / (2.0 * constants::PI),
+			theta * constants::PI_INV,
+		)
+	}
+}
+
+fn rgbe_to_rgb(rgbe: [u8; 4]) -> RgbPixel {
+	if rgbe[3] == 0 {
+		(0.0, 0.0, 0.0)
+	} else {
+		let f = (2.0 as algebra::Scalar).powi(rgbe[3] as i32 - (128 + 8));
+		(
+			rgbe[0] as algebra::Scalar * f,
+			rgbe[1] as algebra::Scalar * f,
+			rgbe[2] as algebra::Scalar * f,
+		)
+	}
+}
+
+// Preetham analytic daylight with solar disk; spectral radiance is scaled so that sky
+// luminance is given in kcd/m^2, spectra are reconstructed with CIE daylight basis
+// source: https://courses.cs.duke.edu/fall01/cps124/resources/p91-preetham.pdf
+pub struct Sky {
+	pub sun_direction: algebra::Vector,
+	pub turbidity: algebra::Scalar,
+	perez_luminance: [algebra::Scalar; 5],
+	perez_x: [algebra::Scalar; 5],
+	perez_y: [algebra::Scalar; 5],
+	// zenith values divided by distribution value at zenith
+	zenith: (algebra::Scalar, algebra::Scalar, algebra::Scalar),
+	// luminance of daylight basis functions
+	basis_luminance: (algebra::Scalar, algebra::Scalar, algebra::Scalar),
+	optical_mass: algebra::Scalar,
+	sun_scale: algebra::Scalar,
+	cos_sun_radius: algebra::Scalar,
+}
+
+impl Sky {
+	// `elevation` is measured from the horizon, `azimuth` counterclockwise from the x axis
+	pub fn new(
+		elevation: algebra::Scalar,
+		azimuth: algebra::Scalar,
+		turbidity: algebra::Scalar,
+	) -> Sky {
+		if !(0.0..=0.5 * constants::PI).contains(&elevation) {
+			panic!(
+				"Sky property `elevation` must be between 0.0 and PI/2, got {}",
+				elevation
+			);
+		}
+		if !(1.7..=10.0).contains(&turbidity) {
+			panic!(
+				"Sky property `turbidity` must be between 1.7 and 10.0, got {}",
+				turbidity
+			);
+		}
+		let theta_s = 0.5 * constants::PI - elevation;
+		let sun_direction = algebra::Vector::new(
+			elevation.cos() * azimuth.cos(),
+			elevation.cos() * azimuth.sin(),
+			elevation.sin(),
+		);
+		let coefficients = |table: &[(algebra::Scalar, algebra::Scalar); 5]| {
+			let mut c = [0.0; 5];
+			for (value, (factor, offset)) in c.iter_mut().zip(table) {
+				*value = factor * turbidity + offset;
+			}
+			c
+		};
+		let perez_luminance = coefficients(&constants::PREETHAM_PEREZ_LUMINANCE);
+		let perez_x = coefficients(&constants::PREETHAM_PEREZ_X);
+		let perez_y = coefficients(&constants::PREETHAM_PEREZ_Y);
+
+		let chi = (4.0 / 9.0 - turbidity / 120.0) * (constants::PI - 2.0 * theta_s);
+		let zenith_luminance =
+			(4.0453 * turbidity - 4.9710) * chi.tan() - 0.2155 * turbidity + 2.4192;
+		let zenith_chromaticity = |m: &[[algebra::Scalar; 4]; 3]| {
+			let t = [turbidity * turbidity, turbidity, 1.0];
+			let th = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.0];
+			let mut value = 0.0;
+			for (i, row) in m.iter().enumerate() {
+				for (j, entry) in row.iter().enumerate() {
+					value += t[i] * entry * th[j];
+				}
+			}
+			value
+		};
+		let zenith = (
+			zenith_luminance / perez(&perez_luminance, 1.0, theta_s),
+			zenith_chromaticity(&constants::PREETHAM_ZENITH_X) / perez(&perez_x, 1.0, theta_s),
+			zenith_chromaticity(&constants::PREETHAM_ZENITH_Y) / perez(&perez_y, 1.0, theta_s),
+		);
+
+		let mut basis_luminance = (0.0, 0.0, 0.0);
+		let mut y_sum = 0.0;
+		for i in 0..constants::CIE_DAYLIGHT_S0.len() {
+			let nm = 300 + 10 * i;
+			if !(360..=830).contains(&nm) {
+				continue;
+			}
+			let y_bar = constants::CIE_XYZ_1931_COLOR_MATCH_2_DEG[nm - 360].1;
+			basis_luminance.0 += constants::CIE_DAYLIGHT_S0[i] * y_bar;
+			basis_luminance.1 += constants::CIE_DAYLIGHT_S1[i] * y_bar;
+			basis_luminance.2 += constants::CIE_DAYLIGHT_S2[i] * y_bar;
+			y_sum += y_bar;
+		}
+		basis_luminance.0 /= y_sum;
+		basis_luminance.1 /= y_sum;
+		basis_luminance.2 /= y_sum;
+
+		// converts blackbody radiance in W/(m^2 sr m) to luminance scale of the sky
+		let y_integral: algebra::Scalar = constants::CIE_XYZ_1931_COLOR_MATCH_2_DEG
+			.iter()
+			.map(|c| c.1)
+			.sum::<algebra::Scalar>()
+			* 1e-9;
+		let sun_scale = constants::LUMINOUS_EFFICACY * y_integral * 1e-3;
+		// relative optical mass of the atmosphere along the sun ray
+		let theta_s_deg = theta_s.to_degrees();
+		let optical_mass = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_s_deg).powf(-1.253));
+
+		Sky {
+			sun_direction,
+			turbidity,
+			perez_luminance,
+			perez_x,
+			perez_y,
+			zenith,
+			basis_luminance,
+			optical_mass,
+			sun_scale,
+			cos_sun_radius: constants::SUN_ANGULAR_RADIUS.cos(),
+		}
+	}
+
+	pub fn return_radiance(&self, dir: algebra::Vector, lambda: algebra::Scalar) -> algebra::Scalar {
+		let dir = dir.normalize();
+		let mut radiance = self.sky_radiance(dir, lambda);
+		if dir * self.sun_direction >= self.cos_sun_radius {
+			radiance += self.sun_radiance(lambda);
+		}
+		radiance
+	}
+
+	// luminance and chromaticity of the sky in given direction
+	pub fn return_yxy(
+		&self,
+		dir: algebra::Vector,
+	) -> (algebra::Scalar, algebra::Scalar, algebra::Scalar) {
+		let cos_theta = dir.z.max(0.0);
+		let cos_gamma = (dir * self.sun_direction).clamp(-1.0, 1.0);
+		let gamma = cos_gamma.acos();
+		(
+			self.zenith.0 * perez(&self.perez_luminance, cos_theta, gamma),
+			self.zenith.1 * perez(&self.perez_x, cos_theta, gamma),
+			self.zenith.2 * perez(&self.perez_y, cos_theta, gamma),
+		)
+	}
+
+	fn sky_radiance(&self, dir: algebra::Vector, lambda: algebra::Scalar) -> algebra::Scalar {
+		let (luminance, x, y) = self.return_yxy(dir);
+		let (m1, m2) = daylight_coefficients(x, y);
+		let relative_luminance =
+			self.basis_luminance.0 + m1 * self.basis_luminance.1 + m2 * self.basis_luminance.2;
+		(daylight_spd(m1, m2, lambda) * luminance / relative_luminance).max(0.0)
+	}
+
+	// extraterrestrial sun approximated by blackbody, attenuated by Rayleigh
+	// and aerosol scattering; ozone and water vapour absorption are ignored
+	fn sun_radiance(&self, lambda: algebra::Scalar) -> algebra::Scalar {
+		let lambda_um = lambda * 1e6;
+		let beta = 0.04608 * self.turbidity - 0.04586;
+		let tau_rayleigh = (-0.008735 * lambda_um.powf(-4.08) * self.optical_mass).exp();
+		let tau_aerosol = (-beta * lambda_um.powf(-1.3) * self.optical_mass).exp();
+		spectrum::Spectrum::BlackBody(constants::SUN_TEMPERATURE).evaluate(lambda)
+			* tau_rayleigh
+			* tau_aerosol
+			* self.sun_scale
+	}
+
+	// samples solar disk uniformly; the rest of the sky is left to BSDF sampling
+	pub fn sample_direction(
+		&self,
+		random: (algebra::Scalar, algebra::Scalar),
+	) -> (algebra::Vector, algebra::Scalar) {
+		let cos_theta = 1.0 - random.0 * (1.0 - self.cos_sun_radius);
+		let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+		let phi = 2.0 * constants::PI * random.1;
+		let w = self.sun_direction;
+		let a = if w.x.abs() > 0.9 {
+			algebra::Vector::new(0.0, 1.0, 0.0)
+		} else {
+			algebra::Vector::new(1.0, 0.0, 0.0)
+		};
+		let u = (w % a).normalize();
+		let v = w % u;
+		let basis = algebra::Basis::new(u, v, w);
+		(
+			basis.basis_to_world(algebra::Vector::new(
+				sin_theta * phi.cos(),
+				sin_theta * phi.sin(),
+				cos_theta,
+			)),
+			self.sun_pdf(),
+		)
+	}
+
+	pub fn pdf(&self, dir: algebra::Vector) -> algebra::Scalar {
+		if dir.normalize() * self.sun_direction >= self.cos_sun_radius {
+			self.sun_pdf()
+		} else {
+			0.0
+		}
+	}
+
+	fn sun_pdf(&self) -> algebra::Scalar {
+		1.0 / (2.0 * constants::PI * (1.0 - self.cos_sun_radius))
+	}
+}
+
+// Perez sky distribution function
+fn perez(
+	c: &[algebra::Scalar; 5],
+	cos_theta: algebra::Scalar,
+	gamma: algebra::Scalar,
+) -> algebra::Scalar {
+	(1.0 + c[0] * (c[1] / cos_theta.max(1e-4)).exp())
+		* (1.0 + c[2] * (c[3] * gamma).exp() + c[4] * gamma.cos().powi(2))
+}
+
+// CIE standard illuminants
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Illuminant {
+	A,
+	D50,
+	D65,
+	E,
+	F1,
+	F2,
+	F3,
+	F4,
+	F5,
+	F6,
+	F7,
+	F8,
+	F9,
+	F10,
+	F11,
+	F12,
+}
+
+impl Illuminant {
+	pub const ALL: [Illuminant; 16] = [
+		Illuminant::A,
+		Illuminant::D50,
+		Illuminant::D65,
+		Illuminant::E,
+		Illuminant::F1,
+		Illuminant::F2,
+		Illuminant::F3,
+		Illuminant::F4,
+		Illuminant::F5,
+		Illuminant::F6,
+		Illuminant::F7,
+		Illuminant::F8,
+		Illuminant::F9,
+		Illuminant::F10,
+		Illuminant::F11,
+		Illuminant::F12,
+	];
+
+	// relative spectral power distribution; A, D and E series equal 100 at 560 nm
+	// source: https://cie.co.at/publications/colorimetry-part-2-cie-standard-illuminants-0
+	pub fn return_spd(&self, lambda: algebra::Scalar) -> algebra::Scalar {
+		match self {
+			Illuminant::A => {
+				// radiation constant as fixed by the definition of the illuminant
+				let c2: algebra::Scalar = 1.435e-2;
+				let temperature: algebra::Scalar = 2848.0;
+				let l560: algebra::Scalar = 560.0e-9;
+				100.0 * (l560 / lambda).powi(5) * ((c2 / (temperature * l560)).exp() - 1.0)
+					/ ((c2 / (temperature * lambda)).exp() - 1.0)
+			}
+			Illuminant::D50 => {
+				let (m1, m2) = daylight_coefficients(0.34567, 0.35850);
+				daylight_spd(m1, m2, lambda)
+			}
+			Illuminant::D65 => {
+				let (m1, m2) = daylight_coefficients(0.31271, 0.32902);
+				daylight_spd(m1, m2, lambda)
+			}
+			Illuminant::E => 100.0,
+			fluorescent => interpolate_table(
+				&constants::CIE_ILLUMINANT_F[*fluorescent as usize - Illuminant::F1 as usize],
+				380.0,
+				5.0,
+				lambda,
+			),
+		}
+	}
+
+	// spectral radiance of the illuminant scaled to given luminance
+	pub fn return_radiance(
+		&self,
+		lambda: algebra::Scalar,
+		luminance: algebra::Scalar,
+	) -> algebra::Scalar {
+		self.return_spd(lambda) / self.spd_luminance() * luminance
+	}
+
+	// luminance of the relative SPD: its 1 nm sum weighted by CIE 1931 y bar
+	// and divided by sum of y bar; computed once for all illuminants
+	pub fn spd_luminance(&self) -> algebra::Scalar {
+		static LUMINANCE: sync::OnceLock<Vec<algebra::Scalar>> = sync::OnceLock::new();
+		LUMINANCE.get_or_init(|| {
+			let cmf = &constants::CIE_XYZ_1931_COLOR_MATCH_2_DEG;
+			let y_sum: algebra::Scalar = cmf.iter().map(|c| c.1).sum();
+			Illuminant::ALL
+				.iter()
+				.map(|illuminant| {
+					cmf.iter()
+						.enumerate()
+						.map(|(i, c)| {
+							illuminant.return_spd((360.0 + i as algebra::Scalar) * 1e-9) * c.1
+						})
+						.sum::<algebra::Scalar>()
+						/ y_sum
+				})
+				.collect()
+		})[*self as usize]
+	}
+}
+
+// daylight basis weights for given chromaticity
+fn daylight_coefficients(
+	x: algebra::Scalar,
+	y: algebra::Scalar,
+) -> (algebra::Scalar, algebra::Scalar) {
+	let denom = 0.0241 + 0.2562 * x - 0.7341 * y;
+	(
+		(-1.3515 - 1.7703 * x + 5.9114 * y) / denom,
+		(0.0300 - 31.4424 * x + 30.0717 * y) / denom,
+	)
+}
+
+fn daylight_spd(m1: algebra::Scalar, m2: algebra::Scalar, lambda: algebra::Scalar) -> algebra::Scalar {
+	daylight_basis(&constants::CIE_DAYLIGHT_S0, lambda)
+		+ m1 * daylight_basis(&constants::CIE_DAYLIGHT_S1, lambda)
+		+ m2 * daylight_basis(&constants::CIE_DAYLIGHT_S2, lambda)
+}
+
+// linearly interpolated daylight basis function
+fn daylight_basis(table: &[algebra::Scalar], lambda: algebra::Scalar) -> algebra::Scalar {
+	interpolate_table(table, 300.0, 10.0, lambda)
+}
+
+// linearly interpolates table sampled in equal steps (given in nm), zero outside of its range
+fn interpolate_table(
+	table: &[algebra::Scalar],
+	lambda_min: algebra::Scalar,
+	step: algebra::Scalar,
+	lambda: algebra::Scalar,
+) -> algebra::Scalar {
+	let x = (lambda * 1e9 - lambda_min) / step;
+	if x < 0.0 || x > (table.len() - 1) as algebra::Scalar {
+		return 0.0;
+	}
+	let i = (x as usize).min(table.len() - 2);
+	let t = x - i as algebra::Scalar;
+	table[i] * (1.0 - t) + table[i + 1] * t
+}
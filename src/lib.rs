@@ -1,7 +1,9 @@
 pub mod algebra;
 pub mod camera;
 pub mod constants;
+pub mod environment;
 pub mod materials;
+pub mod media;
 pub mod output;
 pub mod primitives;
 pub mod ray;
@@ -9,3 +11,5 @@ pub mod renderer;
 pub mod sampler;
 pub mod scene;
 pub mod shaders;
+pub mod spectrum;
+pub mod upsampling;
@@ -1,7 +1,9 @@
 use crate::algebra;
 use crate::constants;
+use crate::media;
 use crate::ray;
 use crate::shaders;
+use crate::spectrum;
 
 #[derive(Clone, PartialEq)]
 pub enum EmissionType {
@@ -14,104 +16,685 @@ pub enum EmissionType {
 		power: algebra::Scalar,
 	},
 	Fresnel,
+	// arbitrary emission spectrum scaled by `power`
+	Spectral {
+		spectrum: spectrum::Spectrum,
+		power: algebra::Scalar,
+	},
 }
 
+// `anisotropy` between -1.0 and 1.0 stretches glossy reflection along tangent of the surface
+// for positive values and across it for negative ones, `rotation` turns the tangent around
+// the normal by given angle in radians; optional `film` covers the surface with thin layer
+// producing interference colors
 #[derive(Clone, PartialEq)]
 pub enum SurfaceType {
 	DielectricOpaque {
-		color: shaders::Color,
+		color: spectrum::Spectrum,
 		roughness: algebra::Scalar,
+		anisotropy: algebra::Scalar,
+		rotation: algebra::Scalar,
 	},
 	DielectricTransparent {
 		roughness: algebra::Scalar,
+		film: std::option::Option<shaders::ThinFilm>,
 	},
 	Conductor {
 		roughness: algebra::Scalar,
+		anisotropy: algebra::Scalar,
+		rotation: algebra::Scalar,
+		film: std::option::Option<shaders::ThinFilm>,
+	},
+	// dielectric boundary enclosing random walk through scattering interior; `color` is
+	// the resulting diffuse albedo and `mean_free_path` is given in meters
+	Subsurface {
+		color: spectrum::Spectrum,
+		mean_free_path: spectrum::Spectrum,
+		roughness: algebra::Scalar,
+	},
+	// clear coat with index of refraction of the material over `base`; `thickness` is
+	// given in meters and `absorption` coefficient of the coat per meter
+	Coated {
+		base: CoatBase,
+		roughness: algebra::Scalar,
+		thickness: algebra::Scalar,
+		absorption: spectrum::Spectrum,
+	},
+	// uber material with parameters of physically based shaders of content creation tools,
+	// blending metal, glass, subsurface scattering and plastic with optional sheen and clear
+	// coat of index of refraction 1.5; weights are between 0.0 and 1.0, index of refraction
+	// of the material is used for its dielectric parts and `specular` of 0.5 leaves their
+	// reflectance as is, other values scale reflectance at normal incidence of the plastic
+	// through its index of refraction, `specular_tint` colors it by `base_color`;
+	// `subsurface_radius` is mean free path in meters of the interior, which glass shares if
+	// both are present
+	// source: https://media.disneyanimation.com/uploads/production/publication_asset/48/asset/s2012_pbs_disney_brdf_notes_v3.pdf
+	Principled {
+		base_color: spectrum::Spectrum,
+		metallic: algebra::Scalar,
+		roughness: algebra::Scalar,
+		specular: algebra::Scalar,
+		specular_tint: algebra::Scalar,
+		sheen: algebra::Scalar,
+		clearcoat: algebra::Scalar,
+		clearcoat_roughness: algebra::Scalar,
+		transmission: algebra::Scalar,
+		subsurface: algebra::Scalar,
+		subsurface_radius: spectrum::Spectrum,
 	},
 }
 
+// surface under clear coat of `SurfaceType::Coated`
 #[derive(Clone, PartialEq)]
-enum InternalType {
-	DielOpaq,
-	DielTrs,
-	Cond,
+pub enum CoatBase {
+	Diffuse {
+		color: spectrum::Spectrum,
+		roughness: algebra::Scalar,
+	},
+	// `n` and `k` form complex index of refraction of the metal
+	Conductor {
+		n: Ior,
+		k: Ior,
+		roughness: algebra::Scalar,
+	},
 }
 
-#[derive(Clone, PartialEq)]
+// index of refraction, evaluated at wavelength in meters
+// source: https://refractiveindex.info
+#[derive(Clone, PartialEq, Debug)]
+pub enum Ior {
+	Constant(algebra::Scalar),
+	// n = a + b / lambda^2 with `b` in square micrometers
+	Cauchy {
+		a: algebra::Scalar,
+		b: algebra::Scalar,
+	},
+	// n^2 = 1 + sum of b * lambda^2 / (lambda^2 - c) over (b, c) terms,
+	// `c` in square micrometers
+	Sellmeier(Vec<(algebra::Scalar, algebra::Scalar)>),
+	// arbitrary spectrum, e.g. measured data
+	Spectral(spectrum::Spectrum),
+}
+
+impl Ior {
+	// Schott N-BK7 borosilicate crown glass
+	pub fn bk7() -> Ior {
+		Ior::Sellmeier(vec![
+			(1.03961212, 0.00600069867),
+			(0.231792344, 0.0200179144),
+			(1.01046945, 103.560653),
+		])
+	}
+
+	// source: I. H. Malitson, https://doi.org/10.1364/JOSA.55.001205
+	pub fn fused_silica() -> Ior {
+		Ior::Sellmeier(vec![
+			(0.6961663, 0.0684043_f64.powi(2)),
+			(0.4079426, 0.1162414_f64.powi(2)),
+			(0.8974794, 9.896161_f64.powi(2)),
+		])
+	}
+
+	// source: H. R. Phillip, E. A. Taft, https://doi.org/10.1103/PhysRev.136.A1445
+	pub fn diamond() -> Ior {
+		Ior::Sellmeier(vec![(0.3306, 0.1750_f64.powi(2)), (4.3356, 0.1060_f64.powi(2))])
+	}
+
+	// water at 20 degrees Celsius
+	// source: M. Daimon, A. Masumura, https://doi.org/10.1364/AO.46.003811
+	pub fn water() -> Ior {
+		Ior::Sellmeier(vec![
+			(5.684027565e-1, 5.101829712e-3),
+			(1.726177391e-1, 1.821153936e-2),
+			(2.086189578e-2, 2.620722293e-2),
+			(1.130748688e-1, 1.069792721e1),
+		])
+	}
+
+	pub fn evaluate(&self, lambda: algebra::Scalar) -> algebra::Scalar {
+		let lambda2 = (lambda * 1e6).powi(2);
+		match self {
+			Ior::Constant(n) => *n,
+			Ior::Cauchy { a, b } => a + b / lambda2,
+			Ior::Sellmeier(terms) => {
+				let n2: algebra::Scalar = 1.0
+					+ terms
+						.iter()
+						.map(|(b, c)| b * lambda2 / (lambda2 - c))
+						.sum::<algebra::Scalar>();
+				n2.max(0.0).sqrt()
+			}
+			Ior::Spectral(spectrum) => spectrum.evaluate(lambda),
+		}
+	}
+
+	pub fn is_dispersive(&self) -> bool {
+		!matches!(self, Ior::Constant(_))
+	}
+}
+
+// metals with tabulated complex index of refraction
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Metal {
+	Gold,
+	Silver,
+	Copper,
+	Aluminium,
+	Chromium,
+	Titanium,
+	Iron,
+}
+
+impl Metal {
+	pub const ALL: [Metal; 7] = [
+		Metal::Gold,
+		Metal::Silver,
+		Metal::Copper,
+		Metal::Aluminium,
+		Metal::Chromium,
+		Metal::Titanium,
+		Metal::Iron,
+	];
+
+	// real and imaginary part of index of refraction, to be passed as `n` and `k`
+	// to `Material::new`
+	pub fn ior(&self) -> (Ior, Ior) {
+		let table: &[(algebra::Scalar, algebra::Scalar, algebra::Scalar)] = match self {
+			Metal::Gold => &constants::CONDUCTOR_AU,
+			Metal::Silver => &constants::CONDUCTOR_AG,
+			Metal::Copper => &constants::CONDUCTOR_CU,
+			Metal::Aluminium => &constants::CONDUCTOR_AL,
+			Metal::Chromium => &constants::CONDUCTOR_CR,
+			Metal::Titanium => &constants::CONDUCTOR_TI,
+			Metal::Iron => &constants::CONDUCTOR_FE,
+		};
+		let n = table.iter().map(|nk| (nk.0 * 1e-9, nk.1)).collect();
+		let k = table.iter().map(|nk| (nk.0 * 1e-9, nk.2)).collect();
+		(
+			Ior::Spectral(spectrum::Spectrum::sampled(n)),
+			Ior::Spectral(spectrum::Spectrum::sampled(k)),
+		)
+	}
+}
+
+// wavelengths in meters of the grid `Fluorescence::dye` tabulates its spectra on
+const DYE_LAMBDA_MIN: algebra::Scalar = 300.0e-9;
+const DYE_LAMBDA_MAX: algebra::Scalar = 800.0e-9;
+const DYE_SAMPLES: usize = 101;
+
+// light absorbed at one wavelength and reradiated diffusely at a longer one, given by Donaldson
+// matrix of bispectral radiance factors on uniform grid of wavelengths, each standing for a bin
+// of the grid spacing around it; ordinary reflection on its diagonal is left to the lobes of
+// the material
+#[derive(Clone, PartialEq, Debug)]
+pub struct Fluorescence {
+	lambda_min: algebra::Scalar,
+	step: algebra::Scalar,
+	// `factors[j][i]` is reradiated at j-th wavelength from i-th one, zero unless j > i
+	factors: Vec<Vec<algebra::Scalar>>,
+	// sums of rows, radiance factor of reradiation under equal energy illumination
+	totals: Vec<algebra::Scalar>,
+}
+
+impl Fluorescence {
+	// `matrix[j][i]` is radiance factor at j-th wavelength due to light at i-th one, out of
+	// wavelengths spaced uniformly from `lambda_min` to `lambda_max` inclusive
+	pub fn donaldson(
+		lambda_min: algebra::Scalar,
+		lambda_max: algebra::Scalar,
+		matrix: Vec<Vec<algebra::Scalar>>,
+	) -> Fluorescence {
+		let n = matrix.len();
+		if n < 2 || matrix.iter().any(|row| row.len() != n) {
+			panic!("Donaldson matrix must be square with at least two wavelengths");
+		}
+		if lambda_min <= 0.0 || lambda_max <= lambda_min {
+			panic!("wavelengths of Donaldson matrix must be positive and increasing");
+		}
+		if matrix.iter().flatten().any(|factor| *factor < 0.0) {
+			panic!("Donaldson matrix must not be negative");
+		}
+		let factors: Vec<Vec<algebra::Scalar>> = matrix
+			.iter()
+			.enumerate()
+			.map(|(j, row)| {
+				let shifted = row.iter().enumerate().map(|(i, factor)| {
+					if j > i {
+						*factor
+					} else {
+						0.0
+					}
+				});
+				shifted.collect()
+			})
+			.collect();
+		let totals = factors.iter().map(|row| row.iter().sum()).collect();
+		Fluorescence {
+			lambda_min,
+			step: (lambda_max - lambda_min) / (n - 1) as algebra::Scalar,
+			factors,
+			totals,
+		}
+	}
+
+	// single dye absorbing fraction `absorption` of light and emitting `quantum_yield` photons
+	// per absorbed one, distributed over wavelengths longer than the absorbed one in proportion
+	// to `emission`
+	pub fn dye(
+		absorption: spectrum::Spectrum,
+		emission: spectrum::Spectrum,
+		quantum_yield: algebra::Scalar,
+	) -> Fluorescence {
+		if !(0.0..=1.0).contains(&quantum_yield) {
+			panic!("quantum yield of dye must be between 0.0 and 1.0");
+		}
+		let step = (DYE_LAMBDA_MAX - DYE_LAMBDA_MIN) / (DYE_SAMPLES - 1) as algebra::Scalar;
+		let lambda = |k: usize| DYE_LAMBDA_MIN + k as algebra::Scalar * step;
+		let shape: Vec<algebra::Scalar> =
+			(0..DYE_SAMPLES).map(|k| emission.evaluate(lambda(k)).max(0.0)).collect();
+		let absorbed: Vec<algebra::Scalar> =
+			(0..DYE_SAMPLES).map(|k| absorption.evaluate(lambda(k)).clamp(0.0, 1.0)).collect();
+		// emission reachable from each excitation sample, the shape summed above it
+		let mut norms = vec![0.0; DYE_SAMPLES];
+		for i in (0..DYE_SAMPLES - 1).rev() {
+			norms[i] = norms[i + 1] + shape[i + 1];
+		}
+		let matrix = (0..DYE_SAMPLES)
+			.map(|j| {
+				(0..DYE_SAMPLES)
+					.map(|i| {
+						if j <= i || norms[i] <= 0.0 {
+							return 0.0;
+						}
+						// photons lose energy on the way to longer wavelength
+						quantum_yield * absorbed[i] * shape[j] / norms[i] * lambda(i) / lambda(j)
+					})
+					.collect()
+			})
+			.collect();
+		Fluorescence::donaldson(DYE_LAMBDA_MIN, DYE_LAMBDA_MAX, matrix)
+	}
+
+	fn index(&self, lambda: algebra::Scalar) -> std::option::Option<usize> {
+		let k = ((lambda - self.lambda_min) / self.step).round();
+		if k < 0.0 || k >= self.totals.len() as algebra::Scalar {
+			return std::option::Option::None;
+		}
+		std::option::Option::Some(k as usize)
+	}
+
+	// radiance factor at wavelength `emission` per unit of `excitation` wavelength
+	pub fn reradiation(
+		&self,
+		excitation: algebra::Scalar,
+		emission: algebra::Scalar,
+	) -> algebra::Scalar {
+		match (self.index(excitation), self.index(emission)) {
+			(std::option::Option::Some(i), std::option::Option::Some(j)) => {
+				self.factors[j][i] / self.step
+			}
+			_ => 0.0,
+		}
+	}
+
+	// radiance factor of reradiation at `emission` under illumination equal at all wavelengths
+	pub fn total(&self, emission: algebra::Scalar) -> algebra::Scalar {
+		self.index(emission).map_or(0.0, |j| self.totals[j])
+	}
+
+	// excitation wavelength reradiated at `emission`, picked in proportion to `reradiation`
+	pub fn sample_excitation(
+		&self,
+		emission: algebra::Scalar,
+		u: f64,
+	) -> std::option::Option<algebra::Scalar> {
+		let j = self.index(emission)?;
+		let target = u * self.totals[j];
+		let mut sum = 0.0;
+		for (i, factor) in self.factors[j].iter().enumerate() {
+			if *factor > 0.0 && sum + factor >= target {
+				let offset = ((target - sum) / factor).min(1.0 - 1e-9);
+				return std::option::Option::Some(
+					self.lambda_min + (i as algebra::Scalar + offset - 0.5) * self.step,
+				);
+			}
+			sum += factor;
+		}
+		std::option::Option::None
+	}
+
+	// density of `sample_excitation` with respect to wavelength in meters
+	pub fn pdf_excitation(
+		&self,
+		excitation: algebra::Scalar,
+		emission: algebra::Scalar,
+	) -> algebra::Scalar {
+		let total = self.total(emission);
+		if total <= 0.0 {
+			return 0.0;
+		}
+		self.reradiation(excitation, emission) / total
+	}
+}
+
+impl From<algebra::Scalar> for Ior {
+	fn from(n: algebra::Scalar) -> Ior {
+		Ior::Constant(n)
+	}
+}
+
+// incoming direction chosen by `Material::sample`
+#[derive(Clone, Copy, Debug)]
+pub struct ScatterSample {
+	// in world space, pointing away from the surface
+	pub direction: algebra::Vector,
+	// scattered radiance times cosine over pdf for each of the sampled wavelengths
+	pub weight: algebra::WavelengthBunch,
+	// solid angle density for the first wavelength, discrete probability if `delta`
+	pub pdf: algebra::Scalar,
+	// direction is one of finitely many, so lights cannot be sampled towards it
+	pub delta: bool,
+}
+
+// direction and excitation wavelength chosen by `Material::sample_fluorescence`
+#[derive(Clone, Copy, Debug)]
+pub struct FluorescenceSample {
+	// in world space, pointing away from the surface
+	pub direction: algebra::Vector,
+	// shorter wavelength the path continues with
+	pub excitation: algebra::Scalar,
+	// reradiated radiance times cosine over pdfs of the direction and the excitation
+	pub weight: algebra::Scalar,
+}
+
+#[derive(Clone)]
 pub struct Material {
 	pub emitter: EmissionType,
-	bxdf: Vec<shaders::BxDF>,
-	n: algebra::Scalar,
-	k: algebra::Scalar,
-	surface: InternalType,
+	// nested transparent objects with higher priority take precedence over
+	// overlapping ones with lower priority
+	pub priority: u32,
+	// participating medium filling the interior of transparent objects
+	pub medium: std::option::Option<media::Medium>,
+	// reradiation of absorbed light at longer wavelengths on top of the lobes
+	pub fluorescence: std::option::Option<Fluorescence>,
+	// scattering is the sum of all lobes
+	lobes: Vec<std::sync::Arc<dyn shaders::Bxdf>>,
+	n: Ior,
+	k: Ior,
+	// angle between tangent of the surface and x axis of shading frame
+	rotation: algebra::Scalar,
+}
+
+// lobes are shared, so materials are equal only if they use the very same ones
+impl PartialEq for Material {
+	fn eq(&self, other: &Self) -> bool {
+		self.emitter == other.emitter
+			&& self.priority == other.priority
+			&& self.medium == other.medium
+			&& self.fluorescence == other.fluorescence
+			&& self.n == other.n
+			&& self.k == other.k
+			&& self.rotation == other.rotation
+			&& self.lobes.len() == other.lobes.len()
+			&& self.lobes.iter().zip(&other.lobes).all(|(a, b)| std::sync::Arc::ptr_eq(a, b))
+	}
 }
 
 impl Material {
 	pub fn new(
 		emitter: EmissionType,
 		surface: SurfaceType,
-		n: algebra::Scalar,
-		k: algebra::Scalar,
+		n: impl Into<Ior>,
+		k: impl Into<Ior>,
+	) -> Self {
+		let mut rotation = 0.0;
+		let (lobes, medium): (Vec<std::sync::Arc<dyn shaders::Bxdf>>, _) = match surface {
+			SurfaceType::Conductor {
+				roughness,
+				anisotropy,
+				rotation: angle,
+				film,
+			} => {
+				rotation = angle;
+				let microfacets = shaders::Ggx::anisotropic(roughness, anisotropy);
+				let mut lobe = shaders::Conductor::with_microfacets(microfacets);
+				if let std::option::Option::Some(film) = film {
+					lobe = lobe.with_film(film);
+				}
+				(vec![std::sync::Arc::new(lobe)], std::option::Option::None)
+			}
+			SurfaceType::DielectricOpaque {
+				color,
+				roughness,
+				anisotropy,
+				rotation: angle,
+			} => {
+				rotation = angle;
+				let microfacets = shaders::Ggx::anisotropic(roughness, anisotropy);
+				let lobe = shaders::FresnelBlend::with_microfacets(color, microfacets);
+				(vec![std::sync::Arc::new(lobe)], std::option::Option::None)
+			}
+			SurfaceType::DielectricTransparent { roughness, film } => {
+				let mut lobe = shaders::Dielectric::new(roughness);
+				if let std::option::Option::Some(film) = film {
+					lobe = lobe.with_film(film);
+				}
+				(vec![std::sync::Arc::new(lobe)], std::option::Option::None)
+			}
+			SurfaceType::Subsurface {
+				color,
+				mean_free_path,
+				roughness,
+			} => (
+				vec![std::sync::Arc::new(shaders::Dielectric::new(roughness))],
+				std::option::Option::Some(media::Medium::subsurface(color, mean_free_path)),
+			),
+			SurfaceType::Coated {
+				base,
+				roughness,
+				thickness,
+				absorption,
+			} => {
+				let microfacets = shaders::Ggx::isotropic(roughness);
+				let coated = match base {
+					CoatBase::Diffuse { color, roughness } => {
+						let base = std::sync::Arc::new(shaders::OrenNayar::new(
+							0.5 * roughness,
+							color,
+						));
+						shaders::Coated::new(base, microfacets, thickness, absorption)
+					}
+					CoatBase::Conductor { n, k, roughness } => {
+						let base = std::sync::Arc::new(shaders::Conductor::new(roughness));
+						shaders::Coated::new(base, microfacets, thickness, absorption)
+							.with_base_ior(move |lambda| (n.evaluate(lambda), k.evaluate(lambda)))
+					}
+				};
+				(
+					vec![
+						std::sync::Arc::new(shaders::Coat::with_microfacets(microfacets)),
+						std::sync::Arc::new(coated),
+					],
+					std::option::Option::None,
+				)
+			}
+			SurfaceType::Principled {
+				base_color,
+				metallic,
+				roughness,
+				specular,
+				specular_tint,
+				sheen,
+				clearcoat,
+				clearcoat_roughness,
+				transmission,
+				subsurface,
+				subsurface_radius,
+			} => {
+				let weights =
+					[metallic, specular, specular_tint, sheen, clearcoat, transmission, subsurface];
+				if weights.iter().any(|w| !(0.0..=1.0).contains(w)) {
+					panic!("weights of principled material must be between 0.0 and 1.0");
+				}
+				let microfacets = shaders::Ggx::isotropic(roughness);
+				let coat = shaders::Coat::new(clearcoat_roughness).with_ior(1.5);
+				let dielectric = 1.0 - metallic;
+				let opaque = dielectric * (1.0 - transmission);
+				let plastic = opaque * (1.0 - subsurface);
+				let refracting = dielectric * transmission + opaque * subsurface;
+				// tint keeps mean reflectance of the base color over visible wavelengths
+				let mean = (0..=30)
+					.map(|i| base_color.evaluate(400e-9 + i as algebra::Scalar * 10e-9))
+					.sum::<algebra::Scalar>()
+					/ 31.0;
+				let tint_color = base_color.clone();
+				let tint = move |lambda: algebra::Scalar| {
+					if mean <= 0.0 {
+						return 1.0;
+					}
+					1.0 - specular_tint + specular_tint * tint_color.evaluate(lambda) / mean
+				};
+				let diffuse = std::sync::Arc::new(shaders::OrenNayar::new(
+					0.5 * roughness,
+					base_color.clone(),
+				));
+				let no_absorption = spectrum::Spectrum::Constant(0.0);
+				let weighted: Vec<(std::sync::Arc<dyn shaders::Bxdf>, algebra::Scalar)> = vec![
+					(
+						std::sync::Arc::new(
+							shaders::Conductor::with_microfacets(microfacets)
+								.with_reflectance(base_color.clone()),
+						),
+						metallic,
+					),
+					(
+						std::sync::Arc::new(shaders::Dielectric::with_microfacets(microfacets)),
+						refracting,
+					),
+					(
+						std::sync::Arc::new(shaders::Weighted::spectral(
+							std::sync::Arc::new(
+								shaders::Coat::with_microfacets(microfacets)
+									.scale_reflectance(2.0 * specular),
+							),
+							tint,
+						)),
+						plastic,
+					),
+					(
+						std::sync::Arc::new(
+							shaders::Coated::new(diffuse, microfacets, 0.0, no_absorption)
+								.scale_reflectance(2.0 * specular),
+						),
+						plastic,
+					),
+					(
+						std::sync::Arc::new(shaders::Sheen::new(spectrum::Spectrum::Constant(1.0))),
+						opaque * sheen,
+					),
+				];
+				// the rest gets only what the clear coat does not reflect
+				let mut lobes: Vec<std::sync::Arc<dyn shaders::Bxdf>> = weighted
+					.into_iter()
+					.filter(|(_, weight)| *weight > 0.0)
+					.map(|(lobe, weight)| {
+						let lobe = shaders::Weighted::new(lobe, weight)
+							.with_coat(coat.clone(), clearcoat);
+						let lobe: std::sync::Arc<dyn shaders::Bxdf> = std::sync::Arc::new(lobe);
+						lobe
+					})
+					.collect();
+				if clearcoat > 0.0 {
+					let lobe = shaders::Weighted::new(std::sync::Arc::new(coat), clearcoat);
+					lobes.push(std::sync::Arc::new(lobe));
+				}
+				let medium = if subsurface > 0.0 {
+					std::option::Option::Some(media::Medium::subsurface(
+						base_color,
+						subsurface_radius,
+					))
+				} else {
+					std::option::Option::None
+				};
+				(lobes, medium)
+			}
+		};
+		let mut material = Material::custom(emitter, lobes, n, k);
+		material.medium = medium;
+		material.rotation = rotation;
+		material
+	}
+
+	// material scattering by user defined lobes, evaluated with index of refraction n + ik
+	pub fn custom(
+		emitter: EmissionType,
+		lobes: Vec<std::sync::Arc<dyn shaders::Bxdf>>,
+		n: impl Into<Ior>,
+		k: impl Into<Ior>,
 	) -> Self {
-		match surface {
-			SurfaceType::Conductor { roughness } => Self {
-				emitter,
-				bxdf: vec![shaders::BxDF::ggx_reflect(roughness)],
-				n,
-				k,
-				surface: InternalType::Cond,
-			},
-			SurfaceType::DielectricOpaque { color, roughness } => Self {
-				emitter,
-				bxdf: vec![
-					shaders::BxDF::oren_nayar(0.5 * roughness, color),
-					shaders::BxDF::ggx_reflect(roughness),
-				],
-				n,
-				k,
-				surface: InternalType::DielOpaq,
-			},
-			SurfaceType::DielectricTransparent { roughness } => Self {
-				emitter,
-				bxdf: vec![
-					shaders::BxDF::specular_refract(),
-					shaders::BxDF::ggx_reflect(roughness),
-				],
-				n,
-				k,
-				surface: InternalType::DielTrs,
-			},
+		if lobes.is_empty() {
+			panic!("material needs at least one lobe");
+		}
+		Self {
+			emitter,
+			priority: 0,
+			medium: std::option::Option::None,
+			fluorescence: std::option::Option::None,
+			lobes,
+			n: n.into(),
+			k: k.into(),
+			rotation: 0.0,
+		}
+	}
+
+	fn context(
+		&self,
+		lambda: algebra::Scalar,
+		exterior: algebra::Scalar,
+	) -> shaders::ShadingContext {
+		shaders::ShadingContext {
+			lambda,
+			eta: self.n.evaluate(lambda) / exterior,
+			k: self.k.evaluate(lambda) / exterior,
+		}
+	}
+
+	// directions in shading frame; opaque surfaces face the viewer on either side of the
+	// geometry, so light always arrives from outside
+	fn to_local(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		frame: &algebra::Basis,
+	) -> (algebra::Vector, algebra::Vector) {
+		let mut incoming = frame.world_to_basis(incoming);
+		let mut outgoing = frame.world_to_basis(outgoing);
+		if self.is_flipped(outgoing) {
+			incoming.z = -incoming.z;
+			outgoing.z = -outgoing.z;
 		}
+		(incoming, outgoing)
 	}
 
+	fn is_flipped(&self, outgoing: algebra::Vector) -> bool {
+		outgoing.z < 0.0 && !self.is_transmissive()
+	}
+
+	// `frame` is shading frame given by `tangent_basis`, `exterior` is index of refraction
+	// of the medium surrounding the surface at `lambda`; specular lobes are left out unless
+	// the material has no other
 	pub fn return_scatter_radiance(
 		&self,
 		incoming: algebra::Vector,
 		outgoing: algebra::Vector,
-		half_vec: algebra::Vector,
-		normal: algebra::Vector,
+		_half_vec: algebra::Vector,
+		frame: &algebra::Basis,
 		lambda: algebra::Scalar,
+		exterior: algebra::Scalar,
 	) -> algebra::Scalar {
-		match self.surface {
-			InternalType::DielOpaq => {
-				let f = self.bxdf[0].fresnel_schlick_dielectric(1.0, self.n, outgoing, half_vec);
-				let diff = self.bxdf[0].compute_bxdf(incoming, outgoing, normal, lambda);
-				let glos = self.bxdf[1].compute_bxdf(incoming, outgoing, normal, lambda);
-				diff * (1.0 - f) + glos * f
-			}
-			InternalType::DielTrs => {
-				let f = self.bxdf[0].fresnel_schlick_dielectric(1.0, self.n, outgoing, half_vec);
-				let trs = self.bxdf[0].compute_bxdf(incoming, outgoing, normal, lambda);
-				let glos = self.bxdf[1].compute_bxdf(incoming, outgoing, normal, lambda);
-				trs * (1.0 - f) + glos * f
-			}
-			InternalType::Cond => {
-				let glos = self.bxdf[0].compute_bxdf(incoming, outgoing, normal, lambda);
-				let half_vec = (incoming + outgoing).normalize();
-				let f = self.bxdf[0].fresnel_conductor(self.n, self.k, outgoing, half_vec);
-				glos * f
-			}
-		}
+		let context = self.context(lambda, exterior);
+		let (incoming, outgoing) = self.to_local(incoming, outgoing, frame);
+		self.continuous_lobes(outgoing).map(|lobe| lobe.eval(incoming, outgoing, &context)).sum()
 	}
 
 	pub fn return_emission_radiance(&self,
@@ -122,158 +705,251 @@ impl Material {
 		normal: algebra::Vector,
 
 	lambda: algebra::Scalar) -> algebra::Scalar {
-		match self.emitter {
+		match &self.emitter {
 			EmissionType::NonEmissive => 0.0,
 			EmissionType::Incandescent { temperature } => {
-				constants::TWO_HC2
-					/ (lambda.powi(5) * ((constants::HC_BY_K / lambda / temperature).exp() - 1.0))
+				spectrum::Spectrum::BlackBody(*temperature).evaluate(lambda)
 			}
 			EmissionType::Cool { temperature, power } => {
-				let lmax: algebra::Scalar = constants::WIEN / temperature;
-				constants::TWO_HC2
-					/ (lambda.powi(5) * ((constants::HC_BY_K / lambda / temperature).exp() - 1.0))
-					/ (constants::TWO_HC2
-						/ (lmax.powi(5) * ((constants::HC_BY_K / lmax / temperature).exp() - 1.0)))
-					* power
+				spectrum::Spectrum::BlackBodyNormalized(*temperature).evaluate(lambda) * power
 			}
 			EmissionType::Fresnel => {
-				//10.0 * self.bxdf[0].fresnel_schlick_dielectric(1.0, self.n, outgoing, half_vec)
+				//10.0 * self.bxdf[0].fresnel_dielectric(outgoing * half_vec, self.n.evaluate(lambda))
 				outgoing * half_vec
 			}
+			EmissionType::Spectral { spectrum, power } => spectrum.evaluate(lambda) * power,
 		}
 	}
 
-	pub fn return_direction(
+	// picks incoming direction for the first of `wavelengths` from a lobe chosen in proportion
+	// to its weight; `outgoing` points away from the surface, `exterior` gives index of
+	// refraction around it
+	pub fn sample(
 		&self,
-		theta_i: algebra::Scalar,
-		phi_i: algebra::Scalar,
-		random: (f64, f64),
-	) -> (algebra::Scalar, algebra::Scalar) {
-		match self.surface {
-			InternalType::DielOpaq => match self.bxdf[1] {
-				shaders::BxDF::GGX_reflect { alpha, .. } => {
-					let mut u = random.0;
-					if u < 0.5 {
-						u = 2.0 * random.0;
-						return shaders::Lobe::evaluate_lobe(
-							shaders::Lobe::Cosine,
-							theta_i,
-							phi_i,
-							(u, random.1),
-						);
-					} else {
-						u = 2.0 * (random.0 - 0.5);
-						return shaders::Lobe::evaluate_lobe(
-							shaders::Lobe::GGX_reflect { alpha },
-							theta_i,
-							phi_i,
-							(u, random.1),
-						);
-					}
-				}
-				_ => {
-					return shaders::Lobe::evaluate_lobe(
-						shaders::Lobe::Cosine,
-						theta_i,
-						phi_i,
-						random,
-					)
-				}
-			},
-			InternalType::DielTrs => {
-				return shaders::Lobe::evaluate_lobe(
-					shaders::Lobe::DeltaRefract,
-					theta_i,
-					phi_i,
-					random,
-				);
+		outgoing: algebra::Vector,
+		frame: &algebra::Basis,
+		random: (f64, f64, f64),
+		wavelengths: algebra::WavelengthBunch,
+		exterior: &dyn Fn(algebra::Scalar) -> algebra::Scalar,
+	) -> std::option::Option<ScatterSample> {
+		let mut local_out = frame.world_to_basis(outgoing);
+		let flipped = self.is_flipped(local_out);
+		if flipped {
+			local_out.z = -local_out.z;
+		}
+		let hero = self.context(wavelengths.0, exterior(wavelengths.0));
+		let (lobe, chance, rest) = self.choose_lobe(local_out, &hero, random.2)?;
+		let total = self.lobe_weight(local_out, &hero);
+		let sampled = lobe.sample(local_out, (random.0, random.1, rest), &hero)?;
+		let local_in = sampled.incoming;
+		let delta = sampled.flags.is_specular();
+		// discrete directions of a specular lobe are never produced by the others,
+		// continuous ones only by the other continuous lobes
+		let pdf = if delta {
+			sampled.pdf * chance
+		} else {
+			let lobes = self.continuous_lobes(local_out);
+			lobes
+				.map(|lobe| lobe.weight(local_out, &hero) * lobe.pdf(local_in, local_out, &hero))
+				.sum::<algebra::Scalar>()
+				/ total
+		};
+		let value = |lambda: algebra::Scalar| {
+			let context = self.context(lambda, exterior(lambda));
+			if delta {
+				lobe.eval(local_in, local_out, &context)
+			} else {
+				let values = self.continuous_lobes(local_out).map(|lobe| {
+					lobe.eval(local_in, local_out, &context)
+				});
+				values.sum()
 			}
-			InternalType::DielTrs => match self.bxdf[1] {
-				shaders::BxDF::GGX_reflect { alpha, .. } => {
-					let mut u = random.0;
-					if u < 0.5 {
-						u = 2.0 * random.0;
-						return shaders::Lobe::evaluate_lobe(
-							shaders::Lobe::Cosine,
-							theta_i,
-							phi_i,
-							(u, random.1),
-						);
-					} else {
-						u = 2.0 * (random.0 - 0.5);
-						return shaders::Lobe::evaluate_lobe(
-							shaders::Lobe::DeltaRefract,
-							theta_i,
-							phi_i,
-							(u, random.1),
-						);
-					}
-				}
-				_ => {
-					return shaders::Lobe::evaluate_lobe(
-						shaders::Lobe::DeltaRefract,
-						theta_i,
-						phi_i,
-						random,
-					)
-				}
-			},
-			InternalType::Cond => match self.bxdf[0] {
-				shaders::BxDF::GGX_reflect { alpha, .. } => {
-					return shaders::Lobe::evaluate_lobe(
-						shaders::Lobe::GGX_reflect { alpha },
-						theta_i,
-						phi_i,
-						random,
-					)
-				}
-				_ => {
-					return shaders::Lobe::evaluate_lobe(
-						shaders::Lobe::DeltaReflect,
-						theta_i,
-						phi_i,
-						random,
-					)
-				}
-			},
+		};
+		if pdf <= 0.0 {
+			return std::option::Option::None;
+		}
+		let scale = local_in.z.abs() / pdf;
+		let mut direction = local_in;
+		if flipped {
+			direction.z = -direction.z;
 		}
+		std::option::Option::Some(ScatterSample {
+			direction: frame.basis_to_world(direction),
+			weight: algebra::WavelengthBunch(
+				value(wavelengths.0) * scale,
+				value(wavelengths.1) * scale,
+				value(wavelengths.2) * scale,
+				value(wavelengths.3) * scale,
+			),
+			pdf,
+			delta,
+		})
 	}
 
+	// specular lobes are left out unless the material has no other, see
+	// `return_scatter_radiance`
 	pub fn return_pdf(
 		&self,
 		incoming: algebra::Vector,
 		outgoing: algebra::Vector,
-		half_vec: algebra::Vector,
-		normal: algebra::Vector,
+		_half_vec: algebra::Vector,
+		frame: &algebra::Basis,
 		lambda: algebra::Scalar,
+		exterior: algebra::Scalar,
 	) -> algebra::Scalar {
-		match self.surface {
-			InternalType::DielOpaq => {
-				return 0.5
-					* (self.bxdf[0].pdf(incoming, outgoing, normal, lambda)
-						+ self.bxdf[1].pdf(incoming, outgoing, normal, lambda));
-			}
-			InternalType::DielTrs => {
-				//return self.bxdf[0].pdf(incoming, outgoing, normal, lambda);
-				return 0.5
-					* (self.bxdf[0].pdf(incoming, outgoing, normal, lambda)
-						+ self.bxdf[1].pdf(incoming, outgoing, normal, lambda));
+		let context = self.context(lambda, exterior);
+		let (incoming, outgoing) = self.to_local(incoming, outgoing, frame);
+		let total = self.lobe_weight(outgoing, &context);
+		if total <= 0.0 {
+			return 0.0;
+		}
+		let sum: algebra::Scalar = self
+			.continuous_lobes(outgoing)
+			.map(|lobe| lobe.weight(outgoing, &context) * lobe.pdf(incoming, outgoing, &context))
+			.sum();
+		sum / total
+	}
+
+	// summed weight of the lobes active for `outgoing` in shading frame
+	fn lobe_weight(
+		&self,
+		outgoing: algebra::Vector,
+		context: &shaders::ShadingContext,
+	) -> algebra::Scalar {
+		self.active_lobes(outgoing)
+			.map(|lobe| lobe.weight(outgoing, context))
+			.sum()
+	}
+
+	// active lobe picked with chance proportional to its weight, together with that chance
+	// and `random` rescaled to be left to the lobe
+	fn choose_lobe(
+		&self,
+		outgoing: algebra::Vector,
+		context: &shaders::ShadingContext,
+		random: f64,
+	) -> std::option::Option<(&std::sync::Arc<dyn shaders::Bxdf>, algebra::Scalar, f64)> {
+		let total = self.lobe_weight(outgoing, context);
+		if total <= 0.0 {
+			return std::option::Option::None;
+		}
+		let mut target = random * total;
+		let mut chosen = std::option::Option::None;
+		for lobe in self.active_lobes(outgoing) {
+			let weight = lobe.weight(outgoing, context);
+			if weight <= 0.0 {
+				continue;
 			}
-			InternalType::Cond => {
-				return self.bxdf[0].pdf(incoming, outgoing, normal, lambda);
+			// rounding may leave `target` past the last lobe, which then takes it
+			let rest = (target / weight).min(1.0 - f64::EPSILON);
+			chosen = std::option::Option::Some((lobe, weight / total, rest));
+			if target < weight {
+				break;
 			}
+			target -= weight;
 		}
+		chosen
 	}
 
-	pub fn new_basis(&self, normal: algebra::Vector) -> algebra::Basis {
-		let a: algebra::Vector;
-		if normal.x.abs() > 1.0 - algebra::Scalar::EPSILON {
-			a = algebra::Vector::new(0.0, 1.0, 0.0);
-		} else {
-			a = algebra::Vector::new(1.0, 0.0, 0.0);
+	// light arriving from inside of transparent objects only meets lobes transmitting it,
+	// `outgoing` is in shading frame
+	fn active_lobes(
+		&self,
+		outgoing: algebra::Vector,
+	) -> impl Iterator<Item = &std::sync::Arc<dyn shaders::Bxdf>> {
+		let inside = outgoing.z < 0.0;
+		let transmission = shaders::LobeFlags::TRANSMISSION;
+		self.lobes.iter().filter(move |lobe| !inside || lobe.flags().contains(transmission))
+	}
+
+	// chance of following reradiation instead of the lobes for light leaving at `lambda`, rising
+	// with its share of the light
+	pub fn fluorescence_probability(&self, lambda: algebra::Scalar) -> algebra::Scalar {
+		let total = self.fluorescence.as_ref().map_or(0.0, |f| f.total(lambda));
+		total / (1.0 + total)
+	}
+
+	// diffuse direction on the side of `outgoing` and shorter wavelength of light reradiated
+	// towards it at `lambda`
+	pub fn sample_fluorescence(
+		&self,
+		outgoing: algebra::Vector,
+		frame: &algebra::Basis,
+		random: (f64, f64, f64),
+		lambda: algebra::Scalar,
+	) -> std::option::Option<FluorescenceSample> {
+		let fluorescence = self.fluorescence.as_ref()?;
+		let excitation = fluorescence.sample_excitation(lambda, random.2)?;
+		let mut direction = shaders::sample_cosine((random.0, random.1));
+		if frame.world_to_basis(outgoing).z < 0.0 {
+			direction.z = -direction.z;
 		}
-		let x = normal % a;
-		let y = normal % x;
-		algebra::Basis::new(x.normalize(), y.normalize(), normal)
+		// cosine and diffuse reradiation cancel out with the pdfs
+		std::option::Option::Some(FluorescenceSample {
+			direction: frame.basis_to_world(direction),
+			excitation,
+			weight: fluorescence.total(lambda),
+		})
+	}
+
+	fn continuous_lobes(
+		&self,
+		outgoing: algebra::Vector,
+	) -> impl Iterator<Item = &std::sync::Arc<dyn shaders::Bxdf>> {
+		let delta = self.has_delta_lobe();
+		self.active_lobes(outgoing).filter(move |lobe| delta || !lobe.flags().is_specular())
+	}
+
+	// materials scattering only into discrete directions cannot be lit by sampled lights
+	pub fn has_delta_lobe(&self) -> bool {
+		self.lobes.iter().all(|lobe| lobe.flags().is_specular())
+	}
+
+	pub fn return_ior(&self, lambda: algebra::Scalar) -> algebra::Scalar {
+		self.n.evaluate(lambda)
+	}
+
+	// only transparent objects enclose a medium a path can travel in
+	pub fn is_transmissive(&self) -> bool {
+		let transmission = shaders::LobeFlags::TRANSMISSION;
+		self.lobes.iter().any(|lobe| lobe.flags().contains(transmission))
+	}
+
+	// refracted direction depends on wavelength, so only a single wavelength can follow it
+	pub fn is_dispersive(&self) -> bool {
+		self.is_transmissive() && self.n.is_dispersive()
+	}
+
+	// shading frame of lobes with `normal` as z axis and x axis along `tangent` turned by
+	// rotation of the material; roughness `alpha_x` of anisotropic microfacets applies along x,
+	// `alpha_y` along y; arbitrary tangent is picked if none is given or it is parallel to normal
+	pub fn tangent_basis(
+		&self,
+		normal: algebra::Vector,
+		tangent: std::option::Option<algebra::Vector>,
+	) -> algebra::Basis {
+		// tangent is projected onto the surface
+		let projected = tangent
+			.map(|tangent| tangent - (tangent * normal) * normal)
+			.filter(|tangent| tangent.norm() > 1e-6);
+		let x = match projected {
+			std::option::Option::Some(tangent) => tangent.normalize(),
+			std::option::Option::None => {
+				// helper axis far from parallel to the normal keeps the tangent well defined
+				let a = if normal.x.abs() > 0.9 {
+					algebra::Vector::new(0.0, 1.0, 0.0)
+				} else {
+					algebra::Vector::new(1.0, 0.0, 0.0)
+				};
+				(normal % a).normalize()
+			}
+		};
+		let y = (normal % x).normalize();
+		let (sin, cos) = self.rotation.sin_cos();
+		algebra::Basis::new(cos * x + sin * y, cos * y - sin * x, normal)
+	}
+
+	pub fn new_basis(&self, normal: algebra::Vector) -> algebra::Basis {
+		self.tangent_basis(normal, std::option::Option::None)
 	}
 }
@@ -0,0 +1,539 @@
+use crate::algebra;
+use crate::constants;
+use crate::spectrum;
+use std::io;
+use std::path;
+
+// voxels per side of block bounded by single majorant
+const MAJORANT_BLOCK: usize = 8;
+
+#[derive(Clone, PartialEq, Debug)]
+enum Coefficients {
+	Extinction {
+		sigma_a: spectrum::Spectrum,
+		sigma_s: spectrum::Spectrum,
+	},
+	// multiple scattering albedo with mean free path in meters
+	Albedo {
+		color: spectrum::Spectrum,
+		mean_free_path: spectrum::Spectrum,
+	},
+}
+
+// participating medium with absorption and scattering coefficients in inverse meters,
+// scaled by density which is 1 everywhere unless given by grid
+#[derive(Clone, PartialEq, Debug)]
+pub struct Medium {
+	coefficients: Coefficients,
+	// Henyey-Greenstein asymmetry parameter, positive values scatter forward
+	g: algebra::Scalar,
+	density: std::option::Option<DensityGrid>,
+}
+
+impl Medium {
+	pub fn new(
+		sigma_a: spectrum::Spectrum,
+		sigma_s: spectrum::Spectrum,
+		g: algebra::Scalar,
+	) -> Medium {
+		if g.abs() >= 1.0 || g.is_nan() {
+			panic!("Henyey-Greenstein asymmetry must lie in range (-1, 1)");
+		}
+		Medium {
+			coefficients: Coefficients::Extinction { sigma_a, sigma_s },
+			g,
+			density: std::option::Option::None,
+		}
+	}
+
+	// isotropic medium whose semi-infinite slab reflects `color` after multiple scattering
+	pub fn subsurface(color: spectrum::Spectrum, mean_free_path: spectrum::Spectrum) -> Medium {
+		Medium {
+			coefficients: Coefficients::Albedo {
+				color,
+				mean_free_path,
+			},
+			g: 0.0,
+			density: std::option::Option::None,
+		}
+	}
+
+	// medium with coefficients scaled by density varying over the grid, e.g. clouds or smoke
+	pub fn heterogeneous(
+		sigma_a: spectrum::Spectrum,
+		sigma_s: spectrum::Spectrum,
+		g: algebra::Scalar,
+		density: DensityGrid,
+	) -> Medium {
+		let mut medium = Medium::new(sigma_a, sigma_s, g);
+		medium.density = std::option::Option::Some(density);
+		medium
+	}
+
+	// purely absorbing medium, e.g. tinted glass or clear liquid
+	pub fn absorbing(sigma_a: spectrum::Spectrum) -> Medium {
+		Medium::new(sigma_a, spectrum::Spectrum::Constant(0.0), 0.0)
+	}
+
+	pub fn is_homogeneous(&self) -> bool {
+		self.density.is_none()
+	}
+
+	pub fn density(&self, point: algebra::Vector) -> algebra::Scalar {
+		match &self.density {
+			std::option::Option::Some(grid) => grid.density(point),
+			std::option::Option::None => 1.0,
+		}
+	}
+
+	// parts of the ray up to `t_max` with upper bounds of density over them, given
+	// as (start, end, majorant density); `direction` must be normalized
+	pub fn majorant_segments(
+		&self,
+		origin: algebra::Vector,
+		direction: algebra::Vector,
+		t_max: algebra::Scalar,
+	) -> Vec<(algebra::Scalar, algebra::Scalar, algebra::Scalar)> {
+		match &self.density {
+			std::option::Option::Some(grid) => grid.majorant_segments(origin, direction, t_max),
+			std::option::Option::None => vec![(0.0, t_max, 1.0)],
+		}
+	}
+
+	// coefficients below are given for unit density
+	pub fn sigma_a(&self, lambda: algebra::Scalar) -> algebra::Scalar {
+		match &self.coefficients {
+			Coefficients::Extinction { sigma_a, .. } => sigma_a.evaluate(lambda),
+			Coefficients::Albedo { .. } => self.sigma_t(lambda) - self.sigma_s(lambda),
+		}
+	}
+
+	pub fn sigma_s(&self, lambda: algebra::Scalar) -> algebra::Scalar {
+		match &self.coefficients {
+			Coefficients::Extinction { sigma_s, .. } => sigma_s.evaluate(lambda),
+			Coefficients::Albedo { color, .. } => {
+				single_scattering_albedo(color.evaluate(lambda)) * self.sigma_t(lambda)
+			}
+		}
+	}
+
+	pub fn sigma_t(&self, lambda: algebra::Scalar) -> algebra::Scalar {
+		match &self.coefficients {
+			Coefficients::Extinction { sigma_a, sigma_s } => {
+				sigma_a.evaluate(lambda) + sigma_s.evaluate(lambda)
+			}
+			Coefficients::Albedo { mean_free_path, .. } => 1.0 / mean_free_path.evaluate(lambda),
+		}
+	}
+
+	// Beer-Lambert law for unit density
+	pub fn transmittance(
+		&self,
+		distance: algebra::Scalar,
+		lambda: algebra::Scalar,
+	) -> algebra::Scalar {
+		let sigma_t = self.sigma_t(lambda);
+		if sigma_t == 0.0 {
+			return 1.0;
+		}
+		(-sigma_t * distance).exp()
+	}
+
+	// free flight distance at unit density with pdf sigma_t * exp(-sigma_t * t), infinite in vacuum
+	pub fn sample_distance(&self, lambda: algebra::Scalar, u: f64) -> algebra::Scalar {
+		let sigma_t = self.sigma_t(lambda);
+		if sigma_t <= 0.0 {
+			return algebra::Scalar::INFINITY;
+		}
+		-(1.0 - u).ln() / sigma_t
+	}
+
+	// phase function for angle between propagation directions before and after scattering,
+	// normalized over the sphere
+	// source: https://pbr-book.org/3ed-2018/Volume_Scattering/Phase_Functions
+	pub fn phase(&self, cos_theta: algebra::Scalar) -> algebra::Scalar {
+		let g = self.g;
+		let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+		(1.0 - g * g) / (4.0 * constants::PI * denominator * denominator.sqrt())
+	}
+
+	// new propagation direction sampled exactly proportional to phase function
+	pub fn sample_phase(&self, direction: algebra::Vector, random: (f64, f64)) -> algebra::Vector {
+		let g = self.g;
+		let cos_theta = if g.abs() < 1e-3 {
+			1.0 - 2.0 * random.0
+		} else {
+			let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * random.0);
+			(1.0 + g * g - s * s) / (2.0 * g)
+		}
+		.clamp(-1.0, 1.0);
+		let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+		let phi = 2.0 * constants::PI * random.1;
+		let w = direction.normalize();
+		let a = if w.x.abs() > 0.9 {
+			algebra::Vector::new(0.0, 1.0, 0.0)
+		} else {
+			algebra::Vector::new(1.0, 0.0, 0.0)
+		};
+		let u = (w % a).normalize();
+		let v = w % u;
+		algebra::Basis::new(u, v, w).basis_to_world(algebra::Vector::new(
+			sin_theta * phi.cos(),
+			sin_theta * phi.sin(),
+			cos_theta,
+		))
+	}
+}
+
+// dense voxel grid of densities spanning axis aligned box from `min` to `max`, with values
+// stored x fastest, then y and z; density is interpolated trilinearly between voxel
+// centers and zero outside of the box
+#[derive(Clone, PartialEq, Debug)]
+pub struct DensityGrid {
+	min: algebra::Vector,
+	max: algebra::Vector,
+	resolution: [usize; 3],
+	values: Vec<algebra::Scalar>,
+	majorant_resolution: [usize; 3],
+	majorants: Vec<algebra::Scalar>,
+}
+
+impl DensityGrid {
+	pub fn new(
+		min: algebra::Vector,
+		max: algebra::Vector,
+		resolution: [usize; 3],
+		values: Vec<algebra::Scalar>,
+	) -> DensityGrid {
+		if resolution.contains(&0) {
+			panic!("DensityGrid resolution must be greater than 0, got {:?}", resolution);
+		}
+		if !(min.x < max.x && min.y < max.y && min.z < max.z) {
+			panic!("DensityGrid bounds must span positive volume");
+		}
+		let count = resolution[0] * resolution[1] * resolution[2];
+		if values.len() != count {
+			panic!(
+				"DensityGrid of resolution {:?} expected {} values, got {}",
+				resolution,
+				count,
+				values.len()
+			);
+		}
+		if values.iter().any(|value| value.is_nan() || *value < 0.0) {
+			panic!("DensityGrid values must be non-negative");
+		}
+		let majorant_resolution = resolution.map(|n| n.div_ceil(MAJORANT_BLOCK));
+		let mut grid = DensityGrid {
+			min,
+			max,
+			resolution,
+			values,
+			majorant_resolution,
+			majorants: Vec::new(),
+		};
+		grid.majorants = grid.compute_majorants();
+		grid
+	}
+
+	// densities evaluated at voxel centers, e.g. procedural noise
+	pub fn from_fn(
+		min: algebra::Vector,
+		max: algebra::Vector,
+		resolution: [usize; 3],
+		density: impl Fn(algebra::Vector) -> algebra::Scalar,
+	) -> DensityGrid {
+		let size = max - min;
+		let center =
+			|index: usize, n: usize| (index as algebra::Scalar + 0.5) / n as algebra::Scalar;
+		let mut values = Vec::with_capacity(resolution[0] * resolution[1] * resolution[2]);
+		for k in 0..resolution[2] {
+			for j in 0..resolution[1] {
+				for i in 0..resolution[0] {
+					values.push(density(
+						min + algebra::Vector::new(
+							size.x * center(i, resolution[0]),
+							size.y * center(j, resolution[1]),
+							size.z * center(k, resolution[2]),
+						),
+					));
+				}
+			}
+		}
+		DensityGrid::new(min, max, resolution, values)
+	}
+
+	pub fn from_file<P: AsRef<path::Path>>(
+		path: P,
+		min: algebra::Vector,
+		max: algebra::Vector,
+	) -> io::Result<DensityGrid> {
+		let bytes = std::fs::read(path)?;
+		DensityGrid::from_bytes(&bytes, min, max)
+	}
+
+	// reads text header `nilepsilon-grid <nx> <ny> <nz>` terminated by newline, followed
+	// by nx * ny * nz little endian 32 bit floats stored x fastest
+	pub fn from_bytes(
+		bytes: &[u8],
+		min: algebra::Vector,
+		max: algebra::Vector,
+	) -> io::Result<DensityGrid> {
+		let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+		let end = bytes
+			.iter()
+			.position(|byte| *byte == b'\n')
+			.ok_or_else(|| invalid("missing density grid header"))?;
+		let header = String::from_utf8_lossy(&bytes[..end]);
+		let tokens: Vec<&str> = header.split_whitespace().collect();
+		if tokens.len() != 4 || tokens[0] != "nilepsilon-grid" {
+			return Err(invalid("invalid density grid header"));
+		}
+		let mut resolution = [0; 3];
+		for (n, token) in resolution.iter_mut().zip(&tokens[1..]) {
+			*n = token
+				.parse()
+				.map_err(|_| invalid("invalid density grid resolution"))?;
+		}
+		if resolution.contains(&0) {
+			return Err(invalid("empty density grid"));
+		}
+		let data = &bytes[end + 1..];
+		let count = resolution[0] * resolution[1] * resolution[2];
+		if data.len() != 4 * count {
+			return Err(invalid("density grid data size mismatch"));
+		}
+		let values: Vec<algebra::Scalar> = data
+			.chunks_exact(4)
+			.map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as algebra::Scalar)
+			.collect();
+		if values.iter().any(|value| value.is_nan() || *value < 0.0) {
+			return Err(invalid("negative or invalid density grid value"));
+		}
+		Ok(DensityGrid::new(min, max, resolution, values))
+	}
+
+	pub fn density(&self, point: algebra::Vector) -> algebra::Scalar {
+		let local = self.local(point);
+		if local.iter().any(|x| !(0.0..=1.0).contains(x)) {
+			return 0.0;
+		}
+		// lower voxel and interpolation weight along each axis
+		let mut lower = [0; 3];
+		let mut upper = [0; 3];
+		let mut weight = [0.0; 3];
+		for axis in 0..3 {
+			let n = self.resolution[axis];
+			let x = local[axis] * n as algebra::Scalar - 0.5;
+			let floor = x.floor();
+			weight[axis] = x - floor;
+			lower[axis] = (floor.max(0.0) as usize).min(n - 1);
+			upper[axis] = ((floor + 1.0).max(0.0) as usize).min(n - 1);
+		}
+		let mut density = 0.0;
+		for corner in 0..8 {
+			let mut index = [0; 3];
+			let mut w = 1.0;
+			for axis in 0..3 {
+				if corner >> axis & 1 == 1 {
+					index[axis] = upper[axis];
+					w *= weight[axis];
+				} else {
+					index[axis] = lower[axis];
+					w *= 1.0 - weight[axis];
+				}
+			}
+			density += w * self.value(index);
+		}
+		density
+	}
+
+	// walks cells of majorant grid crossed by the ray with 3D DDA
+	// source: http://www.cse.yorku.ca/~amana/research/grid.pdf
+	fn majorant_segments(
+		&self,
+		origin: algebra::Vector,
+		direction: algebra::Vector,
+		t_max: algebra::Scalar,
+	) -> Vec<(algebra::Scalar, algebra::Scalar, algebra::Scalar)> {
+		let mut segments = Vec::new();
+		let origin = [origin.x, origin.y, origin.z];
+		let direction = [direction.x, direction.y, direction.z];
+		let min = [self.min.x, self.min.y, self.min.z];
+		let max = [self.max.x, self.max.y, self.max.z];
+		// clip ray by bounding box
+		let (mut t_enter, mut t_exit) = (0.0 as algebra::Scalar, t_max);
+		for axis in 0..3 {
+			if direction[axis] == 0.0 {
+				if origin[axis] < min[axis] || origin[axis] > max[axis] {
+					return segments;
+				}
+				continue;
+			}
+			let t_a = (min[axis] - origin[axis]) / direction[axis];
+			let t_b = (max[axis] - origin[axis]) / direction[axis];
+			t_enter = t_enter.max(t_a.min(t_b));
+			t_exit = t_exit.min(t_a.max(t_b));
+		}
+		if t_enter >= t_exit {
+			return segments;
+		}
+		let mut cell = [0; 3];
+		let mut step = [0_isize; 3];
+		let mut t_next = [algebra::Scalar::INFINITY; 3];
+		let mut t_delta = [algebra::Scalar::INFINITY; 3];
+		for axis in 0..3 {
+			let n = self.majorant_resolution[axis];
+			let width = (max[axis] - min[axis]) / n as algebra::Scalar;
+			let position = origin[axis] + t_enter * direction[axis];
+			cell[axis] = (((position - min[axis]) / width).max(0.0) as usize).min(n - 1);
+			if direction[axis] > 0.0 {
+				step[axis] = 1;
+				let boundary = min[axis] + (cell[axis] + 1) as algebra::Scalar * width;
+				t_next[axis] = (boundary - origin[axis]) / direction[axis];
+				t_delta[axis] = width / direction[axis];
+			} else if direction[axis] < 0.0 {
+				step[axis] = -1;
+				let boundary = min[axis] + cell[axis] as algebra::Scalar * width;
+				t_next[axis] = (boundary - origin[axis]) / direction[axis];
+				t_delta[axis] = -width / direction[axis];
+			}
+		}
+		let mut t = t_enter;
+		loop {
+			let axis = (0..3)
+				.min_by(|a, b| t_next[*a].total_cmp(&t_next[*b]))
+				.unwrap_or(0);
+			let end = t_next[axis].min(t_exit);
+			let majorant = self.majorants[self.majorant_index(cell)];
+			if end > t {
+				segments.push((t, end, majorant));
+			}
+			if end >= t_exit {
+				break;
+			}
+			t = end;
+			let next = cell[axis] as isize + step[axis];
+			if next < 0 || next >= self.majorant_resolution[axis] as isize {
+				break;
+			}
+			cell[axis] = next as usize;
+			t_next[axis] += t_delta[axis];
+		}
+		segments
+	}
+
+	// position inside bounding box mapped to unit cube
+	fn local(&self, point: algebra::Vector) -> [algebra::Scalar; 3] {
+		[
+			(point.x - self.min.x) / (self.max.x - self.min.x),
+			(point.y - self.min.y) / (self.max.y - self.min.y),
+			(point.z - self.min.z) / (self.max.z - self.min.z),
+		]
+	}
+
+	fn value(&self, index: [usize; 3]) -> algebra::Scalar {
+		self.values[(index[2] * self.resolution[1] + index[1]) * self.resolution[0] + index[0]]
+	}
+
+	fn majorant_index(&self, cell: [usize; 3]) -> usize {
+		(cell[2] * self.majorant_resolution[1] + cell[1]) * self.majorant_resolution[0] + cell[0]
+	}
+
+	// maximum over voxels whose interpolation reaches into each block
+	fn compute_majorants(&self) -> Vec<algebra::Scalar> {
+		let [mx, my, mz] = self.majorant_resolution;
+		let mut majorants = vec![0.0; mx * my * mz];
+		let cells =
+			(0..mz).flat_map(|k| (0..my).flat_map(move |j| (0..mx).map(move |i| [i, j, k])));
+		for cell in cells {
+			let mut range = [(0, 0); 3];
+			for axis in 0..3 {
+				let n = self.resolution[axis];
+				let m = self.majorant_resolution[axis];
+				// block spans [start, end] in continuous voxel coordinates, neighbours
+				// of the boundary voxels are included
+				let scale = n as algebra::Scalar / m as algebra::Scalar;
+				let start = cell[axis] as algebra::Scalar * scale;
+				let end = (cell[axis] + 1) as algebra::Scalar * scale;
+				let first = (start - 0.5).floor().max(0.0) as usize;
+				let last = ((end - 0.5).floor() + 1.0).max(0.0) as usize;
+				range[axis] = (first.min(n - 1), last.min(n - 1));
+			}
+			let mut majorant: algebra::Scalar = 0.0;
+			for z in range[2].0..=range[2].1 {
+				for y in range[1].0..=range[1].1 {
+					for x in range[0].0..=range[0].1 {
+						majorant = majorant.max(self.value([x, y, z]));
+					}
+				}
+			}
+			majorants[self.majorant_index(cell)] = majorant;
+		}
+		majorants
+	}
+}
+
+// single scattering albedo of isotropic medium giving multiple scattering albedo `color`
+// source: https://doi.org/10.1145/2897839.2927433
+pub fn single_scattering_albedo(color: algebra::Scalar) -> algebra::Scalar {
+	let color = color.clamp(0.0, 1.0);
+	let root = (9.59217 + 41.6808 * color + 17.7126 * color * color).sqrt();
+	let s = 4.09712 + 4.20863 * color - root;
+	(1.0 - s * s).clamp(0.0, 1.0)
+}
+
+// fractal sum of value noise octaves in range [0, 1], for procedural densities
+pub fn fbm(point: algebra::Vector, octaves: u32) -> algebra::Scalar {
+	let mut sum = 0.0;
+	let mut amplitude = 0.5;
+	let mut frequency = 1.0;
+	let mut total = 0.0;
+	for octave in 0..octaves {
+		sum += amplitude * value_noise(point * frequency, octave);
+		total += amplitude;
+		amplitude *= 0.5;
+		frequency *= 2.0;
+	}
+	if total > 0.0 {
+		sum / total
+	} else {
+		0.0
+	}
+}
+
+// smoothly interpolated random values at integer lattice points
+fn value_noise(point: algebra::Vector, seed: u32) -> algebra::Scalar {
+	let cell = [point.x.floor(), point.y.floor(), point.z.floor()];
+	let fraction = [point.x - cell[0], point.y - cell[1], point.z - cell[2]];
+	let smooth = fraction.map(|t| t * t * (3.0 - 2.0 * t));
+	let mut value = 0.0;
+	for corner in 0..8 {
+		let mut w = 1.0;
+		let mut lattice = [0_i64; 3];
+		for axis in 0..3 {
+			let upper = corner >> axis & 1;
+			lattice[axis] = cell[axis] as i64 + upper as i64;
+			w *= if upper == 1 {
+				smooth[axis]
+			} else {
+				1.0 - smooth[axis]
+			};
+		}
+		value += w * hash(lattice, seed);
+	}
+	value
+}
+
+// integer hash of lattice point mapped to [0, 1)
+fn hash(lattice: [i64; 3], seed: u32) -> algebra::Scalar {
+	let mut h = (seed as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+	for coordinate in lattice {
+		h ^= coordinate as u64;
+		h = h.wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		h ^= h >> 31;
+	}
+	h = h.wrapping_mul(0x94d0_49bb_1331_11eb);
+	h ^= h >> 29;
+	(h >> 11) as algebra::Scalar / (1_u64 << 53) as algebra::Scalar
+}
@@ -32,6 +32,11 @@ impl ImageFile {
 		self.image[index].b = value.2;
 	}
 
+	pub fn get_pixel(&self, x: u32, y: u32) -> (f64, f64, f64) {
+		let pixel = &self.image[(y * self.width + x) as usize];
+		(pixel.r, pixel.g, pixel.b)
+	}
+
 	pub fn out(&self, format: Format) {
 		match format {
 			Format::PPM => {
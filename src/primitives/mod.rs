@@ -7,6 +7,8 @@ use crate::ray;
 pub struct Primitive {
 	pub shape: Shape,
 	pub material: materials::Material,
+	// direction anisotropic materials are aligned with, none picks an arbitrary one
+	pub tangent: std::option::Option<algebra::Vector>,
 }
 
 impl Primitive {
@@ -17,6 +19,7 @@ impl Primitive {
 	) -> Primitive {
 		Primitive {
 			material,
+			tangent: std::option::Option::None,
 			shape: Shape::Sphere { position, radius },
 		}
 	}
@@ -28,6 +31,7 @@ impl Primitive {
 	) -> Primitive {
 		Primitive {
 			material,
+			tangent: std::option::Option::None,
 			shape: Shape::Plane {
 				position,
 				normal: normal.normalize(),
@@ -46,6 +50,7 @@ impl Primitive {
 
 		Primitive {
 			material,
+			tangent: std::option::Option::None,
 			shape: Shape::Triangle {
 				v1,
 				v2,
@@ -55,6 +60,39 @@ impl Primitive {
 			},
 		}
 	}
+
+	// tangent follows direction of increasing u texture coordinate
+	pub fn new_triangle_uv(
+		v1: algebra::Vector,
+		v2: algebra::Vector,
+		v3: algebra::Vector,
+		uv1: (algebra::Scalar, algebra::Scalar),
+		uv2: (algebra::Scalar, algebra::Scalar),
+		uv3: (algebra::Scalar, algebra::Scalar),
+		material: materials::Material,
+	) -> Primitive {
+		let (du12, dv12) = (uv2.0 - uv1.0, uv2.1 - uv1.1);
+		let (du13, dv13) = (uv3.0 - uv1.0, uv3.1 - uv1.1);
+		let determinant = du12 * dv13 - du13 * dv12;
+		let triangle = Primitive::new_triangle(v1, v2, v3, material);
+		if determinant.abs() < algebra::Scalar::EPSILON {
+			return triangle;
+		}
+		// source: https://pbr-book.org/3ed-2018/Shapes/Triangle_Meshes
+		let tangent = ((v2 - v1) * dv13 - (v3 - v1) * dv12) / determinant;
+		triangle.with_tangent(tangent)
+	}
+
+	pub fn with_tangent(self, tangent: algebra::Vector) -> Primitive {
+		Primitive {
+			tangent: std::option::Option::Some(tangent),
+			..self
+		}
+	}
+
+	pub fn shading_basis(&self, normal: algebra::Vector) -> algebra::Basis {
+		self.material.tangent_basis(normal, self.tangent)
+	}
 }
 
 #[derive(Clone, Copy, PartialEq)]
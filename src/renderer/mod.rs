@@ -2,6 +2,7 @@ use crate::algebra;
 use crate::camera;
 use crate::constants;
 use crate::materials;
+use crate::media;
 use crate::output;
 use crate::primitives;
 use crate::ray;
@@ -17,10 +18,184 @@ pub struct Renderer {
 	pub chunk_size_exp: u32,
 	pub lights: Vec<usize>,
 	pub max_depth: u32,
+	pub observer: Observer,
+	pub spectral: SpectralConfig,
+}
+
+// standard observer whose color matching functions convert spectra to XYZ
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Observer {
+	Cie1931TwoDegree,
+	Cie1964TenDegree,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WavelengthSampling {
+	Uniform,
+	// proportional to approximate luminous efficiency function
+	// source: https://pbr-book.org/4ed/Radiometry,_Spectra,_and_Color/Color#x1-SamplingtheVisibleWavelengths
+	Luminance,
+}
+
+// range of traced wavelengths (in meters) and distribution they are sampled from
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SpectralConfig {
+	pub lambda_min: algebra::Scalar,
+	pub lambda_max: algebra::Scalar,
+	pub sampling: WavelengthSampling,
 }
 
 type RawPixel = (algebra::Scalar, algebra::Scalar, algebra::Scalar);
 
+impl SpectralConfig {
+	pub fn new(
+		lambda_min: algebra::Scalar,
+		lambda_max: algebra::Scalar,
+		sampling: WavelengthSampling,
+	) -> SpectralConfig {
+		if lambda_min <= 0.0 {
+			panic!(
+				"SpectralConfig property `lambda_min` must be greater than 0.0, got {}",
+				lambda_min
+			);
+		}
+		if lambda_max <= lambda_min {
+			panic!(
+				"SpectralConfig property `lambda_max` must be greater than `lambda_min`, got {}",
+				lambda_max
+			);
+		}
+		SpectralConfig {
+			lambda_min,
+			lambda_max,
+			sampling,
+		}
+	}
+
+	// hero wavelength with three companions equally spaced in sample space;
+	// returns wavelengths and their pdfs
+	// source: https://jo.dreggn.org/home/2014_herowavelength.pdf
+	pub fn sample_wavelengths(
+		&self,
+		u: algebra::Scalar,
+	) -> (algebra::WavelengthBunch, algebra::WavelengthBunch) {
+		let rotate = |j: algebra::Scalar| (u + 0.25 * j).fract();
+		let (l0, l1, l2, l3) = (
+			self.sample(rotate(0.0)),
+			self.sample(rotate(1.0)),
+			self.sample(rotate(2.0)),
+			self.sample(rotate(3.0)),
+		);
+		(
+			algebra::WavelengthBunch(l0, l1, l2, l3),
+			algebra::WavelengthBunch(self.pdf(l0), self.pdf(l1), self.pdf(l2), self.pdf(l3)),
+		)
+	}
+
+	pub fn sample(&self, u: algebra::Scalar) -> algebra::Scalar {
+		match self.sampling {
+			WavelengthSampling::Uniform => {
+				self.lambda_min + u * (self.lambda_max - self.lambda_min)
+			}
+			WavelengthSampling::Luminance => {
+				let (t_min, t_max) = self.luminance_bounds();
+				let lambda = LUMINANCE_PEAK
+					+ (t_min + u * (t_max - t_min)).atanh() / LUMINANCE_WIDTH;
+				lambda.clamp(self.lambda_min, self.lambda_max)
+			}
+		}
+	}
+
+	// probability density of sampling `lambda`, with respect to wavelength in meters
+	pub fn pdf(&self, lambda: algebra::Scalar) -> algebra::Scalar {
+		if lambda < self.lambda_min || lambda > self.lambda_max {
+			return 0.0;
+		}
+		match self.sampling {
+			WavelengthSampling::Uniform => 1.0 / (self.lambda_max - self.lambda_min),
+			WavelengthSampling::Luminance => {
+				let (t_min, t_max) = self.luminance_bounds();
+				LUMINANCE_WIDTH
+					/ (t_max - t_min)
+					/ (LUMINANCE_WIDTH * (lambda - LUMINANCE_PEAK)).cosh().powi(2)
+			}
+		}
+	}
+
+	fn luminance_bounds(&self) -> (algebra::Scalar, algebra::Scalar) {
+		(
+			(LUMINANCE_WIDTH * (self.lambda_min - LUMINANCE_PEAK)).tanh(),
+			(LUMINANCE_WIDTH * (self.lambda_max - LUMINANCE_PEAK)).tanh(),
+		)
+	}
+}
+
+impl Default for SpectralConfig {
+	// full range of the color matching functions
+	fn default() -> SpectralConfig {
+		SpectralConfig::new(360.0e-9, 830.0e-9, WavelengthSampling::Luminance)
+	}
+}
+
+const RAY_OFFSET: algebra::Scalar = 1e-9;
+
+// scattering events allowed on a single walk through a medium; `max_depth` only limits
+// bounces on surfaces, so random walks are not cut short by it
+const MAX_MEDIUM_STEPS: u32 = 1024;
+
+// transmissive objects enclosing a path; the one with the highest priority, or the latest
+// entered among equal priorities, is the medium the path travels in
+// source: https://doi.org/10.1080/10867651.2002.10487555
+#[derive(Clone, Default)]
+struct MediumStack<'a> {
+	objects: Vec<&'a primitives::Primitive>,
+}
+
+impl<'a> MediumStack<'a> {
+	fn top(&self) -> std::option::Option<&'a primitives::Primitive> {
+		self.objects
+			.iter()
+			.copied()
+			.max_by_key(|object| object.material.priority)
+	}
+
+	fn with(&self, object: &'a primitives::Primitive) -> MediumStack<'a> {
+		let mut objects = self.objects.clone();
+		objects.push(object);
+		MediumStack { objects }
+	}
+
+	fn without(&self, object: &primitives::Primitive) -> MediumStack<'a> {
+		let mut objects = self.objects.clone();
+		if let std::option::Option::Some(index) =
+			objects.iter().rposition(|o| std::ptr::eq(*o, object))
+		{
+			objects.remove(index);
+		}
+		MediumStack { objects }
+	}
+
+	// entering or leaving `object` is invisible while the path is inside a medium
+	// of higher priority
+	fn is_false_hit(&self, object: &primitives::Primitive, entering: bool) -> bool {
+		let surrounding = if entering {
+			self.top()
+		} else {
+			self.without(object).top()
+		};
+		match surrounding {
+			std::option::Option::Some(medium) => {
+				medium.material.priority > object.material.priority
+			}
+			std::option::Option::None => false,
+		}
+	}
+}
+
+// parameters of luminance sampling density 1 / cosh^2(width * (lambda - peak))
+const LUMINANCE_PEAK: algebra::Scalar = 538.0e-9;
+const LUMINANCE_WIDTH: algebra::Scalar = 0.0072e9;
+
 impl Renderer {
 	pub fn render(&mut self) {
 		// list all lights in the scene
@@ -72,16 +247,14 @@ impl Renderer {
 		let mut camera_plane_vector: algebra::Vector;
 		let mut rand_x: algebra::Scalar;
 		let mut rand_y: algebra::Scalar;
-		let mut wavelength: algebra::Scalar;
 		let mut wavelength_bunch: algebra::WavelengthBunch;
+		let mut wavelength_pdf: algebra::WavelengthBunch;
 		let mut radiance = algebra::WavelengthBunch(0.0, 0.0, 0.0, 0.0);
 		let mut temp_color: RawPixel;
 		let camera_samples = sampler.random_list_2d(self.aa_samples, -1.0, 1.0);
-		let wavelength_samples = sampler.random_list_1d(self.aa_samples, 360.0e-9, 650.0e-9);
-		// source: https://jo.dreggn.org/home/2014_herowavelength.pdf
-		let rot_func = |lambda, j| {
-			((lambda * 1e9 - 360.0 + j / 4.0 * 290.0) as i32 % 290 + 360) as algebra::Scalar * 1e-9
-		};
+		let wavelength_samples = sampler.random_list_1d(self.aa_samples, 0.0, 1.0);
+		// output is normalized so that spectrum equal to 1.0 everywhere has Y equal to 1.0
+		let y_integral = self.luminance_integral();
 
 		for i in 0..self.aa_samples {
 			// generate camera ray
@@ -95,20 +268,28 @@ impl Renderer {
 				ray::Ray::new(algebra::Vector::new(0.0, 0.0, 0.0), camera_plane_vector);
 
 			// integrate
-			wavelength = wavelength_samples[i];
-			wavelength_bunch = algebra::WavelengthBunch(
-				wavelength,
-				rot_func(wavelength, 1.0),
-				rot_func(wavelength, 2.0),
-				rot_func(wavelength, 3.0),
+			(wavelength_bunch, wavelength_pdf) =
+				self.spectral.sample_wavelengths(wavelength_samples[i]);
+			radiance = self.integrate(
+				primary_ray,
+				self.max_depth,
+				wavelength_bunch,
+				&mut sampler,
+				std::option::Option::None,
+				false,
+				&MediumStack::default(),
 			);
-			radiance = self.integrate(primary_ray, self.max_depth, wavelength_bunch, &mut sampler);
 
-			// compute color
-			let tc0 = self.wavelength_to_xyz(wavelength_bunch.0);
-			let tc1 = self.wavelength_to_xyz(wavelength_bunch.1);
-			let tc2 = self.wavelength_to_xyz(wavelength_bunch.2);
-			let tc3 = self.wavelength_to_xyz(wavelength_bunch.3);
+			// compute color, each wavelength weighted by its inverse pdf
+			let weighted_xyz = |lambda: algebra::Scalar, pdf: algebra::Scalar| {
+				let xyz = self.wavelength_to_xyz(lambda);
+				let w = if pdf > 0.0 { 1.0 / (pdf * y_integral) } else { 0.0 };
+				(xyz.0 * w, xyz.1 * w, xyz.2 * w)
+			};
+			let tc0 = weighted_xyz(wavelength_bunch.0, wavelength_pdf.0);
+			let tc1 = weighted_xyz(wavelength_bunch.1, wavelength_pdf.1);
+			let tc2 = weighted_xyz(wavelength_bunch.2, wavelength_pdf.2);
+			let tc3 = weighted_xyz(wavelength_bunch.3, wavelength_pdf.3);
 			temp_color = (
 				(tc0.0 * radiance.0 + tc1.0 * radiance.1 + tc2.0 * radiance.2 + tc3.0 * radiance.3)
 					/ 4.0,
@@ -127,148 +308,388 @@ impl Renderer {
 		output_color
 	}
 
+	// `bsdf_pdf` is the solid angle pdf the ray was sampled with, used to weight
+	// background radiance against its direct light samples; `None` for camera and delta rays;
+	// `media` holds transmissive objects the ray travels inside
+	#[allow(clippy::too_many_arguments)]
 	fn integrate(
 		&self,
 		ray: ray::Ray,
 		depth: u32,
 		wavelengths: algebra::WavelengthBunch,
 		sampler: &mut sampler::Sampler,
+		bsdf_pdf: std::option::Option<algebra::Scalar>,
+		dispersed: bool,
+		media: &MediumStack,
 	) -> algebra::WavelengthBunch {
-		// find closest intersection
-		let closest_obj: std::option::Option<&primitives::Primitive>;
-		let intersection: algebra::Vector;
-		let normal: algebra::Vector;
-		(closest_obj, intersection, normal) =
-			self.find_intersection(&ray, algebra::Scalar::EPSILON, algebra::Scalar::INFINITY);
+		let black = algebra::WavelengthBunch(0.0, 0.0, 0.0, 0.0);
+		let (mut ray, mut bsdf_pdf) = (ray, bsdf_pdf);
+		let mut walk = Walk::new(dispersed);
+		let mut steps = 0;
+		let medium = match media.top() {
+			std::option::Option::Some(object) => object.material.medium.as_ref(),
+			std::option::Option::None => self.scene.medium.as_ref(),
+		};
+		// scattering inside medium is followed iteratively, random walks can be long
+		loop {
+			// find closest intersection
+			let hit =
+				self.find_intersection(&ray, algebra::Scalar::EPSILON, algebra::Scalar::INFINITY);
+			let medium = match medium {
+				std::option::Option::Some(medium) => medium,
+				std::option::Option::None => {
+					return self.integrate_surface(
+						ray,
+						hit,
+						depth,
+						wavelengths,
+						sampler,
+						bsdf_pdf,
+						dispersed,
+						media,
+					);
+				}
+			};
+			let distance = match hit.0 {
+				std::option::Option::Some(_) => (hit.1 - ray.orig).norm(),
+				std::option::Option::None => algebra::Scalar::INFINITY,
+			};
+			let event = if medium.is_homogeneous() {
+				sample_homogeneous(medium, &ray, distance, wavelengths, dispersed, sampler)
+			} else {
+				sample_heterogeneous(medium, &ray, distance, wavelengths, dispersed, sampler)
+			};
+			match event {
+				MediumEvent::Absorbed => return black,
+				MediumEvent::Reached(transport, pdf) => {
+					let weight = match walk.extend(transport, pdf) {
+						std::option::Option::Some(weight) => weight,
+						std::option::Option::None => return black,
+					};
+					let radiance = self.integrate_surface(
+						ray,
+						hit,
+						depth,
+						wavelengths,
+						sampler,
+						bsdf_pdf,
+						dispersed,
+						media,
+					);
+					return weight * radiance;
+				}
+				MediumEvent::Scattered(point, transport, pdf) => {
+					if steps == MAX_MEDIUM_STEPS || walk.extend(transport, pdf).is_none() {
+						return black;
+					}
+					// phase function is sampled exactly, so it cancels out with its pdf;
+					// shadow rays are blocked by boundaries of the medium, so lights are only
+					// reached by continuing the path
+					let random = sampler.random_list_2d(1, 0.0, 1.0)[0];
+					ray = ray::Ray::new(point, medium.sample_phase(ray.dir.normalize(), random));
+					steps += 1;
+					bsdf_pdf = std::option::Option::None;
+				}
+			}
+		}
+	}
+
+	// radiance arriving along `ray` from surface `hit` or from background if nothing was hit
+	#[allow(clippy::too_many_arguments)]
+	fn integrate_surface(
+		&self,
+		ray: ray::Ray,
+		hit: (std::option::Option<&primitives::Primitive>, algebra::Vector, algebra::Vector),
+		depth: u32,
+		wavelengths: algebra::WavelengthBunch,
+		sampler: &mut sampler::Sampler,
+		bsdf_pdf: std::option::Option<algebra::Scalar>,
+		dispersed: bool,
+		media: &MediumStack,
+	) -> algebra::WavelengthBunch {
+		let (closest_obj, intersection, normal) = hit;
 
 		match closest_obj {
-			std::option::Option::None => algebra::WavelengthBunch(
-				self.scene
-					.background
-					.return_radiance(ray.dir, wavelengths.0),
-				self.scene
-					.background
-					.return_radiance(ray.dir, wavelengths.1),
-				self.scene
-					.background
-					.return_radiance(ray.dir, wavelengths.2),
-				self.scene
-					.background
-					.return_radiance(ray.dir, wavelengths.3),
-			),
+			std::option::Option::None => {
+				let weight = match bsdf_pdf {
+					std::option::Option::Some(pdf) if self.scene.background.is_sampled() => {
+						power_heuristic(pdf, self.scene.background.pdf(ray.dir))
+					}
+					_ => 1.0,
+				};
+				let background = &self.scene.background;
+				algebra::WavelengthBunch(
+					background.return_radiance(ray.dir, wavelengths.0) * weight,
+					background.return_radiance(ray.dir, wavelengths.1) * weight,
+					background.return_radiance(ray.dir, wavelengths.2) * weight,
+					background.return_radiance(ray.dir, wavelengths.3) * weight,
+				)
+			}
 			std::option::Option::Some(object) => {
-			let mut radiance = algebra::WavelengthBunch(0.0, 0.0, 0.0, 0.0);
-			//	let mut radiance = algebra::WavelengthBunch(
-			//		object.material.return_emission_radiance(wavelengths.0),
-			//		object.material.return_emission_radiance(wavelengths.1),
-			//		object.material.return_emission_radiance(wavelengths.2),
-			//		object.material.return_emission_radiance(wavelengths.3),
-			//	);
+				let mut radiance = algebra::WavelengthBunch(0.0, 0.0, 0.0, 0.0);
+				let transmissive = object.material.is_transmissive();
+				let front_face = ray.dir * normal < 0.0;
+				// boundaries of objects with lower priority than the medium the ray is in
+				// are not real interfaces, the ray passes them unchanged
+				if transmissive && media.is_false_hit(object, front_face) {
+					let next_media = if front_face {
+						media.with(object)
+					} else {
+						media.without(object)
+					};
+					let next_ray =
+						ray::Ray::new(offset_origin(intersection, normal, ray.dir), ray.dir);
+					return self.integrate(
+						next_ray,
+						depth,
+						wavelengths,
+						sampler,
+						bsdf_pdf,
+						dispersed,
+						&next_media,
+					);
+				}
+				// medium on the side of the surface facing away from the normal is the object
+				// itself, on the other side it is the surrounding one
+				let exterior = if transmissive && !front_face {
+					media.without(object).top()
+				} else {
+					media.top()
+				};
+				let exterior_medium = match exterior {
+					std::option::Option::Some(medium) => medium.material.medium.as_ref(),
+					std::option::Option::None => self.scene.medium.as_ref(),
+				};
+				let exterior_ior = |lambda: algebra::Scalar| match exterior {
+					std::option::Option::Some(medium) => medium.material.return_ior(lambda),
+					std::option::Option::None => 1.0,
+				};
 				if depth > 0 {
-					let mut theta_o: algebra::Scalar;
-					let mut phi_o: algebra::Scalar;
-					let mut theta_i: algebra::Scalar;
-					let mut phi_i: algebra::Scalar;
-					let basis: algebra::Basis = object.material.new_basis(normal);
-					(theta_o, phi_o) =
-						basis.basis_to_spherical(basis.world_to_basis(-ray.dir.normalize()));
+					let emission = |incoming: algebra::Vector| {
+						let half_vec = (incoming - ray.dir).normalize();
+						let emitted = |lambda| {
+							object.material.return_emission_radiance(
+								incoming, -ray.dir, half_vec, normal, lambda,
+							)
+						};
+						algebra::WavelengthBunch(
+							emitted(wavelengths.0),
+							emitted(wavelengths.1),
+							emitted(wavelengths.2),
+							emitted(wavelengths.3),
+						)
+					};
+					let delta = object.material.has_delta_lobe();
+					let frame = object.shading_basis(normal);
+
+					// light reradiated at the hero wavelength is followed at the shorter one that
+					// excited it, the others are terminated as for dispersion; the lobes make up
+					// for the paths taken away from them
+					let shift = object.material.fluorescence_probability(wavelengths.0);
+					if shift > 0.0 && sampler.random_list_1d(1, 0.0, 1.0)[0] < shift {
+						let rand_rays: Vec<(f64, f64)> = sampler.random_list_2d(1, 0.0, 1.0);
+						let rand_excitation: Vec<f64> = sampler.random_list_1d(1, 0.0, 1.0);
+						let random = (rand_rays[0].0, rand_rays[0].1, rand_excitation[0]);
+						let reradiated = object.material.sample_fluorescence(
+							-ray.dir.normalize(),
+							&frame,
+							random,
+							wavelengths.0,
+						);
+						let sample = match reradiated {
+							std::option::Option::Some(sample) => sample,
+							std::option::Option::None => return emission(normal),
+						};
+						let direction = sample.direction;
+						let origin = offset_origin(intersection, normal, direction);
+						let lambda = sample.excitation;
+						let contrib = self.integrate(
+							ray::Ray::new(origin, direction),
+							depth - 1,
+							algebra::WavelengthBunch(lambda, lambda, lambda, lambda),
+							sampler,
+							std::option::Option::None,
+							true,
+							media,
+						);
+						let hero = if dispersed { 1.0 } else { 4.0 };
+						let weight = hero * sample.weight / shift;
+						return emission(direction)
+							+ algebra::WavelengthBunch(contrib.0 * weight, 0.0, 0.0, 0.0);
+					}
+					let lobes = 1.0 / (1.0 - shift);
+					let lobes = algebra::WavelengthBunch(lobes, lobes, lobes, lobes);
 
 					// pick random direction
 					let rand_rays: Vec<(f64, f64)> = sampler.random_list_2d(1, 0.0, 1.0);
-					(theta_i, phi_i) =
-						object
-							.material
-							.return_direction(theta_o, phi_o, rand_rays[0]);
-
-					let mut next_ray: ray::Ray = ray::Ray::new(
-						intersection,
-						basis.basis_to_world(basis.spherical_to_basis(theta_i, phi_i)),
-					);
-					let half_vec: algebra::Vector = (next_ray.dir - ray.dir).normalize();
-					let mut contrib = self.integrate(next_ray, depth - 1, wavelengths, sampler);
-				
-				radiance = algebra::WavelengthBunch(
-					object.material.return_emission_radiance(next_ray.dir, -ray.dir, half_vec, normal, wavelengths.0),
-					object.material.return_emission_radiance(next_ray.dir, -ray.dir, half_vec, normal, wavelengths.1),
-					object.material.return_emission_radiance(next_ray.dir, -ray.dir, half_vec, normal, wavelengths.2),
-					object.material.return_emission_radiance(next_ray.dir, -ray.dir, half_vec, normal, wavelengths.3),
-				);
-					
-					let mut surface_response = algebra::WavelengthBunch(
-						object.material.return_scatter_radiance(
-							next_ray.dir,
-							-ray.dir,
-							half_vec,
-							normal,
-							wavelengths.0,
-						),
-						object.material.return_scatter_radiance(
-							next_ray.dir,
-							-ray.dir,
-							half_vec,
-							normal,
-							wavelengths.1,
-						),
-						object.material.return_scatter_radiance(
-							next_ray.dir,
-							-ray.dir,
-							half_vec,
-							normal,
-							wavelengths.2,
-						),
-						object.material.return_scatter_radiance(
-							next_ray.dir,
-							-ray.dir,
-							half_vec,
-							normal,
-							wavelengths.3,
-						),
-					);
-					let mut sample_pdf_inv = algebra::WavelengthBunch(
-						1.0 / object.material.return_pdf(
-							next_ray.dir,
-							-ray.dir,
-							half_vec,
-							normal,
-							wavelengths.0,
-						),
-						1.0 / object.material.return_pdf(
-							next_ray.dir,
-							-ray.dir,
-							half_vec,
-							normal,
-							wavelengths.1,
-						),
-						1.0 / object.material.return_pdf(
-							next_ray.dir,
-							-ray.dir,
-							half_vec,
-							normal,
-							wavelengths.2,
-						),
-						1.0 / object.material.return_pdf(
-							next_ray.dir,
-							-ray.dir,
-							half_vec,
-							normal,
-							wavelengths.3,
-						),
-					);
-					let cos_theta_i = (normal * next_ray.dir).abs();
-					let cti = algebra::WavelengthBunch(
-						cos_theta_i,
-						cos_theta_i,
-						cos_theta_i,
-						cos_theta_i,
+					let rand_lobe: Vec<f64> = sampler.random_list_1d(1, 0.0, 1.0);
+					let random = (rand_rays[0].0, rand_rays[0].1, rand_lobe[0]);
+					let scatter = match object.material.sample(
+						-ray.dir.normalize(),
+						&frame,
+						random,
+						wavelengths,
+						&exterior_ior,
+					) {
+						std::option::Option::Some(scatter) => scatter,
+						// sampled direction carries no light, only direct lighting remains
+						std::option::Option::None => {
+							radiance = emission(normal);
+							if !delta {
+								radiance = radiance
+									+ lobes
+										* self.sample_background(
+											&object.material,
+											intersection,
+											-ray.dir,
+											&frame,
+											wavelengths,
+											sampler,
+											&exterior_ior,
+											exterior_medium,
+										);
+							}
+							return radiance;
+						}
+					};
+
+					let direction = scatter.direction;
+					let next_ray: ray::Ray =
+						ray::Ray::new(offset_origin(intersection, normal, direction), direction);
+					let next_pdf = if scatter.delta {
+						std::option::Option::None
+					} else {
+						std::option::Option::Some(scatter.pdf)
+					};
+					// crossing the surface moves the path into or out of the object
+					let transmitted =
+						transmissive && (next_ray.dir * normal) * (ray.dir * normal) > 0.0;
+					let next_media = match (transmitted, front_face) {
+						(true, true) => media.with(object),
+						(true, false) => media.without(object),
+						_ => media.clone(),
+					};
+					// direction scattered for hero wavelength is only valid for the hero, so the
+					// others are terminated and the hero carries the whole sample
+					// source: https://jo.dreggn.org/home/2014_herowavelength.pdf
+					let disperse = !dispersed
+						&& transmissive
+						&& (object.material.is_dispersive()
+							|| exterior.is_some_and(|medium| medium.material.is_dispersive()));
+					let mut contrib = self.integrate(
+						next_ray,
+						depth - 1,
+						wavelengths,
+						sampler,
+						next_pdf,
+						dispersed || disperse,
+						&next_media,
 					);
-					contrib = contrib * surface_response * sample_pdf_inv * cti;
+					if disperse {
+						contrib = contrib * algebra::WavelengthBunch(4.0, 0.0, 0.0, 0.0);
+					}
+
+					radiance = emission(next_ray.dir);
+
+					contrib = contrib * scatter.weight * lobes;
 					radiance = radiance + contrib;
+					if !delta {
+						radiance = radiance
+							+ lobes
+								* self.sample_background(
+									&object.material,
+									intersection,
+									-ray.dir,
+									&frame,
+									wavelengths,
+									sampler,
+									&exterior_ior,
+									exterior_medium,
+								);
+					}
 				}
 				radiance
 			}
 		}
 	}
 
+	// direct lighting from importance sampled background, weighted against BSDF sampling;
+	// `frame` is shading frame at `point` with the geometric normal as its w axis and
+	// `exterior_ior` gives index of refraction of the medium around the surface and `medium`
+	// its participating medium, if any
+	#[allow(clippy::too_many_arguments)]
+	fn sample_background(
+		&self,
+		material: &materials::Material,
+		point: algebra::Vector,
+		outgoing: algebra::Vector,
+		frame: &algebra::Basis,
+		wavelengths: algebra::WavelengthBunch,
+		sampler: &mut sampler::Sampler,
+		exterior_ior: &dyn Fn(algebra::Scalar) -> algebra::Scalar,
+		medium: std::option::Option<&media::Medium>,
+	) -> algebra::WavelengthBunch {
+		let normal = frame.w;
+		let black = algebra::WavelengthBunch(0.0, 0.0, 0.0, 0.0);
+		let background = &self.scene.background;
+		let random = sampler.random_list_2d(1, 0.0, 1.0)[0];
+		let (light_dir, light_pdf) = match background.sample_direction(random) {
+			std::option::Option::Some(sample) => sample,
+			std::option::Option::None => return black,
+		};
+		let cos_theta_i = (normal * light_dir).abs();
+		if light_pdf <= 0.0 || cos_theta_i == 0.0 {
+			return black;
+		}
+		let shadow_ray = ray::Ray::new(offset_origin(point, normal, light_dir), light_dir);
+		if self
+			.find_intersection(&shadow_ray, algebra::Scalar::EPSILON, algebra::Scalar::INFINITY)
+			.0
+			.is_some()
+		{
+			return black;
+		}
+		// unoccluded shadow ray still passes through the medium around the surface
+		let transmittance = match medium {
+			std::option::Option::Some(medium) => estimate_transmittance(
+				medium,
+				&shadow_ray,
+				algebra::Scalar::INFINITY,
+				wavelengths,
+				sampler,
+			),
+			std::option::Option::None => algebra::WavelengthBunch(1.0, 1.0, 1.0, 1.0),
+		};
+		let half_vec = (light_dir + outgoing).normalize();
+		let bsdf_pdf = material.return_pdf(
+			light_dir,
+			outgoing,
+			half_vec,
+			frame,
+			wavelengths.0,
+			exterior_ior(wavelengths.0),
+		);
+		let weight = power_heuristic(light_pdf, bsdf_pdf) * cos_theta_i / light_pdf;
+		let contrib = |lambda| {
+			background.return_radiance(light_dir, lambda)
+				* material.return_scatter_radiance(
+					light_dir,
+					outgoing,
+					half_vec,
+					frame,
+					lambda,
+					exterior_ior(lambda),
+				) * weight
+		};
+		transmittance
+			* algebra::WavelengthBunch(
+				contrib(wavelengths.0),
+				contrib(wavelengths.1),
+				contrib(wavelengths.2),
+				contrib(wavelengths.3),
+			)
+	}
+
 	fn find_intersection(
 		&self,
 		ray: &ray::Ray,
@@ -305,13 +726,38 @@ impl Renderer {
 		(closest_obj, intersection, normal)
 	}
 
-	// the algorithm assumes wavelengths out of range are invisible, therefore black
+	// the algorithm assumes wavelengths out of range are invisible, therefore black;
+	// color matching functions are linearly interpolated between tabulated values
 	fn wavelength_to_xyz(&self, lambda: algebra::Scalar) -> RawPixel {
-		let index = lambda * 1e9 - 360.0;
-		if index < 0.0 || index > 470.0 {
-			(0.0, 0.0, 0.0)
-		} else {
-			constants::CIE_XYZ_1931_COLOR_MATCH_2_DEG[index as usize]
+		let (table, lambda_min, step) = self.observer_table();
+		let index = (lambda * 1e9 - lambda_min) / step;
+		let last = (table.len() - 1) as algebra::Scalar;
+		if !(0.0..=last).contains(&index) {
+			return (0.0, 0.0, 0.0);
+		}
+		let i = (index as usize).min(table.len() - 2);
+		let t = index - i as algebra::Scalar;
+		let (a, b) = (table[i], table[i + 1]);
+		(
+			a.0 + (b.0 - a.0) * t,
+			a.1 + (b.1 - a.1) * t,
+			a.2 + (b.2 - a.2) * t,
+		)
+	}
+
+	// integral of y bar over wavelength in meters
+	fn luminance_integral(&self) -> algebra::Scalar {
+		let (table, _, step) = self.observer_table();
+		table.iter().map(|c| c.1).sum::<algebra::Scalar>() * step * 1e-9
+	}
+
+	// color matching functions with their first wavelength and step in nm
+	fn observer_table(&self) -> (&'static [RawPixel], algebra::Scalar, algebra::Scalar) {
+		match self.observer {
+			Observer::Cie1931TwoDegree => (&constants::CIE_XYZ_1931_COLOR_MATCH_2_DEG, 360.0, 1.0),
+			Observer::Cie1964TenDegree => {
+				(&constants::CIE_XYZ_1964_COLOR_MATCH_10_DEG, 360.0, 5.0)
+			}
 		}
 	}
 	// source:
@@ -324,3 +770,235 @@ impl Renderer {
 		)
 	}
 }
+
+// moves ray origin off the surface to the side of `direction`, so that the ray
+// does not intersect the surface it starts on
+fn offset_origin(
+	point: algebra::Vector,
+	normal: algebra::Vector,
+	direction: algebra::Vector,
+) -> algebra::Vector {
+	if normal * direction < 0.0 {
+		point - normal * RAY_OFFSET
+	} else {
+		point + normal * RAY_OFFSET
+	}
+}
+
+// source: https://pbr-book.org/3ed-2018/Monte_Carlo_Integration/Importance_Sampling#MultipleImportanceSampling
+fn power_heuristic(pdf_f: algebra::Scalar, pdf_g: algebra::Scalar) -> algebra::Scalar {
+	let f2 = pdf_f * pdf_f;
+	let g2 = pdf_g * pdf_g;
+	if f2 + g2 == 0.0 || f2.is_infinite() {
+		return 1.0;
+	}
+	f2 / (f2 + g2)
+}
+
+// outcome of free flight along ray segment inside participating medium; events carry transport
+// of each wavelength and pdf of sampling the event if that wavelength were the hero
+enum MediumEvent {
+	Absorbed,
+	// path scatters at given point
+	Scattered(algebra::Vector, algebra::WavelengthBunch, algebra::WavelengthBunch),
+	// path reaches the end of the segment
+	Reached(algebra::WavelengthBunch, algebra::WavelengthBunch),
+}
+
+// transport and pdfs of all wavelengths multiplied over the free flights of a random walk, so
+// that spectral MIS weighs whole walks rather than single steps, whose weights would multiply
+// without bound; both are rescaled after each step to keep the largest pdf equal to one
+// source: https://jo.dreggn.org/home/2014_herowavelength.pdf
+struct Walk {
+	transport: [algebra::Scalar; 4],
+	pdf: [algebra::Scalar; 4],
+	// only hero wavelength is left on dispersed paths
+	alive: usize,
+}
+
+impl Walk {
+	fn new(dispersed: bool) -> Walk {
+		Walk {
+			transport: [1.0; 4],
+			pdf: [1.0; 4],
+			alive: if dispersed { 1 } else { 4 },
+		}
+	}
+
+	// adds free flight event to the walk and returns weights of the walk so far, balance
+	// heuristic over wavelengths carried by the path; `None` if none of them could sample it
+	fn extend(
+		&mut self,
+		transport: algebra::WavelengthBunch,
+		pdf: algebra::WavelengthBunch,
+	) -> std::option::Option<algebra::WavelengthBunch> {
+		let transport = [transport.0, transport.1, transport.2, transport.3];
+		let pdf = [pdf.0, pdf.1, pdf.2, pdf.3];
+		for i in 0..4 {
+			self.transport[i] *= transport[i];
+			self.pdf[i] *= pdf[i];
+		}
+		let scale = self.pdf[..self.alive].iter().copied().fold(0.0, algebra::Scalar::max);
+		if scale <= 0.0 || !scale.is_finite() {
+			return std::option::Option::None;
+		}
+		for i in 0..4 {
+			self.transport[i] /= scale;
+			self.pdf[i] /= scale;
+		}
+		let mean = self.pdf[..self.alive].iter().sum::<algebra::Scalar>()
+			/ self.alive as algebra::Scalar;
+		let w = self.transport.map(|transport| transport / mean);
+		std::option::Option::Some(algebra::WavelengthBunch(w[0], w[1], w[2], w[3]))
+	}
+}
+
+// distance is sampled for hero wavelength only
+fn sample_homogeneous(
+	medium: &media::Medium,
+	ray: &ray::Ray,
+	distance: algebra::Scalar,
+	wavelengths: algebra::WavelengthBunch,
+	dispersed: bool,
+	sampler: &mut sampler::Sampler,
+) -> MediumEvent {
+	let direction = ray.dir.normalize();
+	let lambdas = [wavelengths.0, wavelengths.1, wavelengths.2, wavelengths.3];
+	// only hero wavelength is left on dispersed paths
+	let alive = if dispersed { 1 } else { 4 };
+	let bunch = |f: &dyn Fn(algebra::Scalar) -> algebra::Scalar| {
+		algebra::WavelengthBunch(f(lambdas[0]), f(lambdas[1]), f(lambdas[2]), f(lambdas[3]))
+	};
+	let scattering = lambdas[..alive].iter().any(|lambda| medium.sigma_s(*lambda) > 0.0);
+	let u = sampler.random_list_1d(1, 0.0, 1.0)[0];
+	let t = if scattering {
+		medium.sample_distance(lambdas[0], u)
+	} else {
+		// absorbing medium only attenuates, so the surface is always reached
+		algebra::Scalar::INFINITY
+	};
+	if t < distance {
+		let transport = bunch(&|lambda| medium.sigma_s(lambda) * medium.transmittance(t, lambda));
+		let pdf = bunch(&|lambda| medium.sigma_t(lambda) * medium.transmittance(t, lambda));
+		return MediumEvent::Scattered(ray.orig + t * direction, transport, pdf);
+	}
+	let transmittance = bunch(&|lambda| medium.transmittance(distance, lambda));
+	if !scattering {
+		return MediumEvent::Reached(transmittance, algebra::WavelengthBunch(1.0, 1.0, 1.0, 1.0));
+	}
+	MediumEvent::Reached(transmittance, transmittance)
+}
+
+// free flight through medium of varying density is sampled against piecewise constant
+// majorant; events are chosen with probabilities averaged over wavelengths carried by the
+// path and each wavelength is weighted by its own coefficients (spectral tracking)
+// source: https://doi.org/10.1145/3072959.3073665
+fn sample_heterogeneous(
+	medium: &media::Medium,
+	ray: &ray::Ray,
+	distance: algebra::Scalar,
+	wavelengths: algebra::WavelengthBunch,
+	dispersed: bool,
+	sampler: &mut sampler::Sampler,
+) -> MediumEvent {
+	let direction = ray.dir.normalize();
+	let lambdas = [wavelengths.0, wavelengths.1, wavelengths.2, wavelengths.3];
+	let alive = if dispersed { 1 } else { 4 };
+	let sigma_a = lambdas.map(|lambda| medium.sigma_a(lambda));
+	let sigma_s = lambdas.map(|lambda| medium.sigma_s(lambda));
+	let sigma_max = (0..alive)
+		.map(|i| sigma_a[i] + sigma_s[i])
+		.fold(0.0, algebra::Scalar::max);
+	let scattering = sigma_s[..alive].iter().any(|sigma| *sigma > 0.0);
+	let average = |values: [algebra::Scalar; 4]| {
+		values[..alive].iter().sum::<algebra::Scalar>() / alive as algebra::Scalar
+	};
+	let bunch = |w: [algebra::Scalar; 4]| algebra::WavelengthBunch(w[0], w[1], w[2], w[3]);
+	let mut weight = [1.0; 4];
+	for (start, end, density_max) in medium.majorant_segments(ray.orig, direction, distance) {
+		let majorant = density_max * sigma_max;
+		if majorant <= 0.0 {
+			continue;
+		}
+		let mut t = start;
+		loop {
+			let u = sampler.random_list_1d(1, 0.0, 1.0)[0];
+			t -= (1.0 - u).ln() / majorant;
+			if t >= end {
+				break;
+			}
+			let point = ray.orig + t * direction;
+			let density = medium.density(point);
+			let absorption = sigma_a.map(|sigma| sigma * density);
+			let scatter = sigma_s.map(|sigma| sigma * density);
+			let null = [0, 1, 2, 3].map(|i| majorant - absorption[i] - scatter[i]);
+			if !scattering {
+				// ratio tracking estimates transmittance without terminating the path
+				for i in 0..4 {
+					weight[i] *= null[i] / majorant;
+				}
+				continue;
+			}
+			let p_absorb = average(absorption) / majorant;
+			let p_scatter = average(scatter) / majorant;
+			let p_null = average(null) / majorant;
+			let event = sampler.random_list_1d(1, 0.0, 1.0)[0];
+			if event < p_absorb {
+				return MediumEvent::Absorbed;
+			} else if event < p_absorb + p_scatter {
+				for i in 0..4 {
+					weight[i] *= scatter[i] / (majorant * p_scatter);
+				}
+				return MediumEvent::Scattered(point, bunch(weight), bunch([1.0; 4]));
+			} else if p_null > 0.0 {
+				for i in 0..4 {
+					weight[i] *= null[i] / (majorant * p_null);
+				}
+			} else {
+				return MediumEvent::Absorbed;
+			}
+		}
+	}
+	MediumEvent::Reached(bunch(weight), bunch([1.0; 4]))
+}
+
+// fraction of light passing along the ray up to `distance` through medium, exact for
+// homogeneous one and estimated by ratio tracking for varying density
+// source: https://doi.org/10.1145/3072959.3073665
+fn estimate_transmittance(
+	medium: &media::Medium,
+	ray: &ray::Ray,
+	distance: algebra::Scalar,
+	wavelengths: algebra::WavelengthBunch,
+	sampler: &mut sampler::Sampler,
+) -> algebra::WavelengthBunch {
+	let lambdas = [wavelengths.0, wavelengths.1, wavelengths.2, wavelengths.3];
+	if medium.is_homogeneous() {
+		let t = lambdas.map(|lambda| medium.transmittance(distance, lambda));
+		return algebra::WavelengthBunch(t[0], t[1], t[2], t[3]);
+	}
+	let direction = ray.dir.normalize();
+	let sigma_t = lambdas.map(|lambda| medium.sigma_t(lambda));
+	let sigma_max = sigma_t.iter().copied().fold(0.0, algebra::Scalar::max);
+	let mut weight = [1.0; 4];
+	for (start, end, density_max) in medium.majorant_segments(ray.orig, direction, distance) {
+		let majorant = density_max * sigma_max;
+		if majorant <= 0.0 {
+			continue;
+		}
+		let mut t = start;
+		// nothing is left to attenuate once every wavelength is blocked
+		while weight.iter().any(|w| *w > 0.0) {
+			let u = sampler.random_list_1d(1, 0.0, 1.0)[0];
+			t -= (1.0 - u).ln() / majorant;
+			if t >= end {
+				break;
+			}
+			let density = medium.density(ray.orig + t * direction);
+			for i in 0..4 {
+				weight[i] *= 1.0 - sigma_t[i] * density / majorant;
+			}
+		}
+	}
+	algebra::WavelengthBunch(weight[0], weight[1], weight[2], weight[3])
+}
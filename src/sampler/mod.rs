@@ -34,3 +34,118 @@ impl Sampler {
 		v
 	}
 }
+
+// piecewise-constant distributions over [0, 1) and [0, 1)^2
+// source: https://pbr-book.org/3ed-2018/Monte_Carlo_Integration/Sampling_Random_Variables
+pub struct Distribution1D {
+	func: Vec<algebra::Scalar>,
+	cdf: Vec<algebra::Scalar>,
+	pub integral: algebra::Scalar,
+}
+
+impl Distribution1D {
+	pub fn new(func: Vec<algebra::Scalar>) -> Distribution1D {
+		let n = func.len();
+		if n == 0 {
+			panic!("Distribution1D must be built from at least one value");
+		}
+		let mut cdf: Vec<algebra::Scalar> = vec![0.0; n + 1];
+		for i in 1..=n {
+			cdf[i] = cdf[i - 1] + func[i - 1].abs() / n as algebra::Scalar;
+		}
+		let integral = cdf[n];
+		// degenerate function falls back to uniform distribution
+		for (i, c) in cdf.iter_mut().enumerate().skip(1) {
+			if integral == 0.0 {
+				*c = i as algebra::Scalar / n as algebra::Scalar;
+			} else {
+				*c /= integral;
+			}
+		}
+		Distribution1D {
+			func,
+			cdf,
+			integral,
+		}
+	}
+
+	pub fn count(&self) -> usize {
+		self.func.len()
+	}
+
+	// returns continuous sample in [0, 1), its pdf and index of the sampled segment
+	pub fn sample(&self, u: algebra::Scalar) -> (algebra::Scalar, algebra::Scalar, usize) {
+		// find last cdf entry lesser than or equal to u
+		let offset = self
+			.cdf
+			.partition_point(|&c| c <= u)
+			.clamp(1, self.count())
+			- 1;
+		let mut du = u - self.cdf[offset];
+		let width = self.cdf[offset + 1] - self.cdf[offset];
+		if width > 0.0 {
+			du /= width;
+		}
+		(
+			(offset as algebra::Scalar + du) / self.count() as algebra::Scalar,
+			self.pdf_at(offset),
+			offset,
+		)
+	}
+
+	pub fn pdf(&self, x: algebra::Scalar) -> algebra::Scalar {
+		let offset = ((x * self.count() as algebra::Scalar) as usize).min(self.count() - 1);
+		self.pdf_at(offset)
+	}
+
+	fn pdf_at(&self, offset: usize) -> algebra::Scalar {
+		if self.integral == 0.0 {
+			1.0
+		} else {
+			self.func[offset].abs() / self.integral
+		}
+	}
+}
+
+pub struct Distribution2D {
+	conditional: Vec<Distribution1D>,
+	marginal: Distribution1D,
+}
+
+impl Distribution2D {
+	// `func` is given row by row, `nu` values per row and `nv` rows
+	pub fn new(func: &[algebra::Scalar], nu: usize, nv: usize) -> Distribution2D {
+		if func.len() != nu * nv {
+			panic!(
+				"Distribution2D expected {} values, got {}",
+				nu * nv,
+				func.len()
+			);
+		}
+		let conditional: Vec<Distribution1D> = func
+			.chunks(nu)
+			.map(|row| Distribution1D::new(row.to_vec()))
+			.collect();
+		let marginal = Distribution1D::new(conditional.iter().map(|d| d.integral).collect());
+		Distribution2D {
+			conditional,
+			marginal,
+		}
+	}
+
+	// returns sample (u, v) in [0, 1)^2 and its pdf with respect to area of the unit square
+	pub fn sample(
+		&self,
+		random: (algebra::Scalar, algebra::Scalar),
+	) -> ((algebra::Scalar, algebra::Scalar), algebra::Scalar) {
+		let (v, pdf_v, row) = self.marginal.sample(random.1);
+		let (u, pdf_u, _) = self.conditional[row].sample(random.0);
+		((u, v), pdf_u * pdf_v)
+	}
+
+	pub fn pdf(&self, point: (algebra::Scalar, algebra::Scalar)) -> algebra::Scalar {
+		let nv = self.marginal.count();
+		let row = ((point.1 * nv as algebra::Scalar) as usize).min(nv - 1);
+		self.conditional[row].pdf(point.0) * self.marginal.pdf(point.1)
+	}
+}
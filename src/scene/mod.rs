@@ -1,11 +1,26 @@
 use crate::algebra;
 use crate::camera;
-use crate::constants;
+use crate::environment;
+use crate::media;
 use crate::primitives;
+use crate::spectrum;
 
 pub enum BackgroundType {
 	BlackBody(algebra::Scalar),
 	BlackBodyNormalized(algebra::Scalar),
+	// radiance of the map is scaled by `Background::radiance`
+	EnvironmentMap(environment::EnvironmentMap),
+	// radiance of the sky and sun is scaled by `Background::radiance`
+	Sky(environment::Sky),
+	// uniform spectrum scaled by `Background::radiance`
+	Constant(spectrum::Spectrum),
+	// spectrum blended from nadir to zenith along z axis, scaled by `Background::radiance`
+	Gradient {
+		bottom: spectrum::Spectrum,
+		top: spectrum::Spectrum,
+	},
+	// standard illuminant with luminance equal to `Background::radiance`
+	Illuminant(environment::Illuminant),
 }
 
 pub struct Background {
@@ -19,19 +34,54 @@ impl Background {
 		dir: algebra::Vector,
 		lambda: algebra::Scalar,
 	) -> algebra::Scalar {
-		match self.color {
+		match &self.color {
 			BackgroundType::BlackBody(temperature) => {
-				constants::TWO_HC2
-					/ (lambda.powi(5) * ((constants::HC_BY_K / lambda / temperature).exp() - 1.0))
+				spectrum::Spectrum::BlackBody(*temperature).evaluate(lambda)
 			}
 			BackgroundType::BlackBodyNormalized(temperature) => {
-				let lmax: algebra::Scalar = constants::WIEN / temperature;
-				constants::TWO_HC2
-					/ (lambda.powi(5) * ((constants::HC_BY_K / lambda / temperature).exp() - 1.0))
-					/ (constants::TWO_HC2
-						/ (lmax.powi(5) * ((constants::HC_BY_K / lmax / temperature).exp() - 1.0)))
+				spectrum::Spectrum::BlackBodyNormalized(*temperature).evaluate(lambda)
 					* self.radiance
 			}
+			BackgroundType::EnvironmentMap(map) => map.return_radiance(dir, lambda) * self.radiance,
+			BackgroundType::Sky(sky) => sky.return_radiance(dir, lambda) * self.radiance,
+			BackgroundType::Constant(color) => color.evaluate(lambda) * self.radiance,
+			BackgroundType::Gradient { bottom, top } => {
+				let t = (0.5 * (dir.normalize().z + 1.0)).clamp(0.0, 1.0);
+				(bottom.evaluate(lambda) * (1.0 - t) + top.evaluate(lambda) * t) * self.radiance
+			}
+			BackgroundType::Illuminant(illuminant) => {
+				illuminant.return_radiance(lambda, self.radiance)
+			}
+		}
+	}
+
+	// only backgrounds with directional variation are importance sampled,
+	// others are left to be hit by rays sampled from BSDFs
+	pub fn is_sampled(&self) -> bool {
+		matches!(
+			self.color,
+			BackgroundType::EnvironmentMap(_) | BackgroundType::Sky(_)
+		)
+	}
+
+	pub fn sample_direction(
+		&self,
+		random: (algebra::Scalar, algebra::Scalar),
+	) -> std::option::Option<(algebra::Vector, algebra::Scalar)> {
+		match &self.color {
+			BackgroundType::EnvironmentMap(map) => {
+				std::option::Option::Some(map.sample_direction(random))
+			}
+			BackgroundType::Sky(sky) => std::option::Option::Some(sky.sample_direction(random)),
+			_ => std::option::Option::None,
+		}
+	}
+
+	pub fn pdf(&self, dir: algebra::Vector) -> algebra::Scalar {
+		match &self.color {
+			BackgroundType::EnvironmentMap(map) => map.pdf(dir),
+			BackgroundType::Sky(sky) => sky.pdf(dir),
+			_ => 0.0,
 		}
 	}
 }
@@ -40,4 +90,6 @@ pub struct Scene {
 	pub objects: Vec<primitives::Primitive>,
 	pub camera: camera::Camera,
 	pub background: Background,
+	// participating medium filling the space outside of all transparent objects
+	pub medium: std::option::Option<media::Medium>,
 }
@@ -1,280 +1,1911 @@
 use crate::algebra;
 use crate::constants;
-use std::cmp;
-
-pub type Color = Vec<algebra::Scalar>;
-
-pub enum Lobe {
-	Cosine,
-	DeltaReflect,
-	GGX_reflect { alpha: algebra::Scalar },
-	DeltaRefract,
-}
-
-#[derive(Clone, PartialEq)]
-pub enum BxDF {
-	OrenNayar {
-		a: algebra::Scalar,
-		b: algebra::Scalar,
-		color: Color,
-	},
-	Specular {},
-	SpecularRefract {},
-	GGX_reflect {
-		alpha: algebra::Scalar,
-		alpha2: algebra::Scalar,
-	},
-}
-
-impl BxDF {
-	pub fn oren_nayar(sigma: algebra::Scalar, color: Color) -> BxDF {
-		let sigma2;
-		if sigma * sigma > 1.0 {
-			sigma2 = 1.0;
-		} else if sigma * sigma < 0.0 {
-			sigma2 = 0.0;
-		} else {
-			sigma2 = sigma * sigma;
-		}
-		BxDF::OrenNayar {
+use crate::spectrum;
+use std::ops;
+use std::sync;
+
+// below this GGX roughness microfacet surfaces are treated as perfectly smooth
+const SMOOTH_ALPHA: algebra::Scalar = 1e-3;
+
+// multiple scattering is neglected when microfacets lose less energy than this
+const MIN_ENERGY_LOSS: algebra::Scalar = 1e-6;
+
+// stratification of visible normals per axis when computing albedo tables
+const ALBEDO_STRATA: usize = 32;
+
+// dielectric albedo table covers relative index of refraction from its inverse to it
+const MAX_TABLE_ETA: algebra::Scalar = 3.0;
+
+// kinds of scattering performed by a lobe, combined with `|`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct LobeFlags(u8);
+
+impl LobeFlags {
+	pub const REFLECTION: LobeFlags = LobeFlags(1);
+	pub const TRANSMISSION: LobeFlags = LobeFlags(2);
+	pub const DIFFUSE: LobeFlags = LobeFlags(4);
+	pub const GLOSSY: LobeFlags = LobeFlags(8);
+	// scattering into discrete directions described by delta distribution
+	pub const SPECULAR: LobeFlags = LobeFlags(16);
+
+	pub fn contains(self, other: LobeFlags) -> bool {
+		self.0 & other.0 == other.0
+	}
+
+	pub fn is_specular(self) -> bool {
+		self.contains(LobeFlags::SPECULAR)
+	}
+}
+
+impl ops::BitOr for LobeFlags {
+	type Output = LobeFlags;
+
+	fn bitor(self, other: LobeFlags) -> LobeFlags {
+		LobeFlags(self.0 | other.0)
+	}
+}
+
+// quantities a BSDF may depend on besides directions
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ShadingContext {
+	// wavelength in meters
+	pub lambda: algebra::Scalar,
+	// complex index of refraction eta + ik of the material relative to surrounding medium
+	pub eta: algebra::Scalar,
+	pub k: algebra::Scalar,
+}
+
+// sampled incoming direction with everything needed to weight it
+#[derive(Clone, Copy, Debug)]
+pub struct BxdfSample {
+	pub incoming: algebra::Vector,
+	pub value: algebra::Scalar,
+	pub pdf: algebra::Scalar,
+	pub flags: LobeFlags,
+}
+
+// bidirectional scattering distribution function in local shading frame with normal along
+// z axis; `incoming` points towards the light and `outgoing` towards the viewer, both away
+// from the surface; specular lobes return discrete probability of the sampled direction as pdf
+// and its value divided by cosine, so that the usual estimator value * cos / pdf holds
+pub trait Bxdf: Send + Sync {
+	fn flags(&self) -> LobeFlags;
+
+	fn eval(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &ShadingContext,
+	) -> algebra::Scalar;
+
+	// solid angle density of `sample` returning `incoming`
+	fn pdf(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &ShadingContext,
+	) -> algebra::Scalar;
+
+	// the first two random numbers pick direction, the third may choose between lobes;
+	// none if no light can arrive from the sampled direction
+	fn sample(
+		&self,
+		outgoing: algebra::Vector,
+		random: (f64, f64, f64),
+		context: &ShadingContext,
+	) -> std::option::Option<BxdfSample>;
+
+	// share of the light leaving along `outgoing` the lobe accounts for, materials choose
+	// between their lobes in proportion to it
+	fn weight(&self, _outgoing: algebra::Vector, _context: &ShadingContext) -> algebra::Scalar {
+		1.0
+	}
+}
+
+// rough diffuse reflection
+// source: https://pbr-book.org/3ed-2018/Reflection_Models/Microfacet_Models#OrenNayarDiffuseReflection
+#[derive(Clone, PartialEq, Debug)]
+pub struct OrenNayar {
+	a: algebra::Scalar,
+	b: algebra::Scalar,
+	color: spectrum::Spectrum,
+}
+
+impl OrenNayar {
+	// `sigma` is standard deviation of microfacet angle in radians
+	pub fn new(sigma: algebra::Scalar, color: spectrum::Spectrum) -> OrenNayar {
+		let sigma2 = (sigma * sigma).clamp(0.0, 1.0);
+		OrenNayar {
 			a: 1.0 - 0.5 * sigma2 / (sigma2 + 0.33),
 			b: 0.45 * sigma2 / (sigma2 + 0.09),
 			color,
 		}
 	}
+}
+
+impl Bxdf for OrenNayar {
+	fn flags(&self) -> LobeFlags {
+		LobeFlags::REFLECTION | LobeFlags::DIFFUSE
+	}
+
+	fn eval(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &ShadingContext,
+	) -> algebra::Scalar {
+		if incoming.z <= 0.0 || outgoing.z <= 0.0 {
+			return 0.0;
+		}
+		let sin_theta_i = (1.0 - incoming.z * incoming.z).max(0.0).sqrt();
+		let sin_theta_o = (1.0 - outgoing.z * outgoing.z).max(0.0).sqrt();
+		let mut max_cos: algebra::Scalar = 0.0;
+		if sin_theta_i > 1e-4 && sin_theta_o > 1e-4 {
+			let cos_phi_i = (incoming.x / sin_theta_i).clamp(-1.0, 1.0);
+			let sin_phi_i = (incoming.y / sin_theta_i).clamp(-1.0, 1.0);
+			let cos_phi_o = (outgoing.x / sin_theta_o).clamp(-1.0, 1.0);
+			let sin_phi_o = (outgoing.y / sin_theta_o).clamp(-1.0, 1.0);
+			max_cos = (cos_phi_i * cos_phi_o + sin_phi_i * sin_phi_o).max(0.0);
+		}
+		let (sin_alpha, tan_beta) = if incoming.z > outgoing.z {
+			(sin_theta_o, sin_theta_i / incoming.z)
+		} else {
+			(sin_theta_i, sin_theta_o / outgoing.z)
+		};
+		let color = self.color.evaluate(context.lambda).clamp(0.0, 1.0);
+		color * constants::PI_INV * (self.a + self.b * max_cos * sin_alpha * tan_beta)
+	}
+
+	fn pdf(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		_context: &ShadingContext,
+	) -> algebra::Scalar {
+		if incoming.z <= 0.0 || outgoing.z <= 0.0 {
+			return 0.0;
+		}
+		incoming.z * constants::PI_INV
+	}
+
+	fn sample(
+		&self,
+		outgoing: algebra::Vector,
+		random: (f64, f64, f64),
+		context: &ShadingContext,
+	) -> std::option::Option<BxdfSample> {
+		if outgoing.z <= 0.0 {
+			return std::option::Option::None;
+		}
+		let incoming = sample_cosine((random.0, random.1));
+		std::option::Option::Some(BxdfSample {
+			incoming,
+			value: self.eval(incoming, outgoing, context),
+			pdf: self.pdf(incoming, outgoing, context),
+			flags: self.flags(),
+		})
+	}
+}
+
+// anisotropic GGX (Trowbridge-Reitz) distribution of microfacet normals, `alpha_x` is roughness
+// along tangent (x axis) and `alpha_y` along bitangent (y axis) of the shading frame
+// source: https://jcgt.org/published/0003/02/03/paper.pdf
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Ggx {
+	alpha_x: algebra::Scalar,
+	alpha_y: algebra::Scalar,
+}
 
-	pub fn specular() -> BxDF {
-		BxDF::Specular {}
+impl Ggx {
+	// surface is perfectly smooth if both parameters are close to 0.0, otherwise they are
+	// kept away from 0.0 to keep the distribution finite
+	pub fn new(alpha_x: algebra::Scalar, alpha_y: algebra::Scalar) -> Ggx {
+		if alpha_x < 0.0 || alpha_y < 0.0 {
+			panic!("GGX roughness must not be negative");
+		}
+		if alpha_x.max(alpha_y) < SMOOTH_ALPHA {
+			return Ggx { alpha_x, alpha_y };
+		}
+		Ggx {
+			alpha_x: alpha_x.max(SMOOTH_ALPHA),
+			alpha_y: alpha_y.max(SMOOTH_ALPHA),
+		}
 	}
 
-	pub fn specular_refract() -> BxDF {
-		BxDF::SpecularRefract {}
+	// perceptually linear `roughness` is squared to get alpha
+	pub fn isotropic(roughness: algebra::Scalar) -> Ggx {
+		Ggx::new(roughness * roughness, roughness * roughness)
 	}
 
-	pub fn ggx_reflect(roughness: algebra::Scalar) -> BxDF {
-		BxDF::GGX_reflect {
-			alpha: roughness.powi(2),
-			alpha2: roughness.powi(4),
+	// `anisotropy` between -1.0 and 1.0 makes surface rougher along x axis when positive and
+	// along y axis when negative, keeping mean roughness
+	// source: https://media.disneyanimation.com/uploads/production/publication_asset/48/asset/s2012_pbs_disney_brdf_notes_v3.pdf
+	pub fn anisotropic(roughness: algebra::Scalar, anisotropy: algebra::Scalar) -> Ggx {
+		if !(-1.0..=1.0).contains(&anisotropy) {
+			panic!("anisotropy must lie between -1.0 and 1.0");
+		}
+		let alpha = roughness * roughness;
+		let aspect = (1.0 - 0.9 * anisotropy.abs()).sqrt();
+		if anisotropy >= 0.0 {
+			Ggx::new(alpha / aspect, alpha * aspect)
+		} else {
+			Ggx::new(alpha * aspect, alpha / aspect)
 		}
 	}
 
-	pub fn lobe(&self) -> Lobe {
-		match self {
-			BxDF::OrenNayar { .. } => Lobe::Cosine,
-			BxDF::Specular {} => Lobe::DeltaReflect,
-			BxDF::SpecularRefract { .. } => Lobe::DeltaRefract,
-			BxDF::GGX_reflect { alpha, .. } => Lobe::GGX_reflect { alpha: *alpha },
+	pub fn alpha(&self) -> (algebra::Scalar, algebra::Scalar) {
+		(self.alpha_x, self.alpha_y)
+	}
+
+	pub fn is_smooth(&self) -> bool {
+		self.alpha_x.max(self.alpha_y) < SMOOTH_ALPHA
+	}
+
+	// coordinate of albedo tables, anisotropic distributions are treated as isotropic ones
+	// with the geometric mean of their alphas
+	fn table_roughness(&self) -> algebra::Scalar {
+		(self.alpha_x * self.alpha_y).sqrt().sqrt().min(1.0)
+	}
+
+	// density of microfacet normals per solid angle, normalized over projected area
+	pub fn d(&self, half_vec: algebra::Vector) -> algebra::Scalar {
+		if half_vec.z <= 0.0 {
+			return 0.0;
 		}
+		let x = half_vec.x / self.alpha_x;
+		let y = half_vec.y / self.alpha_y;
+		let e = x * x + y * y + half_vec.z * half_vec.z;
+		constants::PI_INV / (self.alpha_x * self.alpha_y * e * e)
 	}
 
-	pub fn compute_bxdf(
+	// Smith auxiliary function, the same on both sides of the surface
+	fn lambda(&self, direction: algebra::Vector) -> algebra::Scalar {
+		let x = self.alpha_x * direction.x;
+		let y = self.alpha_y * direction.y;
+		let tan2 = (x * x + y * y) / (direction.z * direction.z);
+		0.5 * ((1.0 + tan2).sqrt() - 1.0)
+	}
+
+	// fraction of microfacets visible from `direction`
+	pub fn g1(&self, direction: algebra::Vector) -> algebra::Scalar {
+		1.0 / (1.0 + self.lambda(direction))
+	}
+
+	// height correlated masking-shadowing
+	pub fn g(&self, incoming: algebra::Vector, outgoing: algebra::Vector) -> algebra::Scalar {
+		1.0 / (1.0 + self.lambda(incoming) + self.lambda(outgoing))
+	}
+
+	// microfacet normal distributed proportionally to its projected area seen from
+	// `outgoing`, which has to lie above the surface
+	// source: https://jcgt.org/published/0007/04/01/paper.pdf
+	pub fn sample_visible(&self, outgoing: algebra::Vector, random: (f64, f64)) -> algebra::Vector {
+		// transform view direction to configuration with hemisphere of unit roughness
+		let view = algebra::Vector::new(
+			self.alpha_x * outgoing.x,
+			self.alpha_y * outgoing.y,
+			outgoing.z,
+		)
+		.normalize();
+		let len2 = view.x * view.x + view.y * view.y;
+		let t1 = if len2 > 0.0 {
+			algebra::Vector::new(-view.y, view.x, 0.0) / len2.sqrt()
+		} else {
+			algebra::Vector::new(1.0, 0.0, 0.0)
+		};
+		let t2 = view % t1;
+		// uniform point on disk warped onto projection of the visible hemisphere
+		let r = random.0.sqrt();
+		let phi = 2.0 * constants::PI * random.1;
+		let p1 = r * phi.cos();
+		let s = 0.5 * (1.0 + view.z);
+		let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+		let p3 = (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+		let normal = p1 * t1 + p2 * t2 + p3 * view;
+		algebra::Vector::new(
+			self.alpha_x * normal.x,
+			self.alpha_y * normal.y,
+			normal.z.max(0.0),
+		)
+		.normalize()
+	}
+
+	// density of `sample_visible` returning `half_vec`
+	pub fn pdf_visible(
 		&self,
-		incoming: algebra::Vector,
 		outgoing: algebra::Vector,
-		normal: algebra::Vector,
-		lambda: algebra::Scalar,
+		half_vec: algebra::Vector,
 	) -> algebra::Scalar {
-		let zero_clamp = |x| if x < 0.0 { 0.0 } else { x };
-		match self {
-			BxDF::OrenNayar { a, b, color } => {
-				let max = |a, b| if a - b > 0.0 { a } else { b };
-				let clamp = |x, a, b| {
-					if x < a {
-						a
-					} else if x > b {
-						b
-					} else {
-						x
-					}
-				};
-				let mut max_cos: algebra::Scalar = 0.0;
-				let cos_theta_i = incoming.z;
-				let cos_theta_o = outgoing.z;
-				let sin_theta_i = max(0.0, 1.0 - cos_theta_i.powi(2)).sqrt();
-				let sin_theta_o = max(0.0, 1.0 - cos_theta_o.powi(2)).sqrt();
-				if sin_theta_i > 1e-4 && sin_theta_o > 1e-4 {
-					let cos_phi_i = if sin_theta_i == 0.0 {
-						1.0
-					} else {
-						clamp(incoming.x / sin_theta_i, -1.0, 1.0)
-					};
-					let cos_phi_o = if sin_theta_o == 0.0 {
-						1.0
-					} else {
-						clamp(outgoing.x / sin_theta_o, -1.0, 1.0)
-					};
-					let sin_phi_i = if sin_theta_i == 0.0 {
-						0.0
-					} else {
-						clamp(incoming.y / sin_theta_i, -1.0, 1.0)
-					};
-					let sin_phi_o = if sin_theta_o == 0.0 {
-						0.0
-					} else {
-						clamp(outgoing.y / sin_theta_o, -1.0, 1.0)
-					};
-					let d_cos = cos_phi_i * cos_phi_o + sin_phi_i * sin_phi_o;
-					max_cos = max(0.0, d_cos);
-				}
-				let sin_alpha: algebra::Scalar;
-				let tan_beta: algebra::Scalar;
-
-				if cos_theta_i.abs() > cos_theta_o.abs() {
-					sin_alpha = sin_theta_o;
-					tan_beta = sin_theta_i / cos_theta_i.abs();
-				} else {
-					sin_alpha = sin_theta_i;
-					tan_beta = sin_theta_o / cos_theta_o.abs();
-				}
-				return self.return_color(&color, lambda)
-					* constants::PI_INV * (a + b * max_cos * sin_alpha * tan_beta);
-			}
-			BxDF::Specular {} => 1.0,
-			BxDF::GGX_reflect { alpha2, .. } => {
-				let n1: algebra::Scalar = 1.0;
-				let n2: algebra::Scalar = 1.45;
-				let half_vec = (incoming + outgoing).normalize();
-				// https://cseweb.ucsd.edu/~tzli/cse272/homework1.pdf
-				let denom = incoming * normal;
-				0.25 * self.d_ggx(half_vec, normal, *alpha2)
-					* self.g_ggx(incoming, outgoing, half_vec, normal, *alpha2)
-					/ denom
+		let cos_o = outgoing * half_vec;
+		if outgoing.z <= 0.0 || cos_o <= 0.0 {
+			return 0.0;
+		}
+		self.g1(outgoing) * cos_o * self.d(half_vec) / outgoing.z
+	}
+}
+
+// metal reflecting according to complex index of refraction of the context, GGX microfacet
+// distribution or perfect mirror when smooth
+#[derive(Clone, PartialEq, Debug)]
+pub struct Conductor {
+	microfacets: Ggx,
+	film: std::option::Option<ThinFilm>,
+	// reflectance at normal incidence replacing index of refraction of the context
+	reflectance: std::option::Option<spectrum::Spectrum>,
+}
+
+impl Conductor {
+	pub fn new(roughness: algebra::Scalar) -> Conductor {
+		Conductor::with_microfacets(Ggx::isotropic(roughness))
+	}
+
+	pub fn with_microfacets(microfacets: Ggx) -> Conductor {
+		Conductor {
+			microfacets,
+			film: std::option::Option::None,
+			reflectance: std::option::Option::None,
+		}
+	}
+
+	// metal given by its color instead of index of refraction, with Schlick approximation
+	// of Fresnel reflectance rising from `color` at normal incidence to white at grazing angles
+	pub fn with_reflectance(self, color: spectrum::Spectrum) -> Conductor {
+		Conductor {
+			reflectance: std::option::Option::Some(color),
+			..self
+		}
+	}
+
+	// metal coated with interfering thin layer, e.g. anodized titanium
+	pub fn with_film(self, film: ThinFilm) -> Conductor {
+		Conductor {
+			film: std::option::Option::Some(film),
+			..self
+		}
+	}
+
+	fn is_smooth(&self) -> bool {
+		self.microfacets.is_smooth()
+	}
+
+	fn fresnel(&self, cos_theta: algebra::Scalar, context: &ShadingContext) -> algebra::Scalar {
+		match &self.film {
+			std::option::Option::Some(film) => {
+				film.reflectance(cos_theta, context.lambda, context.eta, context.k)
 			}
-			BxDF::SpecularRefract {} => 1.0,
+			std::option::Option::None => match &self.reflectance {
+				std::option::Option::Some(color) => {
+					let f0 = color.evaluate(context.lambda).clamp(0.0, 1.0);
+					f0 + (1.0 - f0) * (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5)
+				}
+				std::option::Option::None => fresnel_conductor(cos_theta, context.eta, context.k),
+			},
 		}
 	}
 
-	pub fn pdf(
+	// energy the single scattering lobe loses between microfacets, returned as a diffuse-like
+	// lobe tinted by the average Fresnel reflectance which it went through several times
+	// source: https://blog.selfshadow.com/publications/s2017-shading-course/imageworks/s2017_pbs_imageworks_slides_v2.pdf
+	fn multiple_scattering(
 		&self,
 		incoming: algebra::Vector,
 		outgoing: algebra::Vector,
-		normal: algebra::Vector,
-		lambda: algebra::Scalar,
+		context: &ShadingContext,
 	) -> algebra::Scalar {
-		let clamp = |x| if x < 0.0 { 0.0 } else { x };
-		match self {
-			BxDF::OrenNayar { .. } => clamp(normal * incoming).abs() * constants::PI_INV,
-			BxDF::Specular {} => 1.0,
-			BxDF::GGX_reflect { alpha2, .. } => {
-				let half_vec = (incoming + outgoing).normalize();
-				let denom = (half_vec * incoming).abs() * (half_vec * outgoing).abs();
-				self.d_ggx(half_vec, normal, *alpha2) * (half_vec * normal).abs() * 0.25 / denom
-			}
-			BxDF::SpecularRefract {} => 1.0,
+		let table = conductor_albedo();
+		let roughness = self.microfacets.table_roughness();
+		let loss = 1.0 - table.average(roughness, 0.0);
+		if loss < MIN_ENERGY_LOSS {
+			return 0.0;
 		}
+		// 2 * integral of F(cos) * cos over the hemisphere by midpoint rule
+		let steps = 16;
+		let f_avg = (0..steps)
+			.map(|i| {
+				let cos = (i as algebra::Scalar + 0.5) / steps as algebra::Scalar;
+				2.0 * cos * self.fresnel(cos, context)
+			})
+			.sum::<algebra::Scalar>()
+			/ steps as algebra::Scalar;
+		let f_ms = f_avg * f_avg * (1.0 - loss) / (1.0 - f_avg * loss);
+		let e_o = table.albedo(outgoing.z, roughness, 0.0);
+		let e_i = table.albedo(incoming.z, roughness, 0.0);
+		f_ms * (1.0 - e_o) * (1.0 - e_i) / (constants::PI * loss)
+	}
+
+	// multiple scattering lobe is sampled in proportion to energy it carries
+	fn multiple_scattering_probability(&self, outgoing: algebra::Vector) -> algebra::Scalar {
+		let table = conductor_albedo();
+		let roughness = self.microfacets.table_roughness();
+		(1.0 - table.albedo(outgoing.z, roughness, 0.0)).max(0.0)
 	}
-	fn return_color(&self, c: &Color, lambda: algebra::Scalar) -> algebra::Scalar {
-		let mut color: algebra::Scalar = 0.0;
-		for (power, coefficient) in c.iter().enumerate() {
-			color += coefficient * lambda.powi(power.try_into().unwrap());
+}
+
+impl Bxdf for Conductor {
+	fn flags(&self) -> LobeFlags {
+		if self.is_smooth() {
+			LobeFlags::REFLECTION | LobeFlags::SPECULAR
+		} else {
+			LobeFlags::REFLECTION | LobeFlags::GLOSSY
 		}
-		if color > 1.0 {
-			return 1.0;
-		} else if color < 0.0 {
+	}
+
+	fn eval(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &ShadingContext,
+	) -> algebra::Scalar {
+		if incoming.z <= 0.0 || outgoing.z <= 0.0 {
+			return 0.0;
+		}
+		if self.is_smooth() {
+			return self.fresnel(outgoing.z, context) / incoming.z;
+		}
+		let half_vec = (incoming + outgoing).normalize();
+		let f = self.fresnel(outgoing * half_vec, context);
+		let microfacets = &self.microfacets;
+		microfacets.d(half_vec) * microfacets.g(incoming, outgoing) * f
+			/ (4.0 * incoming.z * outgoing.z)
+			+ self.multiple_scattering(incoming, outgoing, context)
+	}
+
+	fn pdf(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		_context: &ShadingContext,
+	) -> algebra::Scalar {
+		if incoming.z <= 0.0 || outgoing.z <= 0.0 {
 			return 0.0;
+		}
+		if self.is_smooth() {
+			return 1.0;
+		}
+		let half_vec = (incoming + outgoing).normalize();
+		let pdf_half = self.microfacets.pdf_visible(outgoing, half_vec);
+		let single = pdf_half / (4.0 * (outgoing * half_vec));
+		let multiple = self.multiple_scattering_probability(outgoing);
+		(1.0 - multiple) * single + multiple * incoming.z * constants::PI_INV
+	}
+
+	fn sample(
+		&self,
+		outgoing: algebra::Vector,
+		random: (f64, f64, f64),
+		context: &ShadingContext,
+	) -> std::option::Option<BxdfSample> {
+		if outgoing.z <= 0.0 {
+			return std::option::Option::None;
+		}
+		let incoming = if self.is_smooth() {
+			reflect(outgoing, algebra::Vector::new(0.0, 0.0, 1.0))
+		} else if random.2 < self.multiple_scattering_probability(outgoing) {
+			sample_cosine((random.0, random.1))
 		} else {
-			return color;
+			reflect(outgoing, self.microfacets.sample_visible(outgoing, (random.0, random.1)))
+		};
+		if incoming.z <= 0.0 {
+			return std::option::Option::None;
+		}
+		std::option::Option::Some(BxdfSample {
+			incoming,
+			value: self.eval(incoming, outgoing, context),
+			pdf: self.pdf(incoming, outgoing, context),
+			flags: self.flags(),
+		})
+	}
+}
+
+// reflection and transmission through interface between outside and inside with relative
+// index of refraction `eta` of the context, smooth when microfacets are; transmitted
+// values are scaled for radiance transport
+// source: https://www.graphics.cornell.edu/~bjw/microfacetbsdf.pdf
+#[derive(Clone, PartialEq, Debug)]
+pub struct Dielectric {
+	microfacets: Ggx,
+	film: std::option::Option<ThinFilm>,
+}
+
+impl Dielectric {
+	pub fn new(roughness: algebra::Scalar) -> Dielectric {
+		Dielectric::with_microfacets(Ggx::isotropic(roughness))
+	}
+
+	pub fn with_microfacets(microfacets: Ggx) -> Dielectric {
+		Dielectric {
+			microfacets,
+			film: std::option::Option::None,
+		}
+	}
+
+	// interface coated with interfering thin layer, e.g. oil on water; soap bubble is a film
+	// with relative index of refraction of 1.0 on both sides
+	pub fn with_film(self, film: ThinFilm) -> Dielectric {
+		Dielectric {
+			film: std::option::Option::Some(film),
+			..self
+		}
+	}
+
+	fn is_smooth(&self) -> bool {
+		self.microfacets.is_smooth()
+	}
+
+	// non-absorbing film transmits what it does not reflect
+	fn fresnel(&self, cos_theta: algebra::Scalar, context: &ShadingContext) -> algebra::Scalar {
+		match &self.film {
+			std::option::Option::Some(film) => {
+				film.reflectance(cos_theta, context.lambda, context.eta, 0.0)
+			}
+			std::option::Option::None => fresnel_dielectric(cos_theta, context.eta),
 		}
 	}
 
-	fn d_ggx(
+	// light reflected or refracted by a single rough microfacet
+	fn single_scattering(
 		&self,
-		direction: algebra::Vector,
-		normal: algebra::Vector,
-		alpha2: algebra::Scalar,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &ShadingContext,
 	) -> algebra::Scalar {
-		let cos2_theta: algebra::Scalar = (normal * direction).powi(2);
-		let denom = (cos2_theta * (alpha2 - 1.0) + 1.0).powi(2);
-		alpha2 * constants::PI_INV / denom
+		let eta = context.eta;
+		let (cos_i, cos_o) = (incoming.z, outgoing.z);
+		let reflect = cos_i * cos_o > 0.0;
+		let etap = side_eta(reflect, cos_o, eta);
+		let half_vec = match generalized_half_vector(incoming, outgoing, etap) {
+			std::option::Option::Some(h) => h,
+			std::option::Option::None => return 0.0,
+		};
+		let f = self.fresnel(outgoing * half_vec, context);
+		let d = self.microfacets.d(half_vec);
+		let g = self.microfacets.g(incoming, outgoing);
+		if reflect {
+			d * g * f / (4.0 * cos_i * cos_o).abs()
+		} else {
+			let denom = (incoming * half_vec + outgoing * half_vec / etap).powi(2);
+			let jacobian = (incoming * half_vec) * (outgoing * half_vec) / (cos_i * cos_o * denom);
+			d * g * (1.0 - f) * jacobian.abs() / (etap * etap)
+		}
 	}
 
-	fn g_ggx(
+	fn single_scattering_pdf(
 		&self,
 		incoming: algebra::Vector,
 		outgoing: algebra::Vector,
-		half_vec: algebra::Vector,
-		normal: algebra::Vector,
-		alpha2: algebra::Scalar,
+		context: &ShadingContext,
 	) -> algebra::Scalar {
-		self.gp_ggx(incoming, half_vec, normal, alpha2)
-			* self.gp_ggx(outgoing, half_vec, normal, alpha2)
+		let eta = context.eta;
+		let reflect = incoming.z * outgoing.z > 0.0;
+		let etap = side_eta(reflect, outgoing.z, eta);
+		let half_vec = match generalized_half_vector(incoming, outgoing, etap) {
+			std::option::Option::Some(h) => h,
+			std::option::Option::None => return 0.0,
+		};
+		let f = self.fresnel(outgoing * half_vec, context);
+		// microfacets are seen from below by the same amount as from the mirrored direction
+		let pdf_half = self.microfacets.pdf_visible(mirror_above(outgoing), half_vec);
+		if reflect {
+			pdf_half / (4.0 * (outgoing * half_vec).abs()) * f
+		} else {
+			let denom = (incoming * half_vec + outgoing * half_vec / etap).powi(2);
+			pdf_half * (incoming * half_vec).abs() / denom * (1.0 - f)
+		}
 	}
 
-	fn gp_ggx(
+	// energy lost between microfacets is returned as diffuse-like reflection and
+	// transmission, the latter scaled for radiance transport like single scattering
+	// source: https://blog.selfshadow.com/publications/s2017-shading-course/imageworks/s2017_pbs_imageworks_slides_v2.pdf
+	fn multiple_scattering(
 		&self,
-		direction: algebra::Vector,
-		half_vec: algebra::Vector,
-		normal: algebra::Vector,
-		alpha2: algebra::Scalar,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &ShadingContext,
 	) -> algebra::Scalar {
-		let clamp = |x| if x < 0.0 { 0.0 } else { x };
-		let cos_theta = clamp(normal * direction);
-		// https://graphicrants.blogspot.com/2013/08/specular-brdf-reference.html
-		let sqrterm = alpha2 + (1.0 - alpha2) * cos_theta.powi(2);
-		let denom = cos_theta + sqrterm.sqrt();
-		2.0 * cos_theta / denom
+		let (cos_i, cos_o) = (incoming.z, outgoing.z);
+		let reflect = cos_i * cos_o > 0.0;
+		// relative index of refraction seen from `outgoing`
+		let eta_o = side_eta(false, cos_o, context.eta);
+		let table = dielectric_albedo();
+		let roughness = self.microfacets.table_roughness();
+		let e_o = table.albedo(cos_o.abs(), roughness, eta_coordinate(eta_o));
+		let transmitted = self.transmitted_fraction(eta_o);
+		if reflect {
+			let loss = 1.0 - table.average(roughness, eta_coordinate(eta_o));
+			if loss < MIN_ENERGY_LOSS {
+				return 0.0;
+			}
+			let e_i = table.albedo(cos_i.abs(), roughness, eta_coordinate(eta_o));
+			(1.0 - transmitted) * (1.0 - e_o) * (1.0 - e_i) / (constants::PI * loss)
+		} else {
+			let loss = 1.0 - table.average(roughness, eta_coordinate(1.0 / eta_o));
+			if loss < MIN_ENERGY_LOSS {
+				return 0.0;
+			}
+			let e_i = table.albedo(cos_i.abs(), roughness, eta_coordinate(1.0 / eta_o));
+			transmitted * (1.0 - e_o) * (1.0 - e_i) / (constants::PI * loss) / (eta_o * eta_o)
+		}
+	}
+
+	// share of multiply scattered energy crossing the interface when seen from the side
+	// with relative index of refraction `eta_o`; from the optically thinner side it is the
+	// average Fresnel transmittance, from the denser one it follows from reciprocity which
+	// needs share * loss * (absolute index of refraction)^2 equal on both sides
+	fn transmitted_fraction(&self, eta_o: algebra::Scalar) -> algebra::Scalar {
+		// relative index of refraction seen from the thinner side
+		let eta = if eta_o >= 1.0 { eta_o } else { 1.0 / eta_o };
+		// fit of average Fresnel reflectance for index of refraction above 1.0
+		let transmitted = 1.0 - (eta - 1.0) / (4.08567 + 1.00071 * eta);
+		if eta_o >= 1.0 {
+			return transmitted;
+		}
+		let table = dielectric_albedo();
+		let roughness = self.microfacets.table_roughness();
+		let loss_thin = 1.0 - table.average(roughness, eta_coordinate(eta));
+		let loss_dense = 1.0 - table.average(roughness, eta_coordinate(1.0 / eta));
+		if loss_dense < MIN_ENERGY_LOSS {
+			return 0.0;
+		}
+		(transmitted * loss_thin / (loss_dense * eta * eta)).min(1.0)
 	}
-	pub fn fresnel_schlick_dielectric(
+
+	// multiple scattering lobe is sampled in proportion to energy it carries
+	fn multiple_scattering_probability(
 		&self,
-		n_1: algebra::Scalar,
-		n_2: algebra::Scalar,
 		outgoing: algebra::Vector,
-		half_vec: algebra::Vector,
+		context: &ShadingContext,
 	) -> algebra::Scalar {
-		let cos_theta = half_vec * outgoing;
-		let f_0 = ((n_1 - n_2) / (n_1 + n_2)).powi(2);
-		f_0 + (1.0 - f_0) * (1.0 - cos_theta).powi(5)
+		let eta_o = side_eta(false, outgoing.z, context.eta);
+		let roughness = self.microfacets.table_roughness();
+		let albedo = dielectric_albedo().albedo(outgoing.z.abs(), roughness, eta_coordinate(eta_o));
+		(1.0 - albedo).max(0.0)
+	}
+}
+
+impl Bxdf for Dielectric {
+	fn flags(&self) -> LobeFlags {
+		let kind = if self.is_smooth() {
+			LobeFlags::SPECULAR
+		} else {
+			LobeFlags::GLOSSY
+		};
+		LobeFlags::REFLECTION | LobeFlags::TRANSMISSION | kind
 	}
 
-	pub fn fresnel_conductor(
+	fn eval(
 		&self,
-		n: algebra::Scalar,
-		k: algebra::Scalar,
+		incoming: algebra::Vector,
 		outgoing: algebra::Vector,
-		half_vec: algebra::Vector,
+		context: &ShadingContext,
+	) -> algebra::Scalar {
+		let eta = context.eta;
+		let (cos_i, cos_o) = (incoming.z, outgoing.z);
+		if cos_i == 0.0 || cos_o == 0.0 {
+			return 0.0;
+		}
+		if self.is_smooth() {
+			let reflect = cos_i * cos_o > 0.0;
+			let etap = side_eta(reflect, cos_o, eta);
+			let f = self.fresnel(cos_o, context);
+			return if reflect {
+				f / cos_i.abs()
+			} else {
+				(1.0 - f) / cos_i.abs() / (etap * etap)
+			};
+		}
+		self.single_scattering(incoming, outgoing, context)
+			+ self.multiple_scattering(incoming, outgoing, context)
+	}
+
+	// for smooth interface this is discrete probability of choosing reflection or refraction
+	fn pdf(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &ShadingContext,
+	) -> algebra::Scalar {
+		let (cos_i, cos_o) = (incoming.z, outgoing.z);
+		if cos_i == 0.0 || cos_o == 0.0 {
+			return 0.0;
+		}
+		let reflect = cos_i * cos_o > 0.0;
+		if self.is_smooth() {
+			let f = self.fresnel(cos_o, context);
+			return if reflect { f } else { 1.0 - f };
+		}
+		let multiple = self.multiple_scattering_probability(outgoing, context);
+		let transmitted = self.transmitted_fraction(side_eta(false, cos_o, context.eta));
+		let side = if reflect {
+			1.0 - transmitted
+		} else {
+			transmitted
+		};
+		(1.0 - multiple) * self.single_scattering_pdf(incoming, outgoing, context)
+			+ multiple * side * cos_i.abs() * constants::PI_INV
+	}
+
+	// the first two random numbers pick direction, the third multiple or single scattering
+	// and then reflection or refraction
+	fn sample(
+		&self,
+		outgoing: algebra::Vector,
+		random: (f64, f64, f64),
+		context: &ShadingContext,
+	) -> std::option::Option<BxdfSample> {
+		let eta = context.eta;
+		let multiple = if self.is_smooth() {
+			0.0
+		} else {
+			self.multiple_scattering_probability(outgoing, context)
+		};
+		let incoming = if random.2 < multiple {
+			// diffuse-like direction on the side chosen by the rescaled random number
+			let choice = random.2 / multiple;
+			let transmitted = self.transmitted_fraction(side_eta(false, outgoing.z, eta));
+			let incoming = sample_cosine((random.0, random.1));
+			let below = (outgoing.z > 0.0) == (choice < transmitted);
+			if below {
+				-incoming
+			} else {
+				incoming
+			}
+		} else {
+			let choice = (random.2 - multiple) / (1.0 - multiple);
+			let half_vec = if self.is_smooth() {
+				algebra::Vector::new(0.0, 0.0, 1.0)
+			} else {
+				let random = (random.0, random.1);
+				self.microfacets.sample_visible(mirror_above(outgoing), random)
+			};
+			let cos_o = outgoing * half_vec;
+			// microfacets facing away from the viewer are not visible
+			if cos_o * outgoing.z <= 0.0 {
+				return std::option::Option::None;
+			}
+			let incoming = if choice < self.fresnel(cos_o, context) {
+				reflect(outgoing, half_vec)
+			} else {
+				refract(outgoing, half_vec, eta)?
+			};
+			// microfacet may scatter to the wrong side of the macro surface
+			let reflected = incoming.z * outgoing.z > 0.0;
+			let reflected_micro = (incoming * half_vec) * cos_o > 0.0;
+			if reflected != reflected_micro {
+				return std::option::Option::None;
+			}
+			incoming
+		};
+		if incoming.z == 0.0 {
+			return std::option::Option::None;
+		}
+		let kind = if incoming.z * outgoing.z > 0.0 {
+			LobeFlags::REFLECTION
+		} else {
+			LobeFlags::TRANSMISSION
+		};
+		let smoothness = if self.is_smooth() {
+			LobeFlags::SPECULAR
+		} else {
+			LobeFlags::GLOSSY
+		};
+		std::option::Option::Some(BxdfSample {
+			incoming,
+			value: self.eval(incoming, outgoing, context),
+			pdf: self.pdf(incoming, outgoing, context),
+			flags: kind | smoothness,
+		})
+	}
+}
+
+// directional albedo of single scattering GGX microfacets tabulated on a regular grid over
+// cosine of the outgoing direction, roughness (square root of alpha) and, for dielectrics,
+// relative index of refraction; all coordinates lie between 0.0 and 1.0
+struct AlbedoTable {
+	// number of nodes along each axis, cosine varying fastest
+	shape: (usize, usize, usize),
+	albedo: Vec<algebra::Scalar>,
+	// cosine weighted average over the hemisphere for each roughness and index of refraction
+	average: Vec<algebra::Scalar>,
+}
+
+impl AlbedoTable {
+	fn new(
+		shape: (usize, usize, usize),
+		albedo: impl Fn(algebra::Scalar, algebra::Scalar, algebra::Scalar) -> algebra::Scalar,
+	) -> AlbedoTable {
+		let node = |i: usize, n: usize| i as algebra::Scalar / (n - 1).max(1) as algebra::Scalar;
+		let mut values = Vec::with_capacity(shape.0 * shape.1 * shape.2);
+		for k in 0..shape.2 {
+			for j in 0..shape.1 {
+				for i in 0..shape.0 {
+					values.push(albedo(node(i, shape.0), node(j, shape.1), node(k, shape.2)));
+				}
+			}
+		}
+		// 2 * integral of albedo(cos) * cos by trapezoidal rule
+		let step = 1.0 / (shape.0 - 1) as algebra::Scalar;
+		let average = values
+			.chunks(shape.0)
+			.map(|row| {
+				row.windows(2)
+					.enumerate()
+					.map(|(i, pair)| {
+						let (a, b) = (node(i, shape.0), node(i + 1, shape.0));
+						(pair[0] * a + pair[1] * b) * step
+					})
+					.sum()
+			})
+			.collect();
+		AlbedoTable {
+			shape,
+			albedo: values,
+			average,
+		}
+	}
+
+	fn albedo(
+		&self,
+		cos: algebra::Scalar,
+		roughness: algebra::Scalar,
+		eta: algebra::Scalar,
 	) -> algebra::Scalar {
-		let cos_theta = outgoing * half_vec;
-		let denom = (n + 1.0).powi(2) + k * k;
-		((n - 1.0).powi(2) + 4.0 * n * (1.0 - cos_theta).powi(5) + k * k) / denom
-	}
-}
-
-impl Lobe {
-	pub fn evaluate_lobe(
-		lobe: Self,
-		theta_i: algebra::Scalar,
-		phi_i: algebra::Scalar,
-		random_dir: (f64, f64),
-	) -> (algebra::Scalar, algebra::Scalar) {
-		match lobe {
-			Self::Cosine => (
-				random_dir.0.sqrt().acos(),
-				random_dir.1 * 2.0 * constants::PI,
-			),
-			Self::DeltaReflect => (theta_i, phi_i + constants::PI),
-			Self::GGX_reflect { alpha } => (
-				(alpha * (random_dir.0 / (1.0 - random_dir.0)).sqrt()).atan(),
-				random_dir.1 * 2.0 * constants::PI,
-			),
-			Self::DeltaRefract => {
-				let n1 = 1.0;
-				let n2 = 1.5;
-				return if theta_i < 0.5 * constants::PI {
-					let ratio = n1 * theta_i.sin() / n2;
-					if ratio >= -1.0 && ratio <= 1.0 {
-						(constants::PI - ratio.asin(), phi_i + constants::PI)
-					} else {
-						(theta_i, phi_i + constants::PI)
-					}
-				} else {
-					let ratio = n2 * (constants::PI - theta_i).sin() / n1;
-					if ratio >= -1.0 && ratio <= 1.0 {
-						(ratio.asin(), phi_i + constants::PI)
-					} else {
-						(theta_i, phi_i + constants::PI)
-					}
-				};
+		let (n_cos, n_roughness, n_eta) = self.shape;
+		interpolate([(cos, n_cos), (roughness, n_roughness), (eta, n_eta)], |index| {
+			self.albedo[index[0] + n_cos * (index[1] + n_roughness * index[2])]
+		})
+	}
+
+	fn average(&self, roughness: algebra::Scalar, eta: algebra::Scalar) -> algebra::Scalar {
+		let (_, n_roughness, n_eta) = self.shape;
+		interpolate([(roughness, n_roughness), (eta, n_eta)], |index| {
+			self.average[index[0] + n_roughness * index[1]]
+		})
+	}
+}
+
+// multilinear interpolation of `value` given at grid nodes; each axis has coordinate
+// between 0.0 and 1.0 and number of nodes
+fn interpolate<const N: usize>(
+	axes: [(algebra::Scalar, usize); N],
+	value: impl Fn([usize; N]) -> algebra::Scalar,
+) -> algebra::Scalar {
+	let cells = axes.map(|(x, n)| {
+		let x = x.clamp(0.0, 1.0) * (n - 1) as algebra::Scalar;
+		let i = (x.floor() as usize).min(n.saturating_sub(2));
+		(i, x - i as algebra::Scalar)
+	});
+	let mut sum = 0.0;
+	for corner in 0..1usize << N {
+		let mut index = [0; N];
+		let mut weight = 1.0;
+		for (axis, &(i, t)) in cells.iter().enumerate() {
+			let upper = (corner >> axis) & 1 == 1;
+			// upper node of an axis with a single node has zero weight and is skipped
+			index[axis] = i + upper as usize;
+			weight *= if upper { t } else { 1.0 - t };
+		}
+		if weight > 0.0 {
+			sum += weight * value(index);
+		}
+	}
+	sum
+}
+
+// single scattering albedo of microfacets reflecting everything, or of dielectric interface
+// with relative index of refraction `eta` counting reflection and, if `transmission`, also
+// transmission without radiance scaling, estimated with stratified visible normals
+fn microfacet_albedo(
+	alpha: algebra::Scalar,
+	cos_o: algebra::Scalar,
+	eta: std::option::Option<algebra::Scalar>,
+	transmission: bool,
+) -> algebra::Scalar {
+	if alpha < SMOOTH_ALPHA {
+		return match eta {
+			std::option::Option::Some(eta) if !transmission => fresnel_dielectric(cos_o, eta),
+			_ => 1.0,
+		};
+	}
+	let microfacets = Ggx::new(alpha, alpha);
+	let cos_o = cos_o.max(1e-3);
+	let outgoing = algebra::Vector::new((1.0 - cos_o * cos_o).sqrt(), 0.0, cos_o);
+	let g1 = microfacets.g1(outgoing);
+	let strata = ALBEDO_STRATA;
+	let mut sum = 0.0;
+	for i in 0..strata {
+		for j in 0..strata {
+			let random = (
+				(i as algebra::Scalar + 0.5) / strata as algebra::Scalar,
+				(j as algebra::Scalar + 0.5) / strata as algebra::Scalar,
+			);
+			let half_vec = microfacets.sample_visible(outgoing, random);
+			let f = match eta {
+				std::option::Option::Some(eta) => fresnel_dielectric(outgoing * half_vec, eta),
+				std::option::Option::None => 1.0,
+			};
+			let reflected = reflect(outgoing, half_vec);
+			if reflected.z > 0.0 {
+				sum += f * microfacets.g(reflected, outgoing) / g1;
+			}
+			let refracted = eta
+				.filter(|_| transmission)
+				.and_then(|eta| refract(outgoing, half_vec, eta));
+			if let std::option::Option::Some(refracted) = refracted {
+				if refracted.z < 0.0 {
+					sum += (1.0 - f) * microfacets.g(refracted, outgoing) / g1;
+				}
 			}
 		}
 	}
+	sum / (strata * strata) as algebra::Scalar
+}
+
+// computed on first use
+fn conductor_albedo() -> &'static AlbedoTable {
+	static TABLE: sync::OnceLock<AlbedoTable> = sync::OnceLock::new();
+	TABLE.get_or_init(|| {
+		AlbedoTable::new((32, 32, 1), |cos, roughness, _| {
+			microfacet_albedo(roughness * roughness, cos, std::option::Option::None, false)
+		})
+	})
+}
+
+fn dielectric_albedo() -> &'static AlbedoTable {
+	static TABLE: sync::OnceLock<AlbedoTable> = sync::OnceLock::new();
+	TABLE.get_or_init(|| {
+		AlbedoTable::new((16, 16, 17), |cos, roughness, eta| {
+			let eta = MAX_TABLE_ETA.powf(2.0 * eta - 1.0);
+			microfacet_albedo(roughness * roughness, cos, std::option::Option::Some(eta), true)
+		})
+	})
+}
+
+// reflection only, as seen from outside of a coat
+fn coat_albedo() -> &'static AlbedoTable {
+	static TABLE: sync::OnceLock<AlbedoTable> = sync::OnceLock::new();
+	TABLE.get_or_init(|| {
+		AlbedoTable::new((16, 16, 17), |cos, roughness, eta| {
+			let eta = MAX_TABLE_ETA.powf(2.0 * eta - 1.0);
+			microfacet_albedo(roughness * roughness, cos, std::option::Option::Some(eta), false)
+		})
+	})
+}
+
+// relative index of refraction whose reflectance at normal incidence is `scale` times the one
+// of `eta`, kept on the same side of 1.0
+fn scaled_eta(eta: algebra::Scalar, scale: algebra::Scalar) -> algebra::Scalar {
+	if scale == 1.0 {
+		return eta;
+	}
+	let outer = if eta < 1.0 { 1.0 / eta } else { eta };
+	let root = ((outer - 1.0) / (outer + 1.0) * scale.sqrt()).min(0.99);
+	let scaled = (1.0 + root) / (1.0 - root);
+	if eta < 1.0 {
+		1.0 / scaled
+	} else {
+		scaled
+	}
+}
+
+// relative index of refraction is tabulated logarithmically between its bounds
+fn eta_coordinate(eta: algebra::Scalar) -> algebra::Scalar {
+	0.5 * (eta.ln() / MAX_TABLE_ETA.ln() + 1.0)
+}
+
+// relative index of refraction on the side of `outgoing` with z component `cos_o`,
+// 1.0 for reflection
+fn side_eta(reflect: bool, cos_o: algebra::Scalar, eta: algebra::Scalar) -> algebra::Scalar {
+	if reflect {
+		1.0
+	} else if cos_o > 0.0 {
+		eta
+	} else {
+		1.0 / eta
+	}
+}
+
+// reflection from the top of a dielectric coat with relative index of refraction `eta` of
+// the context; light refracted into the coat is left to `Coated` lobe beneath it
+#[derive(Clone, PartialEq, Debug)]
+pub struct Coat {
+	microfacets: Ggx,
+	// relative index of refraction used instead of the one of the context
+	eta: std::option::Option<algebra::Scalar>,
+	// factor of reflectance at normal incidence, see `scale_reflectance`
+	reflectance_scale: algebra::Scalar,
+}
+
+impl Coat {
+	pub fn new(roughness: algebra::Scalar) -> Coat {
+		Coat::with_microfacets(Ggx::isotropic(roughness))
+	}
+
+	pub fn with_microfacets(microfacets: Ggx) -> Coat {
+		Coat {
+			microfacets,
+			eta: std::option::Option::None,
+			reflectance_scale: 1.0,
+		}
+	}
+
+	// coat of its own material over a surface of different one, e.g. lacquer on metal
+	pub fn with_ior(self, eta: algebra::Scalar) -> Coat {
+		if eta <= 0.0 {
+			panic!("index of refraction of coat must be positive");
+		}
+		Coat {
+			eta: std::option::Option::Some(eta),
+			..self
+		}
+	}
+
+	// reflectance at normal incidence multiplied by `scale`, as `specular` of principled
+	// shaders does; `Coated` beneath must be given the same scale
+	pub fn scale_reflectance(self, scale: algebra::Scalar) -> Coat {
+		if !(0.0..=2.0).contains(&scale) {
+			panic!("reflectance scale of coat must be between 0.0 and 2.0");
+		}
+		Coat {
+			reflectance_scale: scale,
+			..self
+		}
+	}
+
+	fn is_smooth(&self) -> bool {
+		self.microfacets.is_smooth()
+	}
+
+	fn eta(&self, context: &ShadingContext) -> algebra::Scalar {
+		scaled_eta(self.eta.unwrap_or(context.eta), self.reflectance_scale)
+	}
+
+	// fraction of light arriving in direction with cosine `cos` reflected by coat of relative
+	// index of refraction `eta`
+	fn albedo(&self, cos: algebra::Scalar, eta: algebra::Scalar) -> algebra::Scalar {
+		if self.is_smooth() {
+			fresnel_dielectric(cos, eta)
+		} else {
+			let roughness = self.microfacets.table_roughness();
+			coat_albedo().albedo(cos, roughness, eta_coordinate(eta))
+		}
+	}
+}
+
+impl Bxdf for Coat {
+	fn flags(&self) -> LobeFlags {
+		if self.is_smooth() {
+			LobeFlags::REFLECTION | LobeFlags::SPECULAR
+		} else {
+			LobeFlags::REFLECTION | LobeFlags::GLOSSY
+		}
+	}
+
+	fn eval(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &ShadingContext,
+	) -> algebra::Scalar {
+		if incoming.z <= 0.0 || outgoing.z <= 0.0 {
+			return 0.0;
+		}
+		if self.is_smooth() {
+			return fresnel_dielectric(outgoing.z, self.eta(context)) / incoming.z;
+		}
+		let half_vec = (incoming + outgoing).normalize();
+		let f = fresnel_dielectric(outgoing * half_vec, self.eta(context));
+		let microfacets = &self.microfacets;
+		microfacets.d(half_vec) * microfacets.g(incoming, outgoing) * f
+			/ (4.0 * incoming.z * outgoing.z)
+	}
+
+	fn pdf(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		_context: &ShadingContext,
+	) -> algebra::Scalar {
+		if incoming.z <= 0.0 || outgoing.z <= 0.0 {
+			return 0.0;
+		}
+		if self.is_smooth() {
+			return 1.0;
+		}
+		let half_vec = (incoming + outgoing).normalize();
+		self.microfacets.pdf_visible(outgoing, half_vec) / (4.0 * (outgoing * half_vec))
+	}
+
+	fn sample(
+		&self,
+		outgoing: algebra::Vector,
+		random: (f64, f64, f64),
+		context: &ShadingContext,
+	) -> std::option::Option<BxdfSample> {
+		if outgoing.z <= 0.0 {
+			return std::option::Option::None;
+		}
+		let half_vec = if self.is_smooth() {
+			algebra::Vector::new(0.0, 0.0, 1.0)
+		} else {
+			self.microfacets.sample_visible(outgoing, (random.0, random.1))
+		};
+		let incoming = reflect(outgoing, half_vec);
+		if incoming.z <= 0.0 {
+			return std::option::Option::None;
+		}
+		std::option::Option::Some(BxdfSample {
+			incoming,
+			value: self.eval(incoming, outgoing, context),
+			pdf: self.pdf(incoming, outgoing, context),
+			flags: self.flags(),
+		})
+	}
+}
+
+// `base` lobe seen through dielectric coat whose reflection is given by `Coat` with the same
+// microfacets; light not reflected by the coat reaches the base, attenuated by `absorption`
+// coefficient per meter along its refracted path through coat of `thickness` in meters.
+// albedo scaling keeps the sum of both lobes from reflecting more than arrives, but is not
+// reciprocal and neglects light reflected back to the base from inside of the coat
+// source: https://academysoftwarefoundation.github.io/OpenPBR/
+pub struct Coated {
+	base: std::sync::Arc<dyn Bxdf>,
+	microfacets: Ggx,
+	thickness: algebra::Scalar,
+	absorption: spectrum::Spectrum,
+	// complex index of refraction of the base at given wavelength, the coat's if none
+	base_ior: std::option::Option<Box<IorFunction>>,
+	// factor of reflectance of the coat at normal incidence, see `Coat::scale_reflectance`
+	reflectance_scale: algebra::Scalar,
+}
+
+type IorFunction = dyn Fn(algebra::Scalar) -> (algebra::Scalar, algebra::Scalar) + Send + Sync;
+
+impl Coated {
+	pub fn new(
+		base: std::sync::Arc<dyn Bxdf>,
+		microfacets: Ggx,
+		thickness: algebra::Scalar,
+		absorption: spectrum::Spectrum,
+	) -> Coated {
+		if thickness < 0.0 {
+			panic!("coat thickness must not be negative");
+		}
+		Coated {
+			base,
+			microfacets,
+			thickness,
+			absorption,
+			base_ior: std::option::Option::None,
+			reflectance_scale: 1.0,
+		}
+	}
+
+	// matches `Coat::scale_reflectance` of the coat above
+	pub fn scale_reflectance(self, scale: algebra::Scalar) -> Coated {
+		if !(0.0..=2.0).contains(&scale) {
+			panic!("reflectance scale of coat must be between 0.0 and 2.0");
+		}
+		Coated {
+			reflectance_scale: scale,
+			..self
+		}
+	}
+
+	// `ior` returns n and k of the base relative to the medium surrounding the coat, the
+	// same way as the coat's index of refraction is given
+	pub fn with_base_ior(
+		self,
+		ior: impl Fn(algebra::Scalar) -> (algebra::Scalar, algebra::Scalar) + Send + Sync + 'static,
+	) -> Coated {
+		Coated {
+			base_ior: std::option::Option::Some(Box::new(ior)),
+			..self
+		}
+	}
+
+	// base sees index of refraction relative to the coat
+	fn base_context(&self, context: &ShadingContext) -> ShadingContext {
+		let (eta, k) = match &self.base_ior {
+			std::option::Option::Some(ior) => {
+				let (n, k) = ior(context.lambda);
+				(n / context.eta, k / context.eta)
+			}
+			std::option::Option::None => (1.0, 0.0),
+		};
+		ShadingContext {
+			lambda: context.lambda,
+			eta,
+			k,
+		}
+	}
+
+	// fraction of light passing the top of the coat in direction with cosine `cos`
+	fn transmittance(&self, cos: algebra::Scalar, eta: algebra::Scalar) -> algebra::Scalar {
+		let coat = Coat::with_microfacets(self.microfacets);
+		(1.0 - coat.albedo(cos, scaled_eta(eta, self.reflectance_scale))).max(0.0)
+	}
+
+	// attenuation along refracted path down to the base and back up
+	fn attenuation(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &ShadingContext,
+	) -> algebra::Scalar {
+		let density = self.absorption.evaluate(context.lambda) * self.thickness;
+		if density == 0.0 {
+			return 1.0;
+		}
+		let refracted = |cos: algebra::Scalar| {
+			(1.0 - (1.0 - cos * cos) / (context.eta * context.eta)).max(0.0).sqrt()
+		};
+		let path = 1.0 / refracted(incoming.z) + 1.0 / refracted(outgoing.z);
+		(-density * path).exp()
+	}
+}
+
+impl Bxdf for Coated {
+	fn flags(&self) -> LobeFlags {
+		self.base.flags()
+	}
+
+	fn eval(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &ShadingContext,
+	) -> algebra::Scalar {
+		if incoming.z <= 0.0 || outgoing.z <= 0.0 {
+			return 0.0;
+		}
+		let base = self.base.eval(incoming, outgoing, &self.base_context(context));
+		if base == 0.0 {
+			return 0.0;
+		}
+		self.transmittance(outgoing.z, context.eta)
+			* self.attenuation(incoming, outgoing, context)
+			* base
+	}
+
+	fn pdf(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &ShadingContext,
+	) -> algebra::Scalar {
+		self.base.pdf(incoming, outgoing, &self.base_context(context))
+	}
+
+	fn sample(
+		&self,
+		outgoing: algebra::Vector,
+		random: (f64, f64, f64),
+		context: &ShadingContext,
+	) -> std::option::Option<BxdfSample> {
+		let sample = self.base.sample(outgoing, random, &self.base_context(context))?;
+		if sample.incoming.z <= 0.0 || outgoing.z <= 0.0 {
+			return std::option::Option::None;
+		}
+		std::option::Option::Some(BxdfSample {
+			value: self.eval(sample.incoming, outgoing, context),
+			..sample
+		})
+	}
+}
+
+// diffuse base under glossy dielectric reflection, the two weighted by Fresnel reflectance
+// of relative index of refraction `eta` of the context
+#[derive(Clone, PartialEq, Debug)]
+pub struct FresnelBlend {
+	diffuse: OrenNayar,
+	microfacets: Ggx,
+}
+
+impl FresnelBlend {
+	pub fn new(color: spectrum::Spectrum, roughness: algebra::Scalar) -> FresnelBlend {
+		FresnelBlend::with_microfacets(color, Ggx::isotropic(roughness))
+	}
+
+	// diffuse roughness follows mean roughness of the coating, which is never perfectly smooth
+	pub fn with_microfacets(color: spectrum::Spectrum, microfacets: Ggx) -> FresnelBlend {
+		let (alpha_x, alpha_y) = microfacets.alpha();
+		let roughness = (0.5 * (alpha_x + alpha_y)).sqrt();
+		FresnelBlend {
+			diffuse: OrenNayar::new(0.5 * roughness, color),
+			microfacets: Ggx::new(alpha_x.max(SMOOTH_ALPHA), alpha_y.max(SMOOTH_ALPHA)),
+		}
+	}
+
+	fn glossy_pdf(&self, incoming: algebra::Vector, outgoing: algebra::Vector) -> algebra::Scalar {
+		let half_vec = (incoming + outgoing).normalize();
+		self.microfacets.pdf_visible(outgoing, half_vec) / (4.0 * (outgoing * half_vec))
+	}
+}
+
+impl Bxdf for FresnelBlend {
+	fn flags(&self) -> LobeFlags {
+		LobeFlags::REFLECTION | LobeFlags::DIFFUSE | LobeFlags::GLOSSY
+	}
+
+	fn eval(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &ShadingContext,
+	) -> algebra::Scalar {
+		if incoming.z <= 0.0 || outgoing.z <= 0.0 {
+			return 0.0;
+		}
+		let half_vec = (incoming + outgoing).normalize();
+		let f = fresnel_dielectric(outgoing * half_vec, context.eta);
+		let glossy = self.microfacets.d(half_vec) * self.microfacets.g(incoming, outgoing)
+			/ (4.0 * incoming.z * outgoing.z);
+		self.diffuse.eval(incoming, outgoing, context) * (1.0 - f) + glossy * f
+	}
+
+	// diffuse and glossy lobes are sampled with equal probability
+	fn pdf(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &ShadingContext,
+	) -> algebra::Scalar {
+		if incoming.z <= 0.0 || outgoing.z <= 0.0 {
+			return 0.0;
+		}
+		0.5 * (self.diffuse.pdf(incoming, outgoing, context) + self.glossy_pdf(incoming, outgoing))
+	}
+
+	fn sample(
+		&self,
+		outgoing: algebra::Vector,
+		random: (f64, f64, f64),
+		context: &ShadingContext,
+	) -> std::option::Option<BxdfSample> {
+		if outgoing.z <= 0.0 {
+			return std::option::Option::None;
+		}
+		let incoming = if random.2 < 0.5 {
+			sample_cosine((random.0, random.1))
+		} else {
+			reflect(outgoing, self.microfacets.sample_visible(outgoing, (random.0, random.1)))
+		};
+		if incoming.z <= 0.0 {
+			return std::option::Option::None;
+		}
+		std::option::Option::Some(BxdfSample {
+			incoming,
+			value: self.eval(incoming, outgoing, context),
+			pdf: self.pdf(incoming, outgoing, context),
+			flags: self.flags(),
+		})
+	}
+}
+
+// Schlick-like retroreflection at grazing angles of cloth and dusty surfaces, added on top
+// of their diffuse reflection
+// source: https://media.disneyanimation.com/uploads/production/publication_asset/48/asset/s2012_pbs_disney_brdf_notes_v3.pdf
+#[derive(Clone, PartialEq, Debug)]
+pub struct Sheen {
+	color: spectrum::Spectrum,
+}
+
+impl Sheen {
+	pub fn new(color: spectrum::Spectrum) -> Sheen {
+		Sheen { color }
+	}
+}
+
+impl Bxdf for Sheen {
+	fn flags(&self) -> LobeFlags {
+		LobeFlags::REFLECTION | LobeFlags::DIFFUSE
+	}
+
+	fn eval(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &ShadingContext,
+	) -> algebra::Scalar {
+		if incoming.z <= 0.0 || outgoing.z <= 0.0 {
+			return 0.0;
+		}
+		let half_vec = (incoming + outgoing).normalize();
+		let color = self.color.evaluate(context.lambda).clamp(0.0, 1.0);
+		color * (1.0 - (incoming * half_vec).clamp(0.0, 1.0)).powi(5)
+	}
+
+	fn pdf(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		_context: &ShadingContext,
+	) -> algebra::Scalar {
+		if incoming.z <= 0.0 || outgoing.z <= 0.0 {
+			return 0.0;
+		}
+		incoming.z * constants::PI_INV
+	}
+
+	fn sample(
+		&self,
+		outgoing: algebra::Vector,
+		random: (f64, f64, f64),
+		context: &ShadingContext,
+	) -> std::option::Option<BxdfSample> {
+		if outgoing.z <= 0.0 {
+			return std::option::Option::None;
+		}
+		let incoming = sample_cosine((random.0, random.1));
+		std::option::Option::Some(BxdfSample {
+			incoming,
+			value: self.eval(incoming, outgoing, context),
+			pdf: self.pdf(incoming, outgoing, context),
+			flags: self.flags(),
+		})
+	}
+}
+
+// sheen of fibers sticking out of cloth, microfacet lobe whose normals crowd towards the
+// surface and brighten grazing angles; `roughness` between 0.0 and 1.0 spreads it, meant to be
+// added to diffuse lobe of the fabric
+// source: https://blog.selfshadow.com/publications/s2017-shading-course/imageworks/s2017_pbs_imageworks_sheen.pdf
+#[derive(Clone, PartialEq, Debug)]
+pub struct Charlie {
+	color: spectrum::Spectrum,
+	alpha: algebra::Scalar,
+}
+
+impl Charlie {
+	pub fn new(color: spectrum::Spectrum, roughness: algebra::Scalar) -> Charlie {
+		if !(0.0..=1.0).contains(&roughness) {
+			panic!("roughness of sheen must be between 0.0 and 1.0");
+		}
+		Charlie {
+			color,
+			alpha: (roughness * roughness).max(SMOOTH_ALPHA),
+		}
+	}
+
+	fn d(&self, half_vec: algebra::Vector) -> algebra::Scalar {
+		let sin2_theta = (1.0 - half_vec.z * half_vec.z).max(0.0);
+		let inverse = 1.0 / self.alpha;
+		(2.0 + inverse) * sin2_theta.powf(0.5 * inverse) / (2.0 * constants::PI)
+	}
+
+	// fit of logarithm of Smith's lambda for cosine below 0.5
+	fn fit(&self, x: algebra::Scalar) -> algebra::Scalar {
+		let t = 1.0 - (1.0 - self.alpha) * (1.0 - self.alpha);
+		let lerp = |a: algebra::Scalar, b: algebra::Scalar| a + (b - a) * t;
+		let a = lerp(25.3245, 21.5473);
+		let b = lerp(3.32435, 3.82987);
+		let c = lerp(0.16801, 0.19823);
+		let d = lerp(-1.27393, -1.97760);
+		let e = lerp(-4.85967, -4.32054);
+		a / (1.0 + b * x.powf(c)) + d * x + e
+	}
+
+	fn lambda(&self, cos_theta: algebra::Scalar) -> algebra::Scalar {
+		if cos_theta < 0.5 {
+			self.fit(cos_theta).exp()
+		} else {
+			(2.0 * self.fit(0.5) - self.fit(1.0 - cos_theta)).exp()
+		}
+	}
+}
+
+impl Bxdf for Charlie {
+	fn flags(&self) -> LobeFlags {
+		LobeFlags::REFLECTION | LobeFlags::GLOSSY
+	}
+
+	fn eval(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &ShadingContext,
+	) -> algebra::Scalar {
+		if incoming.z <= 0.0 || outgoing.z <= 0.0 {
+			return 0.0;
+		}
+		let half_vec = (incoming + outgoing).normalize();
+		let g = 1.0 / (1.0 + self.lambda(incoming.z) + self.lambda(outgoing.z));
+		let color = self.color.evaluate(context.lambda).clamp(0.0, 1.0);
+		color * self.d(half_vec) * g / (4.0 * incoming.z * outgoing.z)
+	}
+
+	// the lobe is wide, so cosine weighted sampling suits it
+	fn pdf(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		_context: &ShadingContext,
+	) -> algebra::Scalar {
+		if incoming.z <= 0.0 || outgoing.z <= 0.0 {
+			return 0.0;
+		}
+		incoming.z * constants::PI_INV
+	}
+
+	fn sample(
+		&self,
+		outgoing: algebra::Vector,
+		random: (f64, f64, f64),
+		context: &ShadingContext,
+	) -> std::option::Option<BxdfSample> {
+		if outgoing.z <= 0.0 {
+			return std::option::Option::None;
+		}
+		let incoming = sample_cosine((random.0, random.1));
+		std::option::Option::Some(BxdfSample {
+			incoming,
+			value: self.eval(incoming, outgoing, context),
+			pdf: self.pdf(incoming, outgoing, context),
+			flags: self.flags(),
+		})
+	}
+}
+
+// retroreflection of velvet pile, microfacet lobe around the direction back to the viewer
+// given by reflecting `outgoing` mirrored around the normal; smooth microfacets make a perfect
+// retroreflector
+#[derive(Clone, PartialEq, Debug)]
+pub struct Velvet {
+	color: spectrum::Spectrum,
+	microfacets: Ggx,
+}
+
+impl Velvet {
+	pub fn new(color: spectrum::Spectrum, roughness: algebra::Scalar) -> Velvet {
+		Velvet::with_microfacets(color, Ggx::isotropic(roughness))
+	}
+
+	pub fn with_microfacets(color: spectrum::Spectrum, microfacets: Ggx) -> Velvet {
+		Velvet { color, microfacets }
+	}
+
+	fn is_smooth(&self) -> bool {
+		self.microfacets.is_smooth()
+	}
+}
+
+impl Bxdf for Velvet {
+	fn flags(&self) -> LobeFlags {
+		if self.is_smooth() {
+			LobeFlags::REFLECTION | LobeFlags::SPECULAR
+		} else {
+			LobeFlags::REFLECTION | LobeFlags::GLOSSY
+		}
+	}
+
+	fn eval(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &ShadingContext,
+	) -> algebra::Scalar {
+		if incoming.z <= 0.0 || outgoing.z <= 0.0 {
+			return 0.0;
+		}
+		let color = self.color.evaluate(context.lambda).clamp(0.0, 1.0);
+		if self.is_smooth() {
+			return color / incoming.z;
+		}
+		let back = mirror_around_normal(outgoing);
+		let half_vec = (incoming + back).normalize();
+		let microfacets = &self.microfacets;
+		color * microfacets.d(half_vec) * microfacets.g(incoming, back)
+			/ (4.0 * incoming.z * outgoing.z)
+	}
+
+	fn pdf(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		_context: &ShadingContext,
+	) -> algebra::Scalar {
+		if incoming.z <= 0.0 || outgoing.z <= 0.0 {
+			return 0.0;
+		}
+		if self.is_smooth() {
+			return 1.0;
+		}
+		let back = mirror_around_normal(outgoing);
+		let half_vec = (incoming + back).normalize();
+		self.microfacets.pdf_visible(back, half_vec) / (4.0 * (back * half_vec))
+	}
+
+	fn sample(
+		&self,
+		outgoing: algebra::Vector,
+		random: (f64, f64, f64),
+		context: &ShadingContext,
+	) -> std::option::Option<BxdfSample> {
+		if outgoing.z <= 0.0 {
+			return std::option::Option::None;
+		}
+		let back = mirror_around_normal(outgoing);
+		let half_vec = if self.is_smooth() {
+			algebra::Vector::new(0.0, 0.0, 1.0)
+		} else {
+			self.microfacets.sample_visible(back, (random.0, random.1))
+		};
+		let incoming = reflect(back, half_vec);
+		if incoming.z <= 0.0 {
+			return std::option::Option::None;
+		}
+		std::option::Option::Some(BxdfSample {
+			incoming,
+			value: self.eval(incoming, outgoing, context),
+			pdf: self.pdf(incoming, outgoing, context),
+			flags: self.flags(),
+		})
+	}
+}
+
+// `lobe` scaled by wavelength dependent weight for light arriving from outside; from inside
+// of transparent objects it is left as is, so mixing a transmitting lobe with opaque ones
+// decides how much light enters, but not how much of it gets out again
+pub struct Weighted {
+	lobe: std::sync::Arc<dyn Bxdf>,
+	weight: Box<WeightFunction>,
+	// coat over the lobe and its weight, see `with_coat`
+	coat: std::option::Option<(Coat, algebra::Scalar)>,
+}
+
+type WeightFunction = dyn Fn(algebra::Scalar) -> algebra::Scalar + Send + Sync;
+
+impl Weighted {
+	pub fn new(lobe: std::sync::Arc<dyn Bxdf>, weight: algebra::Scalar) -> Weighted {
+		if weight < 0.0 {
+			panic!("weight of lobe must not be negative");
+		}
+		Weighted::spectral(lobe, move |_| weight)
+	}
+
+	// `weight` evaluated at wavelength in meters
+	pub fn spectral(
+		lobe: std::sync::Arc<dyn Bxdf>,
+		weight: impl Fn(algebra::Scalar) -> algebra::Scalar + Send + Sync + 'static,
+	) -> Weighted {
+		Weighted {
+			lobe,
+			weight: Box::new(weight),
+			coat: std::option::Option::None,
+		}
+	}
+
+	// lobe under `coat` covering fraction `weight` of the surface, which gets only light
+	// the coat does not reflect
+	pub fn with_coat(self, coat: Coat, weight: algebra::Scalar) -> Weighted {
+		if !(0.0..=1.0).contains(&weight) {
+			panic!("weight of coat must be between 0.0 and 1.0");
+		}
+		Weighted {
+			coat: std::option::Option::Some((coat, weight)),
+			..self
+		}
+	}
+}
+
+impl Bxdf for Weighted {
+	fn flags(&self) -> LobeFlags {
+		self.lobe.flags()
+	}
+
+	fn eval(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &ShadingContext,
+	) -> algebra::Scalar {
+		self.weight(outgoing, context) * self.lobe.eval(incoming, outgoing, context)
+	}
+
+	fn pdf(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &ShadingContext,
+	) -> algebra::Scalar {
+		self.lobe.pdf(incoming, outgoing, context)
+	}
+
+	fn sample(
+		&self,
+		outgoing: algebra::Vector,
+		random: (f64, f64, f64),
+		context: &ShadingContext,
+	) -> std::option::Option<BxdfSample> {
+		let sampled = self.lobe.sample(outgoing, random, context)?;
+		std::option::Option::Some(BxdfSample {
+			value: self.weight(outgoing, context) * sampled.value,
+			..sampled
+		})
+	}
+
+	fn weight(&self, outgoing: algebra::Vector, context: &ShadingContext) -> algebra::Scalar {
+		if outgoing.z <= 0.0 {
+			return 1.0;
+		}
+		let covered = match &self.coat {
+			std::option::Option::Some((coat, weight)) => {
+				weight * coat.albedo(outgoing.z, coat.eta(context))
+			}
+			std::option::Option::None => 0.0,
+		};
+		(self.weight)(context.lambda) * (1.0 - covered)
+	}
+}
+
+// dielectric layer thin enough for light reflected from its two sides to interfere;
+// `thickness` is given in nanometers and `ior` relative to the medium around the surface
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ThinFilm {
+	thickness: algebra::Scalar,
+	ior: algebra::Scalar,
+}
+
+impl ThinFilm {
+	pub fn new(thickness: algebra::Scalar, ior: algebra::Scalar) -> ThinFilm {
+		if thickness < 0.0 {
+			panic!("thin film thickness must not be negative");
+		}
+		if ior <= 0.0 {
+			panic!("thin film index of refraction must be positive");
+		}
+		ThinFilm { thickness, ior }
+	}
+
+	// unpolarized reflectance at wavelength `lambda` in meters of interface with relative
+	// complex index of refraction eta + ik covered by the film, summing all reflections
+	// inside it; negative `cos_theta` means light arriving from inside, which requires k = 0
+	// source: https://en.wikipedia.org/wiki/Transfer-matrix_method_(optics)
+	pub fn reflectance(
+		&self,
+		cos_theta: algebra::Scalar,
+		lambda: algebra::Scalar,
+		eta: algebra::Scalar,
+		k: algebra::Scalar,
+	) -> algebra::Scalar {
+		let (cos_i, incident, base) = if cos_theta < 0.0 {
+			(-cos_theta, algebra::Complex::from(eta), algebra::Complex::from(1.0))
+		} else {
+			(cos_theta, algebra::Complex::from(1.0), algebra::Complex::new(eta, k))
+		};
+		let cos_i = cos_i.min(1.0);
+		let film = algebra::Complex::from(self.ior);
+		// tangential component of the wave vector is kept across all interfaces, so index of
+		// refraction times cosine of the angle in each layer is
+		let sin2 = incident * incident * (1.0 - cos_i * cos_i);
+		let normal = |n: algebra::Complex| (n * n - sin2).sqrt();
+		let (q1, q2, q3) = (incident * cos_i, normal(film), normal(base));
+		// amplitude reflection coefficients for s and p polarization
+		let r_s = |q_i: algebra::Complex, q_j: algebra::Complex| (q_i - q_j) / (q_i + q_j);
+		let r_p = |n_i: algebra::Complex, q_i, n_j: algebra::Complex, q_j| {
+			(n_j * n_j * q_i - n_i * n_i * q_j) / (n_j * n_j * q_i + n_i * n_i * q_j)
+		};
+		// phase difference of a round trip through the film
+		let round_trip = 4.0 * constants::PI * self.thickness * 1e-9 / lambda;
+		let phase = (algebra::Complex::new(0.0, round_trip) * q2).exp();
+		let airy = |r12: algebra::Complex, r23: algebra::Complex| {
+			let r = (r12 + r23 * phase) / (algebra::Complex::from(1.0) + r12 * r23 * phase);
+			r.norm_sqr()
+		};
+		let s = airy(r_s(q1, q2), r_s(q2, q3));
+		let p = airy(r_p(incident, q1, film, q2), r_p(film, q2, base, q3));
+		(0.5 * (s + p)).clamp(0.0, 1.0)
+	}
+}
+
+// exact unpolarized Fresnel reflectance of interface between outside and inside with
+// relative index of refraction `eta`; negative `cos_theta` means light arriving from inside
+pub fn fresnel_dielectric(cos_theta: algebra::Scalar, eta: algebra::Scalar) -> algebra::Scalar {
+	let (cos_i, eta) = if cos_theta < 0.0 {
+		(-cos_theta, 1.0 / eta)
+	} else {
+		(cos_theta, eta)
+	};
+	let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+	if sin2_t >= 1.0 {
+		return 1.0;
+	}
+	let cos_t = (1.0 - sin2_t).sqrt();
+	let r_parallel = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+	let r_perpendicular = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+	0.5 * (r_parallel * r_parallel + r_perpendicular * r_perpendicular)
+}
+
+// exact unpolarized reflectance of conductor with complex index of refraction n + ik
+// source: https://seblagarde.wordpress.com/2013/04/29/memo-on-fresnel-equations/
+pub fn fresnel_conductor(
+	cos_theta: algebra::Scalar,
+	n: algebra::Scalar,
+	k: algebra::Scalar,
+) -> algebra::Scalar {
+	let cos_theta = cos_theta.clamp(0.0, 1.0);
+	let cos2 = cos_theta * cos_theta;
+	let sin2 = 1.0 - cos2;
+	let t0 = n * n - k * k - sin2;
+	let a2_plus_b2 = (t0 * t0 + 4.0 * n * n * k * k).sqrt();
+	let t1 = a2_plus_b2 + cos2;
+	let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+	let t2 = 2.0 * cos_theta * a;
+	let r_s = (t1 - t2) / (t1 + t2);
+	let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+	let t4 = t2 * sin2;
+	let r_p = r_s * (t3 - t4) / (t3 + t4);
+	0.5 * (r_p + r_s)
+}
+
+// direction of light refracted from `direction` through surface with `normal`,
+// both pointing away from the surface; none for total internal reflection
+pub fn refract(
+	direction: algebra::Vector,
+	normal: algebra::Vector,
+	eta: algebra::Scalar,
+) -> std::option::Option<algebra::Vector> {
+	let mut cos_i = direction * normal;
+	let (normal, eta) = if cos_i < 0.0 {
+		cos_i = -cos_i;
+		(-normal, 1.0 / eta)
+	} else {
+		(normal, eta)
+	};
+	let sin2_t = (1.0 - cos_i * cos_i).max(0.0) / (eta * eta);
+	if sin2_t >= 1.0 {
+		return std::option::Option::None;
+	}
+	let cos_t = (1.0 - sin2_t).sqrt();
+	std::option::Option::Some(-direction / eta + (cos_i / eta - cos_t) * normal)
+}
+
+// direction below the surface reversed to point above it
+fn mirror_above(direction: algebra::Vector) -> algebra::Vector {
+	if direction.z < 0.0 {
+		-direction
+	} else {
+		direction
+	}
+}
+
+// `outgoing` turned by half a revolution around the normal
+fn mirror_around_normal(outgoing: algebra::Vector) -> algebra::Vector {
+	algebra::Vector::new(-outgoing.x, -outgoing.y, outgoing.z)
+}
+
+fn reflect(direction: algebra::Vector, normal: algebra::Vector) -> algebra::Vector {
+	2.0 * (direction * normal) * normal - direction
+}
+
+// cosine weighted direction on the hemisphere around z axis
+pub fn sample_cosine(random: (f64, f64)) -> algebra::Vector {
+	let cos_theta = random.0.sqrt();
+	let sin_theta = (1.0 - random.0).sqrt();
+	let phi = random.1 * 2.0 * constants::PI;
+	algebra::Vector::new(phi.cos() * sin_theta, phi.sin() * sin_theta, cos_theta)
+}
+
+// microfacet normal between `incoming` and `outgoing` facing the outside;
+// `etap` is relative index of refraction, 1.0 for reflection
+fn generalized_half_vector(
+	incoming: algebra::Vector,
+	outgoing: algebra::Vector,
+	etap: algebra::Scalar,
+) -> std::option::Option<algebra::Vector> {
+	let half_vec = incoming * etap + outgoing;
+	if half_vec.norm_sqr() == 0.0 {
+		return std::option::Option::None;
+	}
+	let half_vec = if half_vec.z < 0.0 {
+		-half_vec.normalize()
+	} else {
+		half_vec.normalize()
+	};
+	// backfacing microfacets do not contribute
+	if (half_vec * incoming) * incoming.z < 0.0 || (half_vec * outgoing) * outgoing.z < 0.0 {
+		return std::option::Option::None;
+	}
+	std::option::Option::Some(half_vec)
 }
@@ -0,0 +1,195 @@
+use crate::algebra;
+use crate::constants;
+use crate::upsampling;
+
+// spectral distribution evaluated at wavelength in meters; used both for
+// reflectances and for emitted radiance
+#[derive(Clone, PartialEq, Debug)]
+pub enum Spectrum {
+	Constant(algebra::Scalar),
+	// polynomial coefficients in raw wavelength, lowest power first
+	Polynomial(Vec<algebra::Scalar>),
+	// piecewise linear data given as (wavelength, value) pairs sorted by wavelength;
+	// values are extended as constant outside of tabulated range
+	Sampled(Vec<(algebra::Scalar, algebra::Scalar)>),
+	// Planck's law for given temperature in kelvins
+	BlackBody(algebra::Scalar),
+	// Planck's law normalized to 1.0 at its peak
+	BlackBodyNormalized(algebra::Scalar),
+	// linear RGB triple upsampled to spectrum
+	Rgb(algebra::Scalar, algebra::Scalar, algebra::Scalar),
+}
+
+impl Spectrum {
+	pub fn sampled(data: Vec<(algebra::Scalar, algebra::Scalar)>) -> Spectrum {
+		if data.is_empty() {
+			panic!("Sampled spectrum must contain at least one sample");
+		}
+		if data.windows(2).any(|w| w[0].0 >= w[1].0) {
+			panic!("Sampled spectrum wavelengths must be strictly increasing");
+		}
+		Spectrum::Sampled(data)
+	}
+
+	// samples equally spaced from `lambda_min` to `lambda_max` inclusive
+	pub fn sampled_uniform(
+		lambda_min: algebra::Scalar,
+		lambda_max: algebra::Scalar,
+		values: &[algebra::Scalar],
+	) -> Spectrum {
+		if values.len() < 2 {
+			panic!("Uniformly sampled spectrum must contain at least two samples");
+		}
+		let step = (lambda_max - lambda_min) / (values.len() - 1) as algebra::Scalar;
+		Spectrum::sampled(
+			values
+				.iter()
+				.enumerate()
+				.map(|(i, v)| (lambda_min + i as algebra::Scalar * step, *v))
+				.collect(),
+		)
+	}
+
+	// gamma encoded sRGB triple with components in range [0, 1]
+	pub fn srgb(r: algebra::Scalar, g: algebra::Scalar, b: algebra::Scalar) -> Spectrum {
+		Spectrum::Rgb(
+			upsampling::srgb_to_linear(r),
+			upsampling::srgb_to_linear(g),
+			upsampling::srgb_to_linear(b),
+		)
+	}
+
+	pub fn evaluate(&self, lambda: algebra::Scalar) -> algebra::Scalar {
+		match self {
+			Spectrum::Constant(value) => *value,
+			Spectrum::Polynomial(coefficients) => {
+				let mut value: algebra::Scalar = 0.0;
+				for (power, coefficient) in coefficients.iter().enumerate() {
+					value += coefficient * lambda.powi(power as i32);
+				}
+				value
+			}
+			Spectrum::Sampled(data) => {
+				let i = data.partition_point(|sample| sample.0 <= lambda);
+				if i == 0 {
+					return data[0].1;
+				}
+				if i == data.len() {
+					return data[i - 1].1;
+				}
+				let (a, b) = (data[i - 1], data[i]);
+				let t = (lambda - a.0) / (b.0 - a.0);
+				a.1 + (b.1 - a.1) * t
+			}
+			Spectrum::BlackBody(temperature) => planck(lambda, *temperature),
+			Spectrum::BlackBodyNormalized(temperature) => {
+				planck(lambda, *temperature) / planck(constants::WIEN / temperature, *temperature)
+			}
+			Spectrum::Rgb(r, g, b) => upsampling::linear_rgb_to_spectrum((*r, *g, *b), lambda),
+		}
+	}
+}
+
+// spectral radiance of blackbody in W / (sr m^3)
+fn planck(lambda: algebra::Scalar, temperature: algebra::Scalar) -> algebra::Scalar {
+	constants::TWO_HC2
+		/ (lambda.powi(5) * ((constants::HC_BY_K / lambda / temperature).exp() - 1.0))
+}
+
+// patches of the X-Rite ColorChecker Classic, row by row
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ColorChecker {
+	DarkSkin,
+	LightSkin,
+	BlueSky,
+	Foliage,
+	BlueFlower,
+	BluishGreen,
+	Orange,
+	PurplishBlue,
+	ModerateRed,
+	Purple,
+	YellowGreen,
+	OrangeYellow,
+	Blue,
+	Green,
+	Red,
+	Yellow,
+	Magenta,
+	Cyan,
+	White,
+	Neutral8,
+	Neutral65,
+	Neutral5,
+	Neutral35,
+	Black,
+}
+
+impl ColorChecker {
+	pub const ALL: [ColorChecker; 24] = [
+		ColorChecker::DarkSkin,
+		ColorChecker::LightSkin,
+		ColorChecker::BlueSky,
+		ColorChecker::Foliage,
+		ColorChecker::BlueFlower,
+		ColorChecker::BluishGreen,
+		ColorChecker::Orange,
+		ColorChecker::PurplishBlue,
+		ColorChecker::ModerateRed,
+		ColorChecker::Purple,
+		ColorChecker::YellowGreen,
+		ColorChecker::OrangeYellow,
+		ColorChecker::Blue,
+		ColorChecker::Green,
+		ColorChecker::Red,
+		ColorChecker::Yellow,
+		ColorChecker::Magenta,
+		ColorChecker::Cyan,
+		ColorChecker::White,
+		ColorChecker::Neutral8,
+		ColorChecker::Neutral65,
+		ColorChecker::Neutral5,
+		ColorChecker::Neutral35,
+		ColorChecker::Black,
+	];
+
+	// 8 bit sRGB value of the patch under D65
+	// source: https://www.babelcolor.com/index_htm_files/ColorChecker_RGB_and_spectra.zip
+	pub fn srgb(&self) -> (u8, u8, u8) {
+		match self {
+			ColorChecker::DarkSkin => (115, 82, 68),
+			ColorChecker::LightSkin => (194, 150, 130),
+			ColorChecker::BlueSky => (98, 122, 157),
+			ColorChecker::Foliage => (87, 108, 67),
+			ColorChecker::BlueFlower => (133, 128, 177),
+			ColorChecker::BluishGreen => (103, 189, 170),
+			ColorChecker::Orange => (214, 126, 44),
+			ColorChecker::PurplishBlue => (80, 91, 166),
+			ColorChecker::ModerateRed => (193, 90, 99),
+			ColorChecker::Purple => (94, 60, 108),
+			ColorChecker::YellowGreen => (157, 188, 64),
+			ColorChecker::OrangeYellow => (224, 163, 46),
+			ColorChecker::Blue => (56, 61, 150),
+			ColorChecker::Green => (70, 148, 73),
+			ColorChecker::Red => (175, 54, 60),
+			ColorChecker::Yellow => (231, 199, 31),
+			ColorChecker::Magenta => (187, 86, 149),
+			ColorChecker::Cyan => (8, 133, 161),
+			ColorChecker::White => (243, 243, 242),
+			ColorChecker::Neutral8 => (200, 200, 200),
+			ColorChecker::Neutral65 => (160, 160, 160),
+			ColorChecker::Neutral5 => (122, 122, 121),
+			ColorChecker::Neutral35 => (85, 85, 85),
+			ColorChecker::Black => (52, 52, 52),
+		}
+	}
+
+	// measured spectral reflectance of the patch, held constant outside 380 - 730 nm
+	pub fn spectrum(&self) -> Spectrum {
+		Spectrum::sampled_uniform(
+			constants::COLOR_CHECKER_LAMBDA_MIN,
+			constants::COLOR_CHECKER_LAMBDA_MAX,
+			&constants::COLOR_CHECKER_REFLECTANCE[*self as usize],
+		)
+	}
+}
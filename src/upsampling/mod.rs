@@ -0,0 +1,89 @@
+use crate::algebra;
+
+// RGB to spectrum conversion with Smits' basis spectra
+// source: https://www.cs.utah.edu/~bes/papers/color/paper.pdf
+// tables are sampled in 10 equal bins between 380 and 720 nm
+const SMITS_LAMBDA_MIN: algebra::Scalar = 380.0e-9;
+const SMITS_LAMBDA_MAX: algebra::Scalar = 720.0e-9;
+const SMITS_WHITE: [algebra::Scalar; 10] = [1.0; 10];
+const SMITS_CYAN: [algebra::Scalar; 10] = [
+	0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+];
+const SMITS_MAGENTA: [algebra::Scalar; 10] = [
+	1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+];
+const SMITS_YELLOW: [algebra::Scalar; 10] = [
+	0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+];
+const SMITS_RED: [algebra::Scalar; 10] = [
+	0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+];
+const SMITS_GREEN: [algebra::Scalar; 10] = [
+	0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+];
+const SMITS_BLUE: [algebra::Scalar; 10] = [
+	1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+];
+
+// evaluates spectrum of linear RGB triple at wavelength `lambda` (in meters);
+// wavelengths outside of the tabulated range take value of the closest bin
+pub fn linear_rgb_to_spectrum(
+	rgb: (algebra::Scalar, algebra::Scalar, algebra::Scalar),
+	lambda: algebra::Scalar,
+) -> algebra::Scalar {
+	let bin = (((lambda - SMITS_LAMBDA_MIN) / (SMITS_LAMBDA_MAX - SMITS_LAMBDA_MIN) * 10.0)
+		.floor()
+		.clamp(0.0, 9.0)) as usize;
+	let (r, g, b) = rgb;
+	let mut value: algebra::Scalar = 0.0;
+	if r <= g && r <= b {
+		value += r * SMITS_WHITE[bin];
+		if g <= b {
+			value += (g - r) * SMITS_CYAN[bin];
+			value += (b - g) * SMITS_BLUE[bin];
+		} else {
+			value += (b - r) * SMITS_CYAN[bin];
+			value += (g - b) * SMITS_GREEN[bin];
+		}
+	} else if g <= r && g <= b {
+		value += g * SMITS_WHITE[bin];
+		if r <= b {
+			value += (r - g) * SMITS_MAGENTA[bin];
+			value += (b - r) * SMITS_BLUE[bin];
+		} else {
+			value += (b - g) * SMITS_MAGENTA[bin];
+			value += (r - b) * SMITS_RED[bin];
+		}
+	} else {
+		value += b * SMITS_WHITE[bin];
+		if r <= g {
+			value += (r - b) * SMITS_YELLOW[bin];
+			value += (g - r) * SMITS_GREEN[bin];
+		} else {
+			value += (g - b) * SMITS_YELLOW[bin];
+			value += (r - g) * SMITS_RED[bin];
+		}
+	}
+	value.max(0.0)
+}
+
+// removes sRGB transfer function from a single component in range [0, 1]
+// source: https://www.color.org/chardata/rgb/srgb.xalter
+pub fn srgb_to_linear(c: algebra::Scalar) -> algebra::Scalar {
+	if c <= 0.04045 {
+		c / 12.92
+	} else {
+		((c + 0.055) / 1.055).powf(2.4)
+	}
+}
+
+// evaluates spectrum of gamma encoded sRGB triple at wavelength `lambda` (in meters)
+pub fn srgb_to_spectrum(
+	rgb: (algebra::Scalar, algebra::Scalar, algebra::Scalar),
+	lambda: algebra::Scalar,
+) -> algebra::Scalar {
+	linear_rgb_to_spectrum(
+		(srgb_to_linear(rgb.0), srgb_to_linear(rgb.1), srgb_to_linear(rgb.2)),
+		lambda,
+	)
+}
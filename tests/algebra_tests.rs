@@ -120,3 +120,17 @@ fn spherical() {
 	let result: (algebra::Scalar, algebra::Scalar) = b.basis_to_spherical(v1.normalize());
 	assert_eq!(result, (0.6405223126794245, 1.1071487177940904));
 }
+
+#[test]
+fn complex() {
+	let a = algebra::Complex::new(1.0, 2.0);
+	let b = algebra::Complex::new(-3.0, 0.5);
+	assert_eq!(a * b, algebra::Complex::new(-4.0, -5.5));
+	assert!(((a * b) / b - a).norm_sqr() < 1e-24);
+	assert_eq!(a + b - b, a);
+	let root = algebra::Complex::new(-4.0, 0.0).sqrt();
+	assert_eq!(root, algebra::Complex::new(0.0, 2.0));
+	assert!((a.sqrt() * a.sqrt() - a).norm_sqr() < 1e-24);
+	let unit = algebra::Complex::new(0.0, std::f64::consts::PI).exp();
+	assert!((unit - algebra::Complex::from(-1.0)).norm_sqr() < 1e-24);
+}
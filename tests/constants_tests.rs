@@ -0,0 +1,58 @@
+use nilepsilon::constants;
+
+#[test]
+fn radiation_constants() {
+	assert!((constants::HC_BY_K - 1.438776877e-2).abs() < 1e-11);
+	assert!((constants::TWO_HC2 - 1.191042972e-16).abs() < 1e-24);
+	assert_eq!(constants::PI * constants::PI_INV, 1.0);
+}
+
+#[test]
+fn observers_equal_energy_white() {
+	// equal energy spectrum has the same X, Y and Z for both observers
+	let sum = |table: &[(f64, f64, f64)]| {
+		table.iter().fold((0.0, 0.0, 0.0), |acc, c| {
+			(acc.0 + c.0, acc.1 + c.1, acc.2 + c.2)
+		})
+	};
+	for (x, y, z) in [
+		sum(&constants::CIE_XYZ_1931_COLOR_MATCH_2_DEG),
+		sum(&constants::CIE_XYZ_1964_COLOR_MATCH_10_DEG),
+	] {
+		assert!((x / y - 1.0).abs() < 1e-3);
+		assert!((z / y - 1.0).abs() < 1e-3);
+	}
+	// both observers cover 360 - 830 nm
+	assert_eq!(constants::CIE_XYZ_1931_COLOR_MATCH_2_DEG.len(), 830 - 360 + 1);
+	assert_eq!(constants::CIE_XYZ_1964_COLOR_MATCH_10_DEG.len(), (830 - 360) / 5 + 1);
+}
+
+#[test]
+fn fluorescent_illuminants_chromaticity() {
+	// source: CIE 15:2004, table T.8
+	let reference = [
+		(0.3131, 0.3373),
+		(0.3721, 0.3751),
+		(0.4091, 0.3941),
+		(0.4402, 0.4031),
+		(0.3138, 0.3452),
+		(0.3779, 0.3882),
+		(0.3129, 0.3292),
+		(0.3458, 0.3586),
+		(0.3741, 0.3727),
+		(0.3458, 0.3588),
+		(0.3805, 0.3769),
+		(0.4370, 0.4042),
+	];
+	for (spd, xy) in constants::CIE_ILLUMINANT_F.iter().zip(reference) {
+		let (mut x, mut y, mut z) = (0.0, 0.0, 0.0);
+		for (i, value) in spd.iter().enumerate() {
+			let cmf = constants::CIE_XYZ_1931_COLOR_MATCH_2_DEG[20 + 5 * i];
+			x += value * cmf.0;
+			y += value * cmf.1;
+			z += value * cmf.2;
+		}
+		assert!((x / (x + y + z) - xy.0).abs() < 5e-4);
+		assert!((y / (x + y + z) - xy.1).abs() < 5e-4);
+	}
+}
@@ -0,0 +1,269 @@
+use nilepsilon::algebra;
+use nilepsilon::constants;
+use nilepsilon::environment;
+use nilepsilon::scene;
+use nilepsilon::spectrum;
+
+// 8x4 map with a single bright pixel pointing near +x on the horizon
+fn bright_spot_map() -> environment::EnvironmentMap {
+	let mut pixels = vec![(0.1, 0.1, 0.1); 8 * 4];
+	pixels[8] = (10.0, 10.0, 10.0);
+	environment::EnvironmentMap::new(8, 4, pixels, 0.0)
+}
+
+fn hdr_header(width: usize, height: usize) -> Vec<u8> {
+	format!(
+		"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+		height, width
+	)
+	.into_bytes()
+}
+
+#[test]
+fn environment_hdr_flat() {
+	let mut bytes = hdr_header(2, 1);
+	// 1.0 is stored as mantissa 128 with exponent 129
+	bytes.extend_from_slice(&[128, 64, 0, 129, 128, 128, 128, 130]);
+	let map = environment::EnvironmentMap::from_hdr_bytes(&bytes, 0.0).unwrap();
+	assert_eq!((map.width, map.height), (2, 1));
+	assert_eq!(
+		map.return_rgb(algebra::Vector::new(1.0, 0.1, 0.0)),
+		(1.0, 0.5, 0.0)
+	);
+	assert_eq!(
+		map.return_rgb(algebra::Vector::new(-1.0, -0.1, 0.0)),
+		(2.0, 2.0, 2.0)
+	);
+}
+
+#[test]
+fn environment_hdr_run_length() {
+	let mut bytes = hdr_header(8, 1);
+	bytes.extend_from_slice(&[2, 2, 0, 8]);
+	// red: run of 8, green: 8 literal values, blue: run of 8, exponent: run of 8
+	bytes.extend_from_slice(&[128 + 8, 128]);
+	bytes.extend_from_slice(&[8, 0, 16, 32, 48, 64, 80, 96, 112]);
+	bytes.extend_from_slice(&[128 + 8, 0]);
+	bytes.extend_from_slice(&[128 + 8, 129]);
+	let map = environment::EnvironmentMap::from_hdr_bytes(&bytes, 0.0).unwrap();
+	assert_eq!((map.width, map.height), (8, 1));
+	let phi = 2.0 * constants::PI * 5.5 / 8.0;
+	assert_eq!(
+		map.return_rgb(algebra::Vector::new(phi.cos(), phi.sin(), 0.0)),
+		(1.0, 0.625, 0.0)
+	);
+}
+
+#[test]
+fn environment_hdr_invalid() {
+	let bytes = b"P3\n2 2\n255\n".to_vec();
+	assert!(environment::EnvironmentMap::from_hdr_bytes(&bytes, 0.0).is_err());
+	let mut truncated = hdr_header(4, 4);
+	truncated.extend_from_slice(&[1, 2, 3]);
+	assert!(environment::EnvironmentMap::from_hdr_bytes(&truncated, 0.0).is_err());
+}
+
+#[test]
+fn environment_rotation() {
+	let mut pixels = vec![(0.0, 0.0, 0.0); 4];
+	pixels[0] = (1.0, 1.0, 1.0);
+	let map = environment::EnvironmentMap::new(4, 1, pixels, 0.5 * constants::PI);
+	// first column now starts at +y instead of +x
+	let dir = algebra::Vector::new(-0.1, 1.0, 0.0);
+	assert_eq!(map.return_rgb(dir), (1.0, 1.0, 1.0));
+	let dir = algebra::Vector::new(0.1, 1.0, 0.0);
+	assert_eq!(map.return_rgb(dir), (0.0, 0.0, 0.0));
+}
+
+#[test]
+fn environment_sample_pdf_matches() {
+	let map = bright_spot_map();
+	let randoms = [(0.1, 0.2), (0.5, 0.5), (0.03, 0.4), (0.9, 0.95), (0.7, 0.3)];
+	for random in randoms {
+		let (dir, pdf) = map.sample_direction(random);
+		assert!((dir.norm() - 1.0).abs() < 1e-9);
+		assert!((map.pdf(dir) - pdf).abs() < 1e-9 * pdf.max(1.0));
+	}
+	// bright pixel is sampled far more often than a dark one
+	let bright = algebra::Vector::new(1.0, 0.1, 0.3);
+	let dark = algebra::Vector::new(-1.0, 0.1, 0.3);
+	assert!(map.pdf(bright) > 50.0 * map.pdf(dark));
+}
+
+#[test]
+fn environment_pdf_normalized() {
+	let map = bright_spot_map();
+	// integrate pdf over the sphere on a fine grid
+	let (nt, np) = (400, 800);
+	let mut integral = 0.0;
+	for j in 0..nt {
+		let theta = (j as algebra::Scalar + 0.5) / nt as algebra::Scalar * constants::PI;
+		for i in 0..np {
+			let phi = (i as algebra::Scalar + 0.5) / np as algebra::Scalar * 2.0 * constants::PI;
+			let dir = algebra::Vector::new(
+				theta.sin() * phi.cos(),
+				theta.sin() * phi.sin(),
+				theta.cos(),
+			);
+			integral += map.pdf(dir)
+				* theta.sin() * (constants::PI / nt as algebra::Scalar)
+				* (2.0 * constants::PI / np as algebra::Scalar);
+		}
+	}
+	assert!((integral - 1.0).abs() < 1e-2, "pdf integrates to {}", integral);
+}
+
+#[test]
+fn environment_background_radiance() {
+	let map = environment::EnvironmentMap::new(1, 1, vec![(0.5, 0.5, 0.5)], 0.0);
+	let bg = scene::Background {
+		color: scene::BackgroundType::EnvironmentMap(map),
+		radiance: 4.0,
+	};
+	assert!(bg.is_sampled());
+	for lambda in [380.0e-9, 450.0e-9, 550.0e-9, 700.0e-9] {
+		let value = bg.return_radiance(algebra::Vector::new(0.0, 1.0, 0.0), lambda);
+		assert!((value - 2.0).abs() < 1e-9);
+	}
+}
+
+// luminance and chromaticity of spectral radiance in given direction
+fn sky_yxy(sky: &environment::Sky, dir: algebra::Vector) -> (f64, f64, f64) {
+	let (mut x, mut y, mut z, mut y_sum) = (0.0, 0.0, 0.0, 0.0);
+	for (i, cmf) in constants::CIE_XYZ_1931_COLOR_MATCH_2_DEG.iter().enumerate() {
+		let value = sky.return_radiance(dir, (360.0 + i as f64) * 1e-9);
+		x += cmf.0 * value;
+		y += cmf.1 * value;
+		z += cmf.2 * value;
+		y_sum += cmf.1;
+	}
+	(y / y_sum, x / (x + y + z), y / (x + y + z))
+}
+
+#[test]
+fn sky_spectrum_matches_model() {
+	let sky = environment::Sky::new(0.25 * constants::PI, 0.0, 3.0);
+	for dir in [
+		algebra::Vector::new(0.0, 0.0, 1.0),
+		algebra::Vector::new(-1.0, 0.0, 0.3),
+		algebra::Vector::new(0.0, 1.0, 0.1),
+	] {
+		let model = sky.return_yxy(dir.normalize());
+		let spectral = sky_yxy(&sky, dir);
+		assert!((model.0 - spectral.0).abs() < 0.02 * model.0);
+		assert!((model.1 - spectral.1).abs() < 0.01);
+		assert!((model.2 - spectral.2).abs() < 0.01);
+	}
+	// zenith luminance in kcd/m^2 for this configuration
+	let zenith = sky.return_yxy(algebra::Vector::new(0.0, 0.0, 1.0));
+	assert!((zenith.0 - 7.33).abs() < 0.01);
+}
+
+#[test]
+fn sky_sun_reddens_at_horizon() {
+	let high = environment::Sky::new(1.2, 0.0, 3.0);
+	let low = environment::Sky::new(0.05, 0.0, 3.0);
+	let ratio = |sky: &environment::Sky| {
+		sky.return_radiance(sky.sun_direction, 650.0e-9)
+			/ sky.return_radiance(sky.sun_direction, 450.0e-9)
+	};
+	assert!(ratio(&low) > 1.5 * ratio(&high));
+	// sun is far brighter than the sky around it
+	let beside = algebra::Vector::new(0.0, 0.2, 0.0) + high.sun_direction;
+	assert!(
+		high.return_radiance(high.sun_direction, 550.0e-9)
+			> 1e4 * high.return_radiance(beside, 550.0e-9)
+	);
+}
+
+#[test]
+fn sky_sun_sampling() {
+	let sky = environment::Sky::new(0.6, 2.0, 4.0);
+	let bg = scene::Background {
+		color: scene::BackgroundType::Sky(sky),
+		radiance: 1.0,
+	};
+	for random in [(0.0, 0.0), (0.5, 0.25), (0.999, 0.9)] {
+		let (dir, pdf) = bg.sample_direction(random).unwrap();
+		assert!(pdf > 0.0);
+		assert_eq!(bg.pdf(dir), pdf);
+	}
+	assert_eq!(bg.pdf(algebra::Vector::new(0.0, 0.0, 1.0)), 0.0);
+}
+
+#[test]
+#[should_panic]
+fn sky_invalid_turbidity() {
+	environment::Sky::new(0.5, 0.0, 0.5);
+}
+
+#[test]
+fn background_constant_and_gradient() {
+	let constant = scene::Background {
+		color: scene::BackgroundType::Constant(spectrum::Spectrum::Constant(0.5)),
+		radiance: 2.0,
+	};
+	let gradient = scene::Background {
+		color: scene::BackgroundType::Gradient {
+			bottom: spectrum::Spectrum::Constant(0.0),
+			top: spectrum::Spectrum::Constant(1.0),
+		},
+		radiance: 3.0,
+	};
+	let up = algebra::Vector::new(0.0, 0.0, 1.0);
+	let down = algebra::Vector::new(0.0, 0.0, -2.0);
+	let side = algebra::Vector::new(1.0, 1.0, 0.0);
+	assert_eq!(constant.return_radiance(up, 500.0e-9), 1.0);
+	assert_eq!(constant.return_radiance(down, 700.0e-9), 1.0);
+	assert_eq!(gradient.return_radiance(up, 500.0e-9), 3.0);
+	assert_eq!(gradient.return_radiance(down, 500.0e-9), 0.0);
+	assert!((gradient.return_radiance(side, 500.0e-9) - 1.5).abs() < 1e-12);
+	assert!(!constant.is_sampled() && !gradient.is_sampled());
+}
+
+#[test]
+fn background_illuminant() {
+	// luminance and chromaticity of the illuminant integrated with 1 nm steps
+	let yxy = |bg: &scene::Background| {
+		let dir = algebra::Vector::new(0.0, 1.0, 0.0);
+		let (mut x, mut y, mut z, mut y_sum) = (0.0, 0.0, 0.0, 0.0);
+		for (i, cmf) in constants::CIE_XYZ_1931_COLOR_MATCH_2_DEG.iter().enumerate() {
+			let value = bg.return_radiance(dir, (360.0 + i as f64) * 1e-9);
+			x += cmf.0 * value;
+			y += cmf.1 * value;
+			z += cmf.2 * value;
+			y_sum += cmf.1;
+		}
+		(y / y_sum, x / (x + y + z), y / (x + y + z))
+	};
+	let d65 = scene::Background {
+		color: scene::BackgroundType::Illuminant(environment::Illuminant::D65),
+		radiance: 5.0,
+	};
+	let a = scene::Background {
+		color: scene::BackgroundType::Illuminant(environment::Illuminant::A),
+		radiance: 0.5,
+	};
+	let (lum, x, y) = yxy(&d65);
+	assert!((lum - 5.0).abs() < 1e-6);
+	assert!((x - 0.3127).abs() < 2e-4 && (y - 0.3290).abs() < 2e-4);
+	let (lum, x, y) = yxy(&a);
+	assert!((lum - 0.5).abs() < 1e-6);
+	assert!((x - 0.4476).abs() < 2e-4 && (y - 0.4074).abs() < 2e-4);
+	assert!((environment::Illuminant::A.return_spd(560.0e-9) - 100.0).abs() < 1e-9);
+	assert!((environment::Illuminant::D65.return_spd(560.0e-9) - 100.0).abs() < 1e-9);
+	for (illuminant, xy) in [
+		(environment::Illuminant::D50, (0.3457, 0.3585)),
+		(environment::Illuminant::E, (1.0 / 3.0, 1.0 / 3.0)),
+		(environment::Illuminant::F2, (0.3721, 0.3751)),
+		(environment::Illuminant::F11, (0.3805, 0.3769)),
+	] {
+		let bg = scene::Background {
+			color: scene::BackgroundType::Illuminant(illuminant),
+			radiance: 1.0,
+		};
+		let (lum, x, y) = yxy(&bg);
+		assert!((lum - 1.0).abs() < 1e-6);
+		assert!((x - xy.0).abs() < 1e-3 && (y - xy.1).abs() < 1e-3);
+	}
+}
@@ -0,0 +1,1009 @@
+use nilepsilon::algebra;
+use nilepsilon::materials;
+use nilepsilon::sampler;
+use nilepsilon::shaders;
+use nilepsilon::shaders::Bxdf;
+use nilepsilon::spectrum;
+
+// sodium D line
+const LAMBDA_D: f64 = 589.3e-9;
+
+#[test]
+fn ior_presets() {
+	let close = |ior: materials::Ior, expected: f64| {
+		let n = ior.evaluate(LAMBDA_D);
+		assert!((n - expected).abs() < 1e-3, "expected {}, got {}", expected, n);
+	};
+	close(materials::Ior::bk7(), 1.5168);
+	close(materials::Ior::fused_silica(), 1.4585);
+	close(materials::Ior::diamond(), 2.4175);
+	close(materials::Ior::water(), 1.3330);
+}
+
+#[test]
+fn ior_normal_dispersion() {
+	for ior in [
+		materials::Ior::bk7(),
+		materials::Ior::fused_silica(),
+		materials::Ior::diamond(),
+		materials::Ior::water(),
+		materials::Ior::Cauchy { a: 1.5046, b: 0.0042 },
+	] {
+		assert!(ior.is_dispersive());
+		assert!(ior.evaluate(400.0e-9) > ior.evaluate(550.0e-9));
+		assert!(ior.evaluate(550.0e-9) > ior.evaluate(700.0e-9));
+	}
+}
+
+#[test]
+fn ior_cauchy_and_constant() {
+	let cauchy = materials::Ior::Cauchy { a: 1.5, b: 0.01 };
+	assert!((cauchy.evaluate(500.0e-9) - 1.54).abs() < 1e-12);
+	let constant: materials::Ior = 1.33.into();
+	assert_eq!(constant, materials::Ior::Constant(1.33));
+	assert_eq!(constant.evaluate(400.0e-9), 1.33);
+	assert!(!constant.is_dispersive());
+}
+
+#[test]
+fn material_dispersive() {
+	let glass = |ior: materials::Ior| {
+		materials::Material::new(
+			materials::EmissionType::NonEmissive,
+			materials::SurfaceType::DielectricTransparent {
+				roughness: 0.0,
+				film: None,
+			},
+			ior,
+			0.0,
+		)
+	};
+	assert!(glass(materials::Ior::bk7()).is_dispersive());
+	assert!(!glass(materials::Ior::Constant(1.5)).is_dispersive());
+	// refraction angle follows index of refraction at each wavelength
+	let theta_o = 0.5;
+	let diamond = glass(materials::Ior::diamond());
+	// refraction is always chosen at normal incidence with third random number close to 1
+	let random = (0.5, 0.5, 0.999);
+	let (blue, _) = sample_angles(&diamond, theta_o, 0.0, random, 400.0e-9, 1.0).unwrap();
+	let (red, _) = sample_angles(&diamond, theta_o, 0.0, random, 700.0e-9, 1.0).unwrap();
+	let sin_t = |theta: f64| (std::f64::consts::PI - theta).sin();
+	let n_blue = materials::Ior::diamond().evaluate(400.0e-9);
+	let n_red = materials::Ior::diamond().evaluate(700.0e-9);
+	assert!((sin_t(blue) * n_blue - theta_o.sin()).abs() < 1e-12);
+	assert!((sin_t(red) * n_red - theta_o.sin()).abs() < 1e-12);
+	assert!(blue != red);
+}
+
+fn conductor_reflectance(n: f64, k: f64, cos_theta: f64) -> f64 {
+	shaders::fresnel_conductor(cos_theta, n, k)
+}
+
+#[test]
+fn fresnel_conductor_exact() {
+	// normal incidence
+	let (n, k) = (0.2, 3.5);
+	let expected = ((n - 1.0) * (n - 1.0) + k * k) / ((n + 1.0) * (n + 1.0) + k * k);
+	assert!((conductor_reflectance(n, k, 1.0) - expected).abs() < 1e-12);
+	// grazing incidence
+	assert!((conductor_reflectance(n, k, 0.0) - 1.0).abs() < 1e-12);
+	// with k = 0 reduces to dielectric Fresnel equations, here at 60 degrees
+	let cos_i: f64 = 0.5;
+	let cos_t = (1.0 - (1.0 - cos_i * cos_i) / (1.5 * 1.5)).sqrt();
+	let r_s = ((cos_i - 1.5 * cos_t) / (cos_i + 1.5 * cos_t)).powi(2);
+	let r_p = ((1.5 * cos_i - cos_t) / (1.5 * cos_i + cos_t)).powi(2);
+	assert!((conductor_reflectance(1.5, 0.0, cos_i) - 0.5 * (r_s + r_p)).abs() < 1e-12);
+}
+
+#[test]
+fn metal_tables() {
+	let reflectance = |metal: materials::Metal, lambda: f64| {
+		let (n, k) = metal.ior();
+		conductor_reflectance(n.evaluate(lambda), k.evaluate(lambda), 1.0)
+	};
+	for metal in materials::Metal::ALL {
+		for nm in (380..=780).step_by(20) {
+			let r = reflectance(metal, nm as f64 * 1e-9);
+			assert!(r > 0.3 && r < 1.0, "{:?} at {} nm: {}", metal, nm, r);
+		}
+	}
+	// gold and copper absorb blue, silver and aluminium reflect evenly
+	assert!(reflectance(materials::Metal::Gold, 650.0e-9) > 0.9);
+	assert!(reflectance(materials::Metal::Gold, 450.0e-9) < 0.5);
+	assert!(reflectance(materials::Metal::Copper, 450.0e-9) < 0.6);
+	assert!(reflectance(materials::Metal::Copper, 650.0e-9) > 0.9);
+	assert!(reflectance(materials::Metal::Silver, 450.0e-9) > 0.9);
+	assert!(reflectance(materials::Metal::Aluminium, 550.0e-9) > 0.9);
+	// measured data reaches past 400 - 700 nm instead of being held constant there
+	let gold = |nm: f64| reflectance(materials::Metal::Gold, nm * 1e-9);
+	assert!(gold(800.0) > gold(700.0) && gold(700.0) > gold(600.0));
+	let silver = |nm: f64| reflectance(materials::Metal::Silver, nm * 1e-9);
+	assert!(silver(360.0) < silver(380.0) && silver(380.0) < silver(400.0));
+	// aluminium dips at its interband transition around 1.5 eV
+	let aluminium = |nm: f64| reflectance(materials::Metal::Aluminium, nm * 1e-9);
+	assert!(aluminium(820.0) < aluminium(700.0) && aluminium(700.0) < aluminium(400.0));
+}
+
+fn transparent(roughness: f64) -> materials::Material {
+	materials::Material::new(
+		materials::EmissionType::NonEmissive,
+		materials::SurfaceType::DielectricTransparent { roughness, film: None },
+		1.5,
+		0.0,
+	)
+}
+
+// shading frame of an untextured surface facing +z
+fn frame() -> algebra::Basis {
+	algebra::Basis::new(
+		algebra::Vector::new(1.0, 0.0, 0.0),
+		algebra::Vector::new(0.0, 1.0, 0.0),
+		algebra::Vector::new(0.0, 0.0, 1.0),
+	)
+}
+
+fn spherical(theta: f64, phi: f64) -> algebra::Vector {
+	algebra::Vector::new(phi.cos() * theta.sin(), phi.sin() * theta.sin(), theta.cos())
+}
+
+// spherical angles of incoming direction sampled for outgoing one at `theta_o` in xz plane
+fn sample_angles(
+	material: &materials::Material,
+	theta_o: f64,
+	phi_o: f64,
+	random: (f64, f64, f64),
+	lambda: f64,
+	exterior: f64,
+) -> Option<(f64, f64)> {
+	let frame = frame();
+	let wavelengths = algebra::WavelengthBunch(lambda, lambda, lambda, lambda);
+	let sample =
+		material.sample(spherical(theta_o, phi_o), &frame, random, wavelengths, &|_| exterior)?;
+	let incoming = sample.direction;
+	Some((incoming.z.clamp(-1.0, 1.0).acos(), incoming.y.atan2(incoming.x)))
+}
+
+// estimates directional albedo by sampling the material; transmitted light is
+// scaled back by relative index of refraction to count energy instead of radiance
+fn sampled_albedo(material: &materials::Material, theta_o: f64, samples: usize) -> (f64, f64) {
+	let frame = frame();
+	let outgoing = spherical(theta_o, 0.0);
+	let eta = if outgoing.z > 0.0 { 1.5 } else { 1.0 / 1.5 };
+	let mut sampler = sampler::Sampler {
+		rng: rand::thread_rng(),
+	};
+	let (mut reflected, mut transmitted) = (0.0, 0.0);
+	let wavelengths = algebra::WavelengthBunch(550.0e-9, 550.0e-9, 550.0e-9, 550.0e-9);
+	for _ in 0..samples {
+		let r = sampler.random_list_2d(1, 0.0, 1.0)[0];
+		let u = sampler.random_list_1d(1, 0.0, 1.0)[0];
+		let sample = match material.sample(outgoing, &frame, (r.0, r.1, u), wavelengths, &|_| 1.0) {
+			Some(sample) => sample,
+			None => continue,
+		};
+		let incoming = sample.direction;
+		// evaluation agrees with the sample record
+		let half_vec = (incoming + outgoing).normalize();
+		let f =
+			material.return_scatter_radiance(incoming, outgoing, half_vec, &frame, 550.0e-9, 1.0);
+		let pdf = material.return_pdf(incoming, outgoing, half_vec, &frame, 550.0e-9, 1.0);
+		assert_eq!(sample.delta, material.has_delta_lobe());
+		assert!((sample.pdf - pdf).abs() <= 1e-9 * pdf);
+		assert!((sample.weight.0 - f * incoming.z.abs() / pdf).abs() <= 1e-9 * sample.weight.0);
+		if incoming.z * outgoing.z > 0.0 {
+			reflected += sample.weight.0;
+		} else {
+			transmitted += sample.weight.0 * eta * eta;
+		}
+	}
+	(reflected / samples as f64, transmitted / samples as f64)
+}
+
+#[test]
+fn fresnel_dielectric_exact() {
+	assert!((shaders::fresnel_dielectric(1.0, 1.5) - 0.04).abs() < 1e-12);
+	assert!((shaders::fresnel_dielectric(-1.0, 1.5) - 0.04).abs() < 1e-12);
+	// total internal reflection beyond critical angle from inside
+	let critical = (1.0_f64 / 1.5).asin();
+	assert_eq!(shaders::fresnel_dielectric(-(critical + 0.01).cos(), 1.5), 1.0);
+	assert!(shaders::fresnel_dielectric(-(critical - 0.01).cos(), 1.5) < 1.0);
+	// same reflectance on both sides of refraction
+	let cos_i: f64 = 0.6;
+	let cos_t = (1.0 - (1.0 - cos_i * cos_i) / 2.25).sqrt();
+	let outside = shaders::fresnel_dielectric(cos_i, 1.5);
+	let inside = shaders::fresnel_dielectric(-cos_t, 1.5);
+	assert!((outside - inside).abs() < 1e-12);
+	// agrees with conductor formula for k = 0
+	assert!((outside - conductor_reflectance(1.5, 0.0, cos_i)).abs() < 1e-12);
+}
+
+#[test]
+fn dielectric_smooth_energy() {
+	let glass = transparent(0.0);
+	assert!(glass.has_delta_lobe());
+	for theta_o in [0.1, 0.8, 1.4, std::f64::consts::PI - 0.3, std::f64::consts::PI - 1.2] {
+		let (reflected, transmitted) = sampled_albedo(&glass, theta_o, 20000);
+		// every sample carries exactly its energy, so only the split is random
+		assert!((reflected + transmitted - 1.0).abs() < 0.02, "{} {}", reflected, transmitted);
+		let f = shaders::fresnel_dielectric(theta_o.cos(), 1.5);
+		assert!((reflected - f).abs() < 0.02, "{} {}", reflected, f);
+	}
+}
+
+#[test]
+fn dielectric_rough_energy() {
+	for roughness in [0.3, 0.7] {
+		let glass = transparent(roughness);
+		assert!(!glass.has_delta_lobe());
+		for theta_o in [0.2, 1.0, std::f64::consts::PI - 0.4] {
+			let (reflected, transmitted) = sampled_albedo(&glass, theta_o, 100000);
+			let albedo = reflected + transmitted;
+			// single scattering microfacet model loses some energy, never gains it
+			assert!(albedo < 1.02 && albedo > 0.6, "{} {}: {}", roughness, theta_o, albedo);
+		}
+	}
+}
+
+#[test]
+fn dielectric_pdf_matches_sampling() {
+	// albedo estimated with uniformly sampled directions equals the one from importance sampling
+	let glass = transparent(0.7);
+	let frame = frame();
+	let theta_o: f64 = 0.7;
+	let outgoing = spherical(theta_o, 0.0);
+	let mut sampler = sampler::Sampler {
+		rng: rand::thread_rng(),
+	};
+	let samples = 400000;
+	let mut uniform = 0.0;
+	for _ in 0..samples {
+		let r = sampler.random_list_2d(1, 0.0, 1.0)[0];
+		let incoming = spherical((1.0 - 2.0 * r.0).acos(), 2.0 * std::f64::consts::PI * r.1);
+		let half_vec = (incoming + outgoing).normalize();
+		let f = glass.return_scatter_radiance(incoming, outgoing, half_vec, &frame, 550.0e-9, 1.0);
+		let scale = if incoming.z < 0.0 { 2.25 } else { 1.0 };
+		uniform += f * incoming.z.abs() * scale * 4.0 * std::f64::consts::PI;
+	}
+	uniform /= samples as f64;
+	let (reflected, transmitted) = sampled_albedo(&glass, theta_o, 100000);
+	let sampled = reflected + transmitted;
+	assert!((uniform - sampled).abs() < 0.03, "{} {}", uniform, sampled);
+}
+
+#[test]
+fn dielectric_reciprocity() {
+	let bxdf = shaders::Dielectric::new(0.5);
+	let context = shaders::ShadingContext {
+		lambda: 550.0e-9,
+		eta: 1.5,
+		k: 0.0,
+	};
+	let a = algebra::Vector::new(0.3, 0.1, 0.8).normalize();
+	let b = algebra::Vector::new(-0.5, 0.2, 0.6).normalize();
+	let c = algebra::Vector::new(-0.2, 0.3, -0.7).normalize();
+	// reflection is symmetric
+	let ab = bxdf.eval(a, b, &context);
+	let ba = bxdf.eval(b, a, &context);
+	assert!(ab > 0.0 && (ab - ba).abs() < 1e-12 * ab);
+	// transmission obeys f(i, o) / n_o^2 = f(o, i) / n_i^2
+	let ac = bxdf.eval(a, c, &context);
+	let ca = bxdf.eval(c, a, &context);
+	assert!(ac > 0.0 && (ac / 2.25 - ca).abs() < 1e-12 * ca);
+}
+
+#[test]
+fn dielectric_total_internal_reflection() {
+	let glass = transparent(0.0);
+	let critical = (1.0_f64 / 1.5).asin();
+	// beyond critical angle inside glass every sample reflects back inside
+	let theta_o = std::f64::consts::PI - critical - 0.05;
+	for u in [0.0, 0.5, 0.999] {
+		let (theta_i, _) =
+			sample_angles(&glass, theta_o, 0.0, (0.5, 0.5, u), 550.0e-9, 1.0).unwrap();
+		assert!((theta_i - theta_o).abs() < 1e-12);
+	}
+	// just below critical angle part of the light escapes
+	let theta_o = std::f64::consts::PI - critical + 0.05;
+	let (theta_i, _) =
+		sample_angles(&glass, theta_o, 0.0, (0.5, 0.5, 0.999), 550.0e-9, 1.0).unwrap();
+	assert!(theta_i < 0.5 * std::f64::consts::PI);
+}
+
+#[test]
+fn dielectric_opaque_coating_two_sided() {
+	let material = materials::Material::new(
+		materials::EmissionType::NonEmissive,
+		materials::SurfaceType::DielectricOpaque {
+			color: spectrum::Spectrum::Constant(0.5),
+			roughness: 0.4,
+			anisotropy: 0.0,
+			rotation: 0.0,
+		},
+		1.5,
+		0.0,
+	);
+	let normal = algebra::Vector::new(0.0, 0.0, 1.0);
+	let incoming = algebra::Vector::new(0.6, 0.0, 0.8);
+	let outgoing = algebra::Vector::new(-0.3, 0.4, 0.866).normalize();
+	let flip = |v: algebra::Vector| algebra::Vector::new(v.x, v.y, -v.z);
+	let front = material.return_scatter_radiance(
+		incoming,
+		outgoing,
+		(incoming + outgoing).normalize(),
+		&material.new_basis(normal),
+		550.0e-9,
+		1.0,
+	);
+	let back = material.return_scatter_radiance(
+		flip(incoming),
+		flip(outgoing),
+		flip((incoming + outgoing).normalize()),
+		&material.new_basis(-normal),
+		550.0e-9,
+		1.0,
+	);
+	assert!(front > 0.0 && (front - back).abs() < 1e-12);
+}
+
+#[test]
+fn dielectric_relative_to_exterior() {
+	let glass = transparent(0.0);
+	let theta_o = 0.6;
+	// glass inside liquid of equal index is invisible, light passes straight through
+	for u in [0.0, 0.5, 0.999] {
+		let (theta_i, phi_i) =
+			sample_angles(&glass, theta_o, 0.0, (0.5, 0.5, u), 550.0e-9, 1.5).unwrap();
+		assert!((theta_i - (std::f64::consts::PI - theta_o)).abs() < 1e-9);
+		assert!((phi_i.abs() - std::f64::consts::PI).abs() < 1e-9);
+	}
+	// refraction into glass from water follows relative index of refraction
+	let (theta_i, _) =
+		sample_angles(&glass, theta_o, 0.0, (0.5, 0.5, 0.999), 550.0e-9, 1.333).unwrap();
+	let sin_t = (std::f64::consts::PI - theta_i).sin();
+	assert!((1.333 * theta_o.sin() - 1.5 * sin_t).abs() < 1e-9);
+	// exterior medium lowers reflectance of the interface
+	let frame = frame();
+	let outgoing = spherical(theta_o, 0.0);
+	let incoming = spherical(theta_o, std::f64::consts::PI);
+	let half_vec = (incoming + outgoing).normalize();
+	let reflected = |exterior: f64| {
+		glass.return_scatter_radiance(incoming, outgoing, half_vec, &frame, 550.0e-9, exterior)
+	};
+	assert!(reflected(1.333) > 0.0 && reflected(1.333) < 0.2 * reflected(1.0));
+}
+
+#[test]
+fn subsurface_material() {
+	let wax = materials::Material::new(
+		materials::EmissionType::NonEmissive,
+		materials::SurfaceType::Subsurface {
+			color: spectrum::Spectrum::Constant(0.8),
+			mean_free_path: spectrum::Spectrum::Constant(0.05),
+			roughness: 0.3,
+		},
+		1.4,
+		0.0,
+	);
+	// rough boundary refracts into medium carrying the random walk
+	assert!(wax.is_transmissive());
+	assert!(!wax.has_delta_lobe());
+	let medium = wax.medium.as_ref().unwrap();
+	assert!((medium.sigma_t(550.0e-9) - 20.0).abs() < 1e-9);
+	assert!(medium.sigma_s(550.0e-9) > 0.9 * medium.sigma_t(550.0e-9));
+}
+
+// Pearson's chi-square test of directions sampled by the material against its pdf integrated
+// over bins of equal solid angle; bins expecting too few samples are pooled together
+// source: https://rgl.epfl.ch/publications/Jakob2014Microfacet
+fn assert_chi_square(material: &materials::Material, outgoing: algebra::Vector) {
+	const COS_BINS: usize = 16;
+	const PHI_BINS: usize = 32;
+	const SUBSTEPS: usize = 12;
+	let samples = 200000;
+	let frame = frame();
+	let lambda = 550.0e-9;
+	let wavelengths = algebra::WavelengthBunch(lambda, lambda, lambda, lambda);
+	let cos_step = 2.0 / COS_BINS as f64;
+	let phi_step = 2.0 * std::f64::consts::PI / PHI_BINS as f64;
+	let bin = |direction: algebra::Vector| {
+		let cos_bin = ((direction.z + 1.0) / cos_step) as usize;
+		let phi = direction.y.atan2(direction.x) + std::f64::consts::PI;
+		let phi_bin = (phi / phi_step) as usize;
+		cos_bin.min(COS_BINS - 1) * PHI_BINS + phi_bin.min(PHI_BINS - 1)
+	};
+	let mut observed = vec![0.0; COS_BINS * PHI_BINS];
+	let mut sampler = sampler::Sampler {
+		rng: rand::thread_rng(),
+	};
+	let random = sampler.random_list_2d(samples, 0.0, 1.0);
+	let choice = sampler.random_list_1d(samples, 0.0, 1.0);
+	for (r, u) in random.iter().zip(choice) {
+		let sample = material.sample(outgoing, &frame, (r.0, r.1, u), wavelengths, &|_| 1.0);
+		if let Some(sample) = sample {
+			assert!(!sample.delta);
+			observed[bin(sample.direction)] += 1.0;
+		}
+	}
+	let sub_area = cos_step * phi_step / (SUBSTEPS * SUBSTEPS) as f64;
+	let mut expected = vec![0.0; COS_BINS * PHI_BINS];
+	for (i, expected) in expected.iter_mut().enumerate() {
+		let (cos_bin, phi_bin) = (i / PHI_BINS, i % PHI_BINS);
+		for j in 0..SUBSTEPS * SUBSTEPS {
+			let offset = ((j / SUBSTEPS) as f64 + 0.5, (j % SUBSTEPS) as f64 + 0.5);
+			let cos_theta = -1.0 + (cos_bin as f64 + offset.0 / SUBSTEPS as f64) * cos_step;
+			let phi_offset = (phi_bin as f64 + offset.1 / SUBSTEPS as f64) * phi_step;
+			let phi = -std::f64::consts::PI + phi_offset;
+			let incoming = spherical(cos_theta.acos(), phi);
+			let half_vec = (incoming + outgoing).normalize();
+			let pdf = material.return_pdf(incoming, outgoing, half_vec, &frame, lambda, 1.0);
+			*expected += pdf * sub_area * samples as f64;
+		}
+	}
+	let (mut statistic, mut dof) = (0.0, 0);
+	let (mut pooled_observed, mut pooled_expected) = (0.0, 0.0);
+	for (o, e) in observed.iter().zip(&expected) {
+		if *e < 5.0 {
+			pooled_observed += o;
+			pooled_expected += e;
+		} else {
+			statistic += (o - e) * (o - e) / e;
+			dof += 1;
+		}
+	}
+	if pooled_expected > 0.0 {
+		let difference = pooled_observed - pooled_expected;
+		statistic += difference * difference / pooled_expected.max(5.0);
+		dof += 1;
+	}
+	dof -= 1;
+	// Wilson-Hilferty approximation of the quantile for significance level 1e-4
+	let k = dof as f64;
+	let z = 3.719;
+	let critical = k * (1.0 - 2.0 / (9.0 * k) + z * (2.0 / (9.0 * k)).sqrt()).powi(3);
+	assert!(statistic < critical, "chi-square {} over {} with {} dof", statistic, critical, dof);
+}
+
+#[test]
+fn sampling_chi_square() {
+	let opaque = materials::Material::new(
+		materials::EmissionType::NonEmissive,
+		materials::SurfaceType::DielectricOpaque {
+			color: spectrum::Spectrum::Constant(0.7),
+			roughness: 0.6,
+			anisotropy: 0.0,
+			rotation: 0.0,
+		},
+		1.5,
+		0.0,
+	);
+	let (n, k) = materials::Metal::Gold.ior();
+	let gold = materials::Material::new(
+		materials::EmissionType::NonEmissive,
+		materials::SurfaceType::Conductor {
+			roughness: 0.5,
+			anisotropy: 0.0,
+			rotation: 0.0,
+			film: None,
+		},
+		n,
+		k,
+	);
+	let lobes: Vec<std::sync::Arc<dyn shaders::Bxdf>> = vec![
+		std::sync::Arc::new(shaders::OrenNayar::new(0.3, spectrum::Spectrum::Constant(0.5))),
+		std::sync::Arc::new(shaders::Conductor::new(0.4)),
+	];
+	let mixed = materials::Material::custom(materials::EmissionType::NonEmissive, lobes, 0.2, 3.0);
+	let base = materials::CoatBase::Diffuse {
+		color: spectrum::Spectrum::Constant(0.6),
+		roughness: 0.5,
+	};
+	let lacquered = coated(base, 0.3, 0.0);
+	// cloth with sheen and retroreflective pile over diffuse base
+	let cloth = spectrum::Spectrum::Constant(0.4);
+	let lobes: Vec<std::sync::Arc<dyn shaders::Bxdf>> = vec![
+		std::sync::Arc::new(shaders::OrenNayar::new(0.5, cloth.clone())),
+		std::sync::Arc::new(shaders::Charlie::new(cloth.clone(), 0.4)),
+		std::sync::Arc::new(shaders::Velvet::new(cloth, 0.5)),
+	];
+	let fabric = materials::Material::custom(materials::EmissionType::NonEmissive, lobes, 1.5, 0.0);
+	for theta_o in [0.3, 1.2] {
+		assert_chi_square(&opaque, spherical(theta_o, 0.4));
+		assert_chi_square(&gold, spherical(theta_o, 0.4));
+		assert_chi_square(&mixed, spherical(theta_o, 0.4));
+		assert_chi_square(&lacquered, spherical(theta_o, 0.4));
+		assert_chi_square(&fabric, spherical(theta_o, 0.4));
+	}
+	let glass = transparent(0.6);
+	assert_chi_square(&glass, spherical(0.5, 0.4));
+	assert_chi_square(&glass, spherical(std::f64::consts::PI - 0.5, 0.4));
+	// anisotropic microfacets
+	let ggx = shaders::Ggx::new(0.15, 0.5);
+	let brushed = std::sync::Arc::new(shaders::Conductor::with_microfacets(ggx));
+	let brushed = materials::Material::custom(
+		materials::EmissionType::NonEmissive,
+		vec![brushed],
+		0.2,
+		3.0,
+	);
+	let frosted = std::sync::Arc::new(shaders::Dielectric::with_microfacets(ggx));
+	let frosted =
+		materials::Material::custom(materials::EmissionType::NonEmissive, vec![frosted], 1.5, 0.0);
+	for theta_o in [0.4, 1.3] {
+		assert_chi_square(&brushed, spherical(theta_o, 0.9));
+		assert_chi_square(&frosted, spherical(theta_o, 0.9));
+		assert_chi_square(&frosted, spherical(std::f64::consts::PI - theta_o, 0.9));
+	}
+}
+
+#[test]
+fn delta_sample_record() {
+	let (n, k) = materials::Metal::Silver.ior();
+	let lambda = 550.0e-9;
+	let wavelengths = algebra::WavelengthBunch(lambda, 450.0e-9, 650.0e-9, lambda);
+	let frame = frame();
+	let outgoing = spherical(0.7, 0.3);
+	let mirror = std::sync::Arc::new(shaders::Conductor::new(0.0));
+	let white = spectrum::Spectrum::Constant(1.0);
+	let diffuse = std::sync::Arc::new(shaders::OrenNayar::new(0.0, white));
+	let silver = materials::Material::custom(
+		materials::EmissionType::NonEmissive,
+		vec![mirror.clone()],
+		n.clone(),
+		k.clone(),
+	);
+	let sample = silver.sample(outgoing, &frame, (0.3, 0.6, 0.2), wavelengths, &|_| 1.0).unwrap();
+	let reflectance = |lambda| {
+		shaders::fresnel_conductor(0.7_f64.cos(), n.evaluate(lambda), k.evaluate(lambda))
+	};
+	assert!(sample.delta);
+	assert!((sample.direction - spherical(0.7, 0.3 + std::f64::consts::PI)).norm() < 1e-9);
+	assert!((sample.pdf - 1.0).abs() < 1e-12);
+	assert!((sample.weight.0 - reflectance(lambda)).abs() < 1e-9);
+	assert!((sample.weight.1 - reflectance(450.0e-9)).abs() < 1e-9);
+	// specular lobe chosen among two carries twice its energy, the other lobe none of it
+	let mixed = materials::Material::custom(
+		materials::EmissionType::NonEmissive,
+		vec![mirror, diffuse],
+		n.clone(),
+		k.clone(),
+	);
+	assert!(!mixed.has_delta_lobe());
+	let sample = mixed.sample(outgoing, &frame, (0.3, 0.6, 0.2), wavelengths, &|_| 1.0).unwrap();
+	assert!(sample.delta);
+	assert!((sample.pdf - 0.5).abs() < 1e-12);
+	assert!((sample.weight.0 - 2.0 * reflectance(lambda)).abs() < 1e-9);
+	let sample = mixed.sample(outgoing, &frame, (0.3, 0.6, 0.7), wavelengths, &|_| 1.0).unwrap();
+	assert!(!sample.delta);
+	assert!((sample.pdf - 0.5 * sample.direction.z / std::f64::consts::PI).abs() < 1e-12);
+	assert!((sample.weight.0 - 2.0).abs() < 1e-9);
+}
+
+#[test]
+fn weighted_lobe_selection() {
+	let (n, k) = materials::Metal::Silver.ior();
+	let lambda = 550.0e-9;
+	let wavelengths = algebra::WavelengthBunch(lambda, lambda, lambda, lambda);
+	let frame = frame();
+	let outgoing = spherical(0.7, 0.3);
+	let mirror = std::sync::Arc::new(shaders::Conductor::new(0.0));
+	let white = spectrum::Spectrum::Constant(1.0);
+	let diffuse = std::sync::Arc::new(shaders::OrenNayar::new(0.0, white));
+	let lobes: Vec<std::sync::Arc<dyn shaders::Bxdf>> = vec![
+		std::sync::Arc::new(shaders::Weighted::new(mirror, 0.25)),
+		std::sync::Arc::new(shaders::Weighted::new(diffuse, 0.75)),
+	];
+	let mixed = materials::Material::custom(
+		materials::EmissionType::NonEmissive,
+		lobes,
+		n.clone(),
+		k.clone(),
+	);
+	let reflectance =
+		shaders::fresnel_conductor(0.7_f64.cos(), n.evaluate(lambda), k.evaluate(lambda));
+	// lobes are chosen as often as they are weighted, which cancels the weight of each sample
+	let sample = mixed.sample(outgoing, &frame, (0.3, 0.6, 0.2), wavelengths, &|_| 1.0).unwrap();
+	assert!(sample.delta);
+	assert!((sample.pdf - 0.25).abs() < 1e-12);
+	assert!((sample.weight.0 - reflectance).abs() < 1e-9);
+	let sample = mixed.sample(outgoing, &frame, (0.3, 0.6, 0.3), wavelengths, &|_| 1.0).unwrap();
+	assert!(!sample.delta);
+	let pdf = 0.75 * sample.direction.z / std::f64::consts::PI;
+	assert!((sample.pdf - pdf).abs() < 1e-12);
+	assert!((sample.weight.0 - 1.0).abs() < 1e-9);
+	let incoming = sample.direction;
+	let half_vec = (incoming + outgoing).normalize();
+	let returned = mixed.return_pdf(incoming, outgoing, half_vec, &frame, lambda, 1.0);
+	assert!((returned - pdf).abs() < 1e-12);
+}
+
+fn brushed_metal(anisotropy: f64, rotation: f64) -> materials::Material {
+	let (n, k) = materials::Metal::Aluminium.ior();
+	materials::Material::new(
+		materials::EmissionType::NonEmissive,
+		materials::SurfaceType::Conductor {
+			roughness: 0.4,
+			anisotropy,
+			rotation,
+			film: None,
+		},
+		n,
+		k,
+	)
+}
+
+#[test]
+fn tangent_frame() {
+	let normal = algebra::Vector::new(0.0, 0.0, 1.0);
+	let tangent = algebra::Vector::new(1.0, 1.0, 1.0);
+	let frame = brushed_metal(0.8, 0.0).tangent_basis(normal, Some(tangent));
+	// tangent is projected onto the surface
+	let expected = algebra::Vector::new(1.0, 1.0, 0.0).normalize();
+	assert!((frame.u - expected).norm() < 1e-12);
+	assert!((frame.w - normal).norm() < 1e-12);
+	assert!((frame.u % frame.v - frame.w).norm() < 1e-12);
+	// rotation turns the tangent around the normal
+	let rotated = brushed_metal(0.8, std::f64::consts::FRAC_PI_4);
+	let rotated = rotated.tangent_basis(normal, Some(tangent));
+	assert!((rotated.u - algebra::Vector::new(0.0, 1.0, 0.0)).norm() < 1e-12);
+	// tangent along the normal falls back to an arbitrary frame
+	let fallback = brushed_metal(0.8, 0.0).tangent_basis(normal, Some(normal));
+	assert!((fallback.u * normal).abs() < 1e-12 && (fallback.u.norm() - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn triangle_tangent_from_uv() {
+	let triangle = nilepsilon::primitives::Primitive::new_triangle_uv(
+		algebra::Vector::new(0.0, 0.0, 0.0),
+		algebra::Vector::new(0.0, 2.0, 0.0),
+		algebra::Vector::new(-2.0, 0.0, 0.0),
+		(0.0, 0.0),
+		(1.0, 0.0),
+		(0.0, 1.0),
+		brushed_metal(0.8, 0.0),
+	);
+	let frame = triangle.shading_basis(algebra::Vector::new(0.0, 0.0, 1.0));
+	assert!((frame.u - algebra::Vector::new(0.0, 1.0, 0.0)).norm() < 1e-12);
+	let degenerate = nilepsilon::primitives::Primitive::new_triangle_uv(
+		algebra::Vector::new(0.0, 0.0, 0.0),
+		algebra::Vector::new(0.0, 2.0, 0.0),
+		algebra::Vector::new(-2.0, 0.0, 0.0),
+		(0.0, 0.0),
+		(0.0, 0.0),
+		(0.0, 0.0),
+		brushed_metal(0.8, 0.0),
+	);
+	assert!(degenerate.tangent.is_none());
+}
+
+#[test]
+fn anisotropic_highlight_follows_tangent() {
+	let metal = brushed_metal(0.9, 0.0);
+	let normal = algebra::Vector::new(0.0, 0.0, 1.0);
+	let outgoing = normal;
+	let along = algebra::Vector::new(0.5, 0.0, 0.866);
+	let across = algebra::Vector::new(0.0, 0.5, 0.866);
+	let eval = |frame: &algebra::Basis, incoming: algebra::Vector| {
+		let half_vec = (incoming + outgoing).normalize();
+		metal.return_scatter_radiance(incoming, outgoing, half_vec, frame, 550.0e-9, 1.0)
+	};
+	// positive anisotropy stretches the highlight along the tangent
+	let x = metal.tangent_basis(normal, Some(algebra::Vector::new(1.0, 0.0, 0.0)));
+	assert!(eval(&x, along) > 2.0 * eval(&x, across));
+	let y = metal.tangent_basis(normal, Some(algebra::Vector::new(0.0, 1.0, 0.0)));
+	assert!(eval(&y, across) > 2.0 * eval(&y, along));
+	assert!((eval(&x, along) - eval(&y, across)).abs() < 1e-9);
+}
+
+#[test]
+#[should_panic]
+fn anisotropy_out_of_range() {
+	shaders::Ggx::anisotropic(0.5, 1.5);
+}
+
+fn coated(base: materials::CoatBase, roughness: f64, absorption: f64) -> materials::Material {
+	materials::Material::new(
+		materials::EmissionType::NonEmissive,
+		materials::SurfaceType::Coated {
+			base,
+			roughness,
+			thickness: 100.0e-6,
+			absorption: spectrum::Spectrum::Constant(absorption),
+		},
+		1.5,
+		0.0,
+	)
+}
+
+// mean of sample weights for the hero wavelength, including specular coat reflection;
+// transmitted light is scaled back to energy as in `sampled_albedo`
+fn coated_albedo(material: &materials::Material, theta_o: f64) -> f64 {
+	let mut sampler = sampler::Sampler {
+		rng: rand::thread_rng(),
+	};
+	let frame = frame();
+	let outgoing = spherical(theta_o, 0.0);
+	let eta: f64 = if outgoing.z > 0.0 { 1.5 } else { 1.0 / 1.5 };
+	let wavelengths = algebra::WavelengthBunch(550.0e-9, 450.0e-9, 650.0e-9, 550.0e-9);
+	let samples = 20000;
+	let random = sampler.random_list_2d(samples, 0.0, 1.0);
+	let choice = sampler.random_list_1d(samples, 0.0, 1.0);
+	random
+		.iter()
+		.zip(choice)
+		.filter_map(|(r, u)| {
+			material.sample(outgoing, &frame, (r.0, r.1, u), wavelengths, &|_| 1.0)
+		})
+		.map(|sample| {
+			if sample.direction.z * outgoing.z > 0.0 {
+				sample.weight.0
+			} else {
+				sample.weight.0 * eta * eta
+			}
+		})
+		.sum::<f64>()
+		/ samples as f64
+}
+
+#[test]
+fn coated_conserves_energy() {
+	let white = materials::CoatBase::Diffuse {
+		color: spectrum::Spectrum::Constant(1.0),
+		roughness: 0.0,
+	};
+	for roughness in [0.0, 0.4] {
+		let lacquered = coated(white.clone(), roughness, 0.0);
+		assert!(!lacquered.has_delta_lobe());
+		for theta_o in [0.2, 0.9, 1.4] {
+			// whatever the coat does not reflect reaches the white base
+			let albedo = coated_albedo(&lacquered, theta_o);
+			assert!((albedo - 1.0).abs() < 0.03, "{} {} {}", roughness, theta_o, albedo);
+		}
+	}
+	// absorbing coat darkens the base more at grazing angles, where the path is longer
+	let tinted = coated(white, 0.0, 5000.0);
+	let base = |theta_o: f64| {
+		let fresnel = shaders::fresnel_dielectric(theta_o.cos(), 1.5);
+		(coated_albedo(&tinted, theta_o) - fresnel) / (1.0 - fresnel)
+	};
+	let (normal, grazing) = (base(0.0), base(1.4));
+	assert!(normal < 0.6 && normal > 0.2, "{}", normal);
+	assert!(grazing < 0.9 * normal, "{} {}", normal, grazing);
+}
+
+#[test]
+fn coated_conductor() {
+	let (n, k) = materials::Metal::Gold.ior();
+	let bare = materials::Material::new(
+		materials::EmissionType::NonEmissive,
+		materials::SurfaceType::Conductor {
+			roughness: 0.3,
+			anisotropy: 0.0,
+			rotation: 0.0,
+			film: None,
+		},
+		n.clone(),
+		k.clone(),
+	);
+	let base = materials::CoatBase::Conductor { n, k, roughness: 0.3 };
+	let lacquered = coated(base, 0.0, 0.0);
+	let frame = frame();
+	let outgoing = spherical(0.3, 0.0);
+	let incoming = spherical(0.3, std::f64::consts::PI);
+	let half_vec = algebra::Vector::new(0.0, 0.0, 1.0);
+	// metal seen through the coat keeps its color, dimmed by reflection of the coat
+	for lambda in [450.0e-9, 550.0e-9, 650.0e-9] {
+		let eval = |material: &materials::Material| {
+			material.return_scatter_radiance(incoming, outgoing, half_vec, &frame, lambda, 1.0)
+		};
+		let (f, g) = (eval(&bare), eval(&lacquered));
+		assert!(g > 0.0 && g < f, "{} {} {}", lambda, f, g);
+	}
+}
+
+fn principled(
+	color: f64,
+	metallic: f64,
+	roughness: f64,
+	clearcoat: f64,
+	transmission: f64,
+) -> materials::Material {
+	principled_specular(color, metallic, roughness, clearcoat, transmission, 0.5)
+}
+
+fn principled_specular(
+	color: f64,
+	metallic: f64,
+	roughness: f64,
+	clearcoat: f64,
+	transmission: f64,
+	specular: f64,
+) -> materials::Material {
+	materials::Material::new(
+		materials::EmissionType::NonEmissive,
+		materials::SurfaceType::Principled {
+			base_color: spectrum::Spectrum::Constant(color),
+			metallic,
+			roughness,
+			specular,
+			specular_tint: 0.0,
+			sheen: 0.0,
+			clearcoat,
+			clearcoat_roughness: 0.3,
+			transmission,
+			subsurface: 0.0,
+			subsurface_radius: spectrum::Spectrum::Constant(1.0e-3),
+		},
+		1.5,
+		0.0,
+	)
+}
+
+#[test]
+fn principled_conserves_energy() {
+	let weights = [(0.0, 0.0, 0.5), (1.0, 0.0, 0.5), (0.5, 0.5, 0.5), (0.0, 1.0, 0.5)];
+	// plastic reflectance scaled by `specular` is taken from its diffuse base
+	let specular = [(0.0, 0.0, 1.0), (1.0, 0.0, 1.0), (0.0, 0.0, 0.0)];
+	for (clearcoat, transmission, specular) in weights.into_iter().chain(specular) {
+		let smooth = principled_specular(1.0, 0.0, 0.0, clearcoat, transmission, specular);
+		let rough = principled_specular(1.0, 0.0, 0.4, clearcoat, transmission, specular);
+		for theta_o in [0.2, 0.9, 1.4] {
+			// white plastic and clear glass under clear coat neither gain nor lose light
+			let albedo = coated_albedo(&smooth, theta_o);
+			assert!(
+				(albedo - 1.0).abs() < 0.03,
+				"{} {} {} {}",
+				clearcoat,
+				specular,
+				theta_o,
+				albedo
+			);
+			let albedo = coated_albedo(&rough, theta_o);
+			assert!(
+				albedo < 1.03 && albedo > 0.8,
+				"{} {} {} {}",
+				clearcoat,
+				specular,
+				theta_o,
+				albedo
+			);
+		}
+	}
+}
+
+#[test]
+fn principled_metallic() {
+	let metal = principled(0.8, 1.0, 0.0, 0.0, 0.0);
+	assert!(metal.has_delta_lobe());
+	assert!(!metal.is_transmissive());
+	// base color is reflectance at normal incidence, rising towards white at grazing angles
+	let normal = coated_albedo(&metal, 0.0);
+	assert!((normal - 0.8).abs() < 1e-6, "{}", normal);
+	assert!(coated_albedo(&metal, 1.4) > 0.85);
+	// half metallic mixes the metal with plastic
+	let plastic = coated_albedo(&principled(0.8, 0.0, 0.0, 0.0, 0.0), 1.4);
+	let half = coated_albedo(&principled(0.8, 0.5, 0.0, 0.0, 0.0), 1.4);
+	let grazing = coated_albedo(&metal, 1.4);
+	assert!((half - 0.5 * (plastic + grazing)).abs() < 0.02, "{} {}", plastic, half);
+}
+
+#[test]
+fn principled_transmission_from_inside() {
+	// light inside of partly transparent material only meets the glass lobe, so none is lost
+	let material = principled(0.5, 0.0, 0.0, 0.0, 0.5);
+	assert!(material.is_transmissive());
+	for theta_o in [0.2, 0.9] {
+		let albedo = coated_albedo(&material, std::f64::consts::PI - theta_o);
+		assert!((albedo - 1.0).abs() < 0.03, "{} {}", theta_o, albedo);
+	}
+}
+
+#[test]
+fn principled_chi_square() {
+	let plastic = principled(0.6, 0.3, 0.4, 0.5, 0.0);
+	let glass = principled(0.6, 0.0, 0.4, 0.0, 0.7);
+	for theta_o in [0.3, 1.2] {
+		assert_chi_square(&plastic, spherical(theta_o, 0.4));
+		assert_chi_square(&glass, spherical(theta_o, 0.4));
+	}
+	assert_chi_square(&glass, spherical(std::f64::consts::PI - 0.5, 0.4));
+}
+
+#[test]
+#[should_panic]
+fn principled_weight_out_of_range() {
+	principled(0.5, 1.5, 0.3, 0.0, 0.0);
+}
+
+// dye absorbing violet and blue light, glowing green like a highlighter ink
+fn highlighter() -> materials::Fluorescence {
+	let absorption = spectrum::Spectrum::sampled(vec![
+		(380.0e-9, 0.0),
+		(430.0e-9, 0.9),
+		(480.0e-9, 0.9),
+		(510.0e-9, 0.0),
+	]);
+	let emission =
+		spectrum::Spectrum::sampled(vec![(490.0e-9, 0.0), (520.0e-9, 1.0), (580.0e-9, 0.0)]);
+	materials::Fluorescence::dye(absorption, emission, 0.8)
+}
+
+#[test]
+fn fluorescent_dye() {
+	let dye = highlighter();
+	let step = 5.0e-9;
+	for k in 0..100 {
+		let excitation = 300.0e-9 + k as f64 * step;
+		let reradiated: f64 = (0..=100)
+			.map(|j| {
+				let emission = 300.0e-9 + j as f64 * step;
+				let factor = dye.reradiation(excitation, emission);
+				// light only ever moves to longer wavelengths
+				assert!(factor == 0.0 || emission > excitation);
+				factor * step
+			})
+			.sum();
+		// no more energy than the absorbed photons carried
+		assert!(reradiated <= 0.8 * 0.9 + 1e-9, "{} {}", excitation, reradiated);
+	}
+	assert!(dye.total(520.0e-9) > 0.1);
+	assert_eq!(dye.total(450.0e-9), 0.0);
+	assert_eq!(dye.total(650.0e-9), 0.0);
+}
+
+#[test]
+fn fluorescence_sampling() {
+	let dye = highlighter();
+	let emission = 530.0e-9;
+	let mut sampler = sampler::Sampler {
+		rng: rand::thread_rng(),
+	};
+	let samples = 100000;
+	let (min, step, bins) = (380.0e-9, 10.0e-9, 15);
+	let mut histogram = vec![0.0; bins];
+	for u in sampler.random_list_1d(samples, 0.0, 1.0) {
+		let excitation = dye.sample_excitation(emission, u).unwrap();
+		assert!(excitation < emission);
+		let bin = ((excitation - min) / step) as usize;
+		histogram[bin] += 1.0 / samples as f64;
+	}
+	for (bin, observed) in histogram.iter().enumerate() {
+		let expected: f64 = (0..20)
+			.map(|k| {
+				let excitation = min + (bin as f64 + (k as f64 + 0.5) / 20.0) * step;
+				dye.pdf_excitation(excitation, emission) * step / 20.0
+			})
+			.sum();
+		assert!((observed - expected).abs() < 0.01, "{} {} {}", bin, observed, expected);
+	}
+}
+
+#[test]
+fn donaldson_matrix() {
+	// diagonal is ordinary reflectance, above it light would gain energy
+	let matrix = vec![vec![0.5, 0.3, 0.0], vec![0.2, 0.5, 0.1], vec![0.1, 0.3, 0.5]];
+	let fluorescence = materials::Fluorescence::donaldson(400.0e-9, 600.0e-9, matrix);
+	assert_eq!(fluorescence.total(400.0e-9), 0.0);
+	assert!((fluorescence.total(500.0e-9) - 0.2).abs() < 1e-12);
+	assert!((fluorescence.total(590.0e-9) - 0.4).abs() < 1e-12);
+	assert!((fluorescence.reradiation(410.0e-9, 600.0e-9) - 0.1 / 100.0e-9).abs() < 1e-3);
+	assert_eq!(fluorescence.reradiation(600.0e-9, 400.0e-9), 0.0);
+	let mut material = transparent(0.0);
+	material.fluorescence = Some(fluorescence);
+	assert!((material.fluorescence_probability(600.0e-9) - 0.4 / 1.4).abs() < 1e-12);
+	assert_eq!(material.fluorescence_probability(700.0e-9), 0.0);
+	// reradiation leaves diffusely towards the viewer, whichever side it is on
+	let frame = frame();
+	for outgoing in [spherical(0.3, 0.0), spherical(2.5, 1.0)] {
+		let sample = material.sample_fluorescence(outgoing, &frame, (0.4, 0.7, 0.9), 600.0e-9);
+		let sample = sample.unwrap();
+		assert!(sample.direction.z * outgoing.z > 0.0);
+		assert!((sample.weight - 0.4).abs() < 1e-12);
+		assert!(sample.excitation >= 350.0e-9 && sample.excitation < 550.0e-9);
+	}
+}
+
+#[test]
+#[should_panic]
+fn donaldson_matrix_not_square() {
+	materials::Fluorescence::donaldson(400.0e-9, 600.0e-9, vec![vec![0.5, 0.3], vec![0.2]]);
+}
@@ -0,0 +1,186 @@
+use nilepsilon::algebra;
+use nilepsilon::media;
+use nilepsilon::sampler;
+use nilepsilon::spectrum;
+
+fn fog(g: f64) -> media::Medium {
+	media::Medium::new(
+		spectrum::Spectrum::Constant(0.5),
+		spectrum::Spectrum::Polynomial(vec![0.0, 2.0e6]),
+		g,
+	)
+}
+
+#[test]
+fn medium_coefficients() {
+	let medium = fog(0.0);
+	assert!((medium.sigma_s(500.0e-9) - 1.0).abs() < 1e-12);
+	assert!((medium.sigma_t(500.0e-9) - 1.5).abs() < 1e-12);
+	// Beer-Lambert attenuation
+	assert!((medium.transmittance(2.0, 500.0e-9) - (-3.0_f64).exp()).abs() < 1e-12);
+	let vacuum = media::Medium::absorbing(spectrum::Spectrum::Constant(0.0));
+	assert_eq!(vacuum.transmittance(algebra::Scalar::INFINITY, 500.0e-9), 1.0);
+	assert_eq!(vacuum.sample_distance(500.0e-9, 0.5), algebra::Scalar::INFINITY);
+}
+
+#[test]
+fn medium_distance_sampling() {
+	let medium = fog(0.0);
+	let mut sampler = sampler::Sampler {
+		rng: rand::thread_rng(),
+	};
+	let samples = 100000;
+	let mean = sampler
+		.random_list_1d(samples, 0.0, 1.0)
+		.iter()
+		.map(|u| medium.sample_distance(500.0e-9, *u))
+		.sum::<f64>()
+		/ samples as f64;
+	// mean free path is reciprocal of extinction
+	assert!((mean - 1.0 / 1.5).abs() < 0.01);
+}
+
+#[test]
+fn phase_normalized() {
+	for g in [-0.7, 0.0, 0.5, 0.9] {
+		let medium = fog(g);
+		let steps = 100000;
+		let step = 2.0 / steps as f64;
+		let integral: f64 = (0..steps)
+			.map(|i| {
+				let cos_theta = -1.0 + (i as f64 + 0.5) * step;
+				2.0 * std::f64::consts::PI * medium.phase(cos_theta) * step
+			})
+			.sum();
+		assert!((integral - 1.0).abs() < 1e-3, "g = {}, integral {}", g, integral);
+	}
+}
+
+#[test]
+fn phase_sampling_mean_cosine() {
+	let mut sampler = sampler::Sampler {
+		rng: rand::thread_rng(),
+	};
+	let direction = algebra::Vector::new(0.3, -0.5, 0.8).normalize();
+	for g in [-0.6, 0.0, 0.8] {
+		let medium = fog(g);
+		let samples = 100000;
+		let mut mean = 0.0;
+		for random in sampler.random_list_2d(samples, 0.0, 1.0) {
+			let scattered = medium.sample_phase(direction, random);
+			assert!((scattered.norm() - 1.0).abs() < 1e-9);
+			mean += scattered * direction;
+		}
+		// mean cosine of Henyey-Greenstein is its asymmetry parameter
+		mean /= samples as f64;
+		assert!((mean - g).abs() < 0.01, "g = {}, mean cosine {}", g, mean);
+	}
+}
+
+#[test]
+#[should_panic]
+fn medium_invalid_asymmetry() {
+	fog(1.0);
+}
+
+fn unit_box() -> (algebra::Vector, algebra::Vector) {
+	(algebra::Vector::new(0.0, 0.0, 0.0), algebra::Vector::new(1.0, 1.0, 1.0))
+}
+
+#[test]
+fn grid_trilinear_density() {
+	let (min, max) = unit_box();
+	// density rising linearly along x
+	let grid = media::DensityGrid::from_fn(min, max, [4, 2, 3], |p| p.x);
+	for x in [0.125, 0.3, 0.5, 0.77, 0.875] {
+		let density = grid.density(algebra::Vector::new(x, 0.4, 0.6));
+		assert!((density - x).abs() < 1e-12, "x = {}, density {}", x, density);
+	}
+	// clamped to boundary voxels near the faces and empty outside
+	assert!((grid.density(algebra::Vector::new(0.05, 0.5, 0.5)) - 0.125).abs() < 1e-12);
+	assert_eq!(grid.density(algebra::Vector::new(1.1, 0.5, 0.5)), 0.0);
+	assert_eq!(grid.density(algebra::Vector::new(0.5, -0.1, 0.5)), 0.0);
+}
+
+#[test]
+fn grid_from_bytes() {
+	let (min, max) = unit_box();
+	let mut bytes = b"nilepsilon-grid 2 1 1\n".to_vec();
+	bytes.extend_from_slice(&1.0_f32.to_le_bytes());
+	bytes.extend_from_slice(&3.0_f32.to_le_bytes());
+	let grid = media::DensityGrid::from_bytes(&bytes, min, max).unwrap();
+	assert_eq!(grid.density(algebra::Vector::new(0.25, 0.5, 0.5)), 1.0);
+	assert_eq!(grid.density(algebra::Vector::new(0.5, 0.5, 0.5)), 2.0);
+	assert!(media::DensityGrid::from_bytes(&bytes[..bytes.len() - 1], min, max).is_err());
+	assert!(media::DensityGrid::from_bytes(b"nilepsilon-grid 2 1\n", min, max).is_err());
+	let mut negative = b"nilepsilon-grid 1 1 1\n".to_vec();
+	negative.extend_from_slice(&(-1.0_f32).to_le_bytes());
+	assert!(media::DensityGrid::from_bytes(&negative, min, max).is_err());
+}
+
+#[test]
+fn grid_majorants_bound_density() {
+	let (min, max) = unit_box();
+	let grid = media::DensityGrid::from_fn(min, max, [20, 20, 20], |p| {
+		media::fbm(p * 6.0, 4) * (p.z < 0.5) as u8 as f64
+	});
+	let medium = media::Medium::heterogeneous(
+		spectrum::Spectrum::Constant(1.0),
+		spectrum::Spectrum::Constant(1.0),
+		0.0,
+		grid,
+	);
+	let origin = algebra::Vector::new(-0.5, 0.2, 1.3);
+	let direction = algebra::Vector::new(1.0, 0.3, -0.8).normalize();
+	let segments = medium.majorant_segments(origin, direction, 10.0);
+	assert!(!segments.is_empty());
+	// consecutive segments inside the box, each bounding density along it
+	for pair in segments.windows(2) {
+		assert!((pair[0].1 - pair[1].0).abs() < 1e-12);
+	}
+	for (start, end, majorant) in segments {
+		assert!(start < end);
+		for i in 0..=20 {
+			let t = start + (end - start) * i as f64 / 20.0;
+			assert!(medium.density(origin + t * direction) <= majorant + 1e-12);
+		}
+	}
+	// rays missing the box cross no segments
+	let miss = medium.majorant_segments(origin, -direction, 10.0);
+	assert!(miss.is_empty());
+	let homogeneous = fog(0.0);
+	assert!(homogeneous.is_homogeneous());
+	assert_eq!(homogeneous.majorant_segments(origin, direction, 2.0), vec![(0.0, 2.0, 1.0)]);
+}
+
+#[test]
+fn fbm_range() {
+	let mut sampler = sampler::Sampler {
+		rng: rand::thread_rng(),
+	};
+	for (x, y) in sampler.random_list_2d(1000, -10.0, 10.0) {
+		let value = media::fbm(algebra::Vector::new(x, y, x - y), 5);
+		assert!((0.0..=1.0).contains(&value));
+	}
+}
+
+#[test]
+fn subsurface_albedo_inversion() {
+	// black and white surfaces need no and full single scattering
+	assert!(media::single_scattering_albedo(0.0).abs() < 1e-4);
+	assert!((media::single_scattering_albedo(1.0) - 1.0).abs() < 1e-4);
+	let mut previous = 0.0;
+	for i in 1..=10 {
+		let albedo = media::single_scattering_albedo(i as f64 / 10.0);
+		assert!(albedo > previous);
+		previous = albedo;
+	}
+	let medium = media::Medium::subsurface(
+		spectrum::Spectrum::Constant(0.5),
+		spectrum::Spectrum::Constant(0.01),
+	);
+	assert!((medium.sigma_t(550.0e-9) - 100.0).abs() < 1e-9);
+	let single = media::single_scattering_albedo(0.5);
+	assert!((medium.sigma_s(550.0e-9) - 100.0 * single).abs() < 1e-9);
+	assert!((medium.sigma_a(550.0e-9) - 100.0 * (1.0 - single)).abs() < 1e-9);
+}
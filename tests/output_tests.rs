@@ -41,6 +41,14 @@ fn output_color_wheels() {
 	img.out(output::Format::PPM);
 }
 
+#[test]
+fn output_get_pixel() {
+	let mut img = output::ImageFile::new(4,2);
+	img.set_pixel(3, 1, (0.25, 0.5, 2.0));
+	assert_eq!(img.get_pixel(3, 1), (0.25, 0.5, 2.0));
+	assert_eq!(img.get_pixel(1, 1), (0.0, 0.0, 0.0));
+}
+
 #[test]
 fn output_corner() {
 	let mut img = output::ImageFile::new(640,480);
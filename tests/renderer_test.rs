@@ -1,10 +1,14 @@
 use nilepsilon::algebra;
 use nilepsilon::camera;
+use nilepsilon::environment;
 use nilepsilon::materials;
+use nilepsilon::media;
 use nilepsilon::output;
 use nilepsilon::primitives;
 use nilepsilon::renderer;
 use nilepsilon::scene;
+use nilepsilon::shaders;
+use nilepsilon::spectrum;
 
 #[test]
 fn renderer_three_spheres_raster() {
@@ -25,8 +29,10 @@ fn renderer_three_spheres_raster() {
     let diff1 = materials::Material::new(
         materials::EmissionType::NonEmissive,
         materials::SurfaceType::DielectricOpaque {
-            color: vec![0.6],
+            color: spectrum::Spectrum::Constant(0.6),
             roughness: 0.8,
+            anisotropy: 0.0,
+            rotation: 0.0,
         },
         1.5,
         0.0,
@@ -41,6 +47,7 @@ fn renderer_three_spheres_raster() {
         objects: vec![sph1, sph2, sph3],
         camera: cam,
         background: bg,
+        medium: None,
     };
     let img = output::ImageFile::new(640, 480);
     let mut dis = renderer::Renderer {
@@ -50,6 +57,8 @@ fn renderer_three_spheres_raster() {
         chunk_size_exp: 16,
         lights: vec![],
         max_depth: 5,
+        observer: renderer::Observer::Cie1931TwoDegree,
+        spectral: renderer::SpectralConfig::default(),
     };
     dis.render();
     dis.output.out(output::Format::PPM);
@@ -74,8 +83,10 @@ fn renderer_overlapping_spheres_raster() {
     let diff1 = materials::Material::new(
         materials::EmissionType::NonEmissive,
         materials::SurfaceType::DielectricOpaque {
-            color: vec![0.6],
+            color: spectrum::Spectrum::Constant(0.6),
             roughness: 0.8,
+            anisotropy: 0.0,
+            rotation: 0.0,
         },
         1.5,
         0.0,
@@ -90,6 +101,7 @@ fn renderer_overlapping_spheres_raster() {
         objects: vec![sph1, sph2, sph3],
         camera: cam,
         background: bg,
+        medium: None,
     };
     let img = output::ImageFile::new(640, 480);
     let mut dis = renderer::Renderer {
@@ -99,6 +111,8 @@ fn renderer_overlapping_spheres_raster() {
         chunk_size_exp: 16,
         lights: vec![],
         max_depth: 5,
+        observer: renderer::Observer::Cie1931TwoDegree,
+        spectral: renderer::SpectralConfig::default(),
     };
     dis.render();
     dis.output.out(output::Format::PPM);
@@ -123,8 +137,10 @@ fn renderer_sphere_plane_raster() {
     let diff1 = materials::Material::new(
         materials::EmissionType::NonEmissive,
         materials::SurfaceType::DielectricOpaque {
-            color: vec![0.6],
+            color: spectrum::Spectrum::Constant(0.6),
             roughness: 0.8,
+            anisotropy: 0.0,
+            rotation: 0.0,
         },
         1.5,
         0.0,
@@ -140,6 +156,7 @@ fn renderer_sphere_plane_raster() {
         objects: vec![sph1, pln1],
         camera: cam,
         background: bg,
+        medium: None,
     };
     let img = output::ImageFile::new(640, 480);
     let mut dis = renderer::Renderer {
@@ -149,6 +166,8 @@ fn renderer_sphere_plane_raster() {
         chunk_size_exp: 16,
         lights: vec![],
         max_depth: 5,
+        observer: renderer::Observer::Cie1931TwoDegree,
+        spectral: renderer::SpectralConfig::default(),
     };
     dis.render();
     dis.output.out(output::Format::PPM);
@@ -173,8 +192,10 @@ fn renderer_triangle_algorithm_speedtest() {
     let diff1 = materials::Material::new(
         materials::EmissionType::NonEmissive,
         materials::SurfaceType::DielectricOpaque {
-            color: vec![0.6],
+            color: spectrum::Spectrum::Constant(0.6),
             roughness: 0.8,
+            anisotropy: 0.0,
+            rotation: 0.0,
         },
         1.5,
         0.0,
@@ -190,6 +211,7 @@ fn renderer_triangle_algorithm_speedtest() {
         objects: vec![tri],
         camera: cam,
         background: bg,
+        medium: None,
     };
     let img = output::ImageFile::new(640, 480);
     let mut dis = renderer::Renderer {
@@ -199,6 +221,8 @@ fn renderer_triangle_algorithm_speedtest() {
         chunk_size_exp: 16,
         lights: vec![],
         max_depth: 5,
+        observer: renderer::Observer::Cie1931TwoDegree,
+        spectral: renderer::SpectralConfig::default(),
     };
     for _i in 0..200 {
         dis.render();
@@ -224,8 +248,10 @@ fn renderer_cornell_1() {
     let diffuse1 = materials::Material::new(
         materials::EmissionType::NonEmissive,
         materials::SurfaceType::DielectricOpaque {
-            color: vec![0.6],
+            color: spectrum::Spectrum::Constant(0.6),
             roughness: 0.8,
+            anisotropy: 0.0,
+            rotation: 0.0,
         },
         1.5,
         0.0,
@@ -265,6 +291,7 @@ fn renderer_cornell_1() {
         objects: vec![back, right, left, ceil, floor, ball],
         camera: cam,
         background: bg,
+        medium: None,
     };
     let img = output::ImageFile::new(512, 512);
     let mut dis = renderer::Renderer {
@@ -274,6 +301,8 @@ fn renderer_cornell_1() {
         chunk_size_exp: 4,
         lights: vec![],
         max_depth: 5,
+        observer: renderer::Observer::Cie1931TwoDegree,
+        spectral: renderer::SpectralConfig::default(),
     };
     dis.render();
     dis.output.out(output::Format::PPM);
@@ -299,8 +328,10 @@ fn renderer_empty() {
     let diff1 = materials::Material::new(
         materials::EmissionType::NonEmissive,
         materials::SurfaceType::DielectricOpaque {
-            color: vec![0.6],
+            color: spectrum::Spectrum::Constant(0.6),
             roughness: 0.8,
+            anisotropy: 0.0,
+            rotation: 0.0,
         },
         1.5,
         0.0,
@@ -308,6 +339,7 @@ fn renderer_empty() {
     let sc = scene::Scene {
         camera: cam,
         background: bg,
+        medium: None,
         objects: vec![],
     };
     let img = output::ImageFile::new(512, 512);
@@ -318,6 +350,8 @@ fn renderer_empty() {
         chunk_size_exp: 4,
         lights: vec![],
         max_depth: 5,
+        observer: renderer::Observer::Cie1931TwoDegree,
+        spectral: renderer::SpectralConfig::default(),
     };
     dis.render();
     dis.output.out(output::Format::PPM);
@@ -344,9 +378,11 @@ fn renderer_furnace() {
     let specular = materials::Material::new(
         materials::EmissionType::NonEmissive,
         materials::SurfaceType::DielectricOpaque {
-            //color: vec![-99.2, 4.0e8, -4e14],
-            color: vec![0.5],
+            //color: spectrum::Spectrum::Polynomial(vec![-99.2, 4.0e8, -4e14]),
+            color: spectrum::Spectrum::Constant(0.5),
             roughness: 0.001,
+            anisotropy: 0.0,
+            rotation: 0.0,
         },
         1.5,
         0.0,
@@ -361,6 +397,7 @@ fn renderer_furnace() {
     let sc = scene::Scene {
         camera: cam,
         background: bg,
+        medium: None,
         objects: vec![ball1],
     };
     let img = output::ImageFile::new(512, 512);
@@ -371,6 +408,8 @@ fn renderer_furnace() {
         chunk_size_exp: 4,
         lights: vec![],
         max_depth: 2,
+        observer: renderer::Observer::Cie1931TwoDegree,
+        spectral: renderer::SpectralConfig::default(),
     };
     dis.render();
     dis.output.out(output::Format::PPM);
@@ -396,8 +435,10 @@ fn renderer_cornell_2() {
     let diff1 = materials::Material::new(
         materials::EmissionType::NonEmissive,
         materials::SurfaceType::DielectricOpaque {
-            color: vec![0.6],
+            color: spectrum::Spectrum::Constant(0.6),
             roughness: 0.8,
+            anisotropy: 0.0,
+            rotation: 0.0,
         },
         1.5,
         0.0,
@@ -405,10 +446,12 @@ fn renderer_cornell_2() {
     let specular = materials::Material::new(
         materials::EmissionType::Fresnel,
         materials::SurfaceType::DielectricOpaque {
-            //color: vec![-99.2, 4.0e8, -4e14],
-            //color: vec![1.90511e-17, 2.97674e-9, 0.22952, 1.16378e7, 4.36303e14, 1.28923e22],
-            color: vec![0.0],
+            //color: spectrum::Spectrum::Polynomial(vec![-99.2, 4.0e8, -4e14]),
+            //color: spectrum::Spectrum::Polynomial(vec![1.90511e-17, 2.97674e-9, 0.22952, 1.16378e7, 4.36303e14, 1.28923e22]),
+            color: spectrum::Spectrum::Constant(0.0),
             roughness: 0.01,
+            anisotropy: 0.0,
+            rotation: 0.0,
         },
         1.5,
         0.0,
@@ -425,15 +468,22 @@ fn renderer_cornell_2() {
     //);
     let metal = materials::Material::new(
         materials::EmissionType::NonEmissive,
-        materials::SurfaceType::Conductor { roughness: 0.1 },
+        materials::SurfaceType::Conductor {
+            roughness: 0.1,
+            anisotropy: 0.0,
+            rotation: 0.0,
+            film: None,
+        },
         0.05,
         3.9,
     );
     let red1 = materials::Material::new(
         materials::EmissionType::NonEmissive,
         materials::SurfaceType::DielectricOpaque {
-            color: vec![-179.2, 6.0e8, -5e14],
+            color: spectrum::Spectrum::Polynomial(vec![-179.2, 6.0e8, -5e14]),
             roughness: 0.8,
+            anisotropy: 0.0,
+            rotation: 0.0,
         },
         1.5,
         0.0,
@@ -441,8 +491,10 @@ fn renderer_cornell_2() {
     let blue1 = materials::Material::new(
         materials::EmissionType::NonEmissive,
         materials::SurfaceType::DielectricOpaque {
-            color: vec![-99.2, 4.0e8, -4e14],
+            color: spectrum::Spectrum::Polynomial(vec![-99.2, 4.0e8, -4e14]),
             roughness: 0.8,
+            anisotropy: 0.0,
+            rotation: 0.0,
         },
         1.5,
         0.0,
@@ -453,8 +505,10 @@ fn renderer_cornell_2() {
             power: 200.0,
         },
         materials::SurfaceType::DielectricOpaque {
-            color: vec![0.8],
+            color: spectrum::Spectrum::Constant(0.8),
             roughness: 0.8,
+            anisotropy: 0.0,
+            rotation: 0.0,
         },
         1.5,
         0.0,
@@ -465,8 +519,10 @@ fn renderer_cornell_2() {
             power: 1.0,
         },
         materials::SurfaceType::DielectricOpaque {
-            color: vec![0.8],
+            color: spectrum::Spectrum::Constant(0.8),
             roughness: 0.8,
+            anisotropy: 0.0,
+            rotation: 0.0,
         },
         1.5,
         0.0,
@@ -618,6 +674,7 @@ fn renderer_cornell_2() {
     let mut sc = scene::Scene {
         camera: cam,
         background: bg,
+        medium: None,
         objects: vec![
             ball1, ball2, 	//	ball3,
             //			ball3, ball4, ball5,
@@ -634,7 +691,587 @@ fn renderer_cornell_2() {
         chunk_size_exp: 2,
         lights: vec![],
         max_depth: 3,
+        observer: renderer::Observer::Cie1931TwoDegree,
+        spectral: renderer::SpectralConfig::default(),
     };
     dis.render();
     dis.output.out(output::Format::PPM);
 }
+
+// tests below render small images and check pixel values instead of printing them
+
+// camera looking along y axis, resolution in pixels per side
+fn small_camera(resolution: u32) -> camera::Camera {
+    camera::Camera::new(
+        camera::Lens::Perspective,
+        0.035,
+        0.0512,
+        0.0512,
+        resolution,
+        resolution,
+        0.0,
+        1000.0,
+    )
+}
+
+// white light from every direction, with luminance 0.5
+fn furnace_background() -> scene::Background {
+    scene::Background {
+        color: scene::BackgroundType::Constant(spectrum::Spectrum::Constant(1.0)),
+        radiance: 0.5,
+    }
+}
+
+// single sphere filling the middle of the view of `small_camera`
+fn furnace_sphere(material: materials::Material) -> primitives::Primitive {
+    primitives::Primitive::new_sphere(algebra::Vector::new(0.0, 2.0, 0.0), 1.0, material)
+}
+
+fn render_small(scene: scene::Scene, aa_samples: usize, max_depth: u32) -> output::ImageFile {
+    let resolution = scene.camera.canvas_pix_width;
+    let mut dis = renderer::Renderer {
+        scene,
+        output: output::ImageFile::new(resolution, resolution),
+        aa_samples,
+        chunk_size_exp: 4,
+        lights: vec![],
+        max_depth,
+        observer: renderer::Observer::Cie1931TwoDegree,
+        spectral: renderer::SpectralConfig::default(),
+    };
+    dis.render();
+    dis.output
+}
+
+// mean linear sRGB color of the middle of the image, covered by `furnace_sphere`
+fn center_color(image: &output::ImageFile) -> (f64, f64, f64) {
+    let (width, height) = (image.width, image.height);
+    let mut sum = (0.0, 0.0, 0.0);
+    for x in width / 4..3 * width / 4 {
+        for y in height / 4..3 * height / 4 {
+            let pixel = image.get_pixel(x, y);
+            sum = (sum.0 + pixel.0, sum.1 + pixel.1, sum.2 + pixel.2);
+        }
+    }
+    let count = (width / 2 * height / 2) as f64;
+    (sum.0 / count, sum.1 / count, sum.2 / count)
+}
+
+fn luminance(color: (f64, f64, f64)) -> f64 {
+    0.212671 * color.0 + 0.715160 * color.1 + 0.072169 * color.2
+}
+
+// object that neither absorbs nor emits light disappears in the furnace
+fn assert_vanishes(image: &output::ImageFile) {
+    let center = luminance(center_color(image));
+    assert!(
+        (center - 0.5).abs() < 0.015,
+        "luminance {} in front of background with luminance 0.5",
+        center
+    );
+}
+
+fn furnace(
+    objects: Vec<primitives::Primitive>,
+    aa_samples: usize,
+    max_depth: u32,
+) -> output::ImageFile {
+    let sc = scene::Scene {
+        camera: small_camera(16),
+        background: furnace_background(),
+        medium: None,
+        objects,
+    };
+    render_small(sc, aa_samples, max_depth)
+}
+
+#[test]
+fn renderer_environment_map() {
+    // uniform map is importance sampled like any other, direct light samples and
+    // BSDF samples hitting the map are weighted against each other
+    let (width, height) = (16, 8);
+    let bg = scene::Background {
+        color: scene::BackgroundType::EnvironmentMap(environment::EnvironmentMap::new(
+            width,
+            height,
+            vec![(1.0, 1.0, 1.0); width * height],
+            0.0,
+        )),
+        radiance: 0.5,
+    };
+    let diffuse = materials::Material::new(
+        materials::EmissionType::NonEmissive,
+        materials::SurfaceType::DielectricOpaque {
+            color: spectrum::Spectrum::Constant(1.0),
+            roughness: 0.0,
+            anisotropy: 0.0,
+            rotation: 0.0,
+        },
+        1.5,
+        0.0,
+    );
+    let sc = scene::Scene {
+        camera: small_camera(16),
+        background: bg,
+        medium: None,
+        objects: vec![furnace_sphere(diffuse)],
+    };
+    assert_vanishes(&render_small(sc, 256, 3));
+}
+
+#[test]
+fn renderer_sky() {
+    // late afternoon sun to the left of the camera
+    let bg = scene::Background {
+        color: scene::BackgroundType::Sky(environment::Sky::new(0.3, 2.5, 3.0)),
+        radiance: 0.02,
+    };
+    let diff1 = materials::Material::new(
+        materials::EmissionType::NonEmissive,
+        materials::SurfaceType::DielectricOpaque {
+            color: spectrum::Spectrum::Constant(0.6),
+            roughness: 0.8,
+            anisotropy: 0.0,
+            rotation: 0.0,
+        },
+        1.5,
+        0.0,
+    );
+    let pln1 = primitives::Primitive::new_plane(
+        algebra::Vector::new(0.0, 0.0, -1.0),
+        algebra::Vector::new(0.0, 0.0, 1.0),
+        diff1,
+    );
+    let sc = scene::Scene {
+        objects: vec![pln1],
+        camera: small_camera(16),
+        background: bg,
+        medium: None,
+    };
+    let image = render_small(sc, 16, 3);
+    // clear sky above the horizon is blue, the ground below it is lit by sun and sky
+    let sky = image.get_pixel(8, 0);
+    let ground = image.get_pixel(8, 15);
+    assert!(sky.2 > sky.0 && sky.0 > 0.0, "sky {:?}", sky);
+    assert!(luminance(ground) > 0.0, "ground {:?}", ground);
+}
+
+#[test]
+fn renderer_furnace_constant() {
+    let diffuse = materials::Material::new(
+        materials::EmissionType::NonEmissive,
+        materials::SurfaceType::DielectricOpaque {
+            color: spectrum::Spectrum::Constant(1.0),
+            roughness: 0.0,
+            anisotropy: 0.0,
+            rotation: 0.0,
+        },
+        1.5,
+        0.0,
+    );
+    assert_vanishes(&furnace(vec![furnace_sphere(diffuse)], 256, 5));
+}
+
+#[test]
+fn renderer_furnace_conductor() {
+    // rough conductor reflecting everything vanishes against the background when
+    // multiple scattering between microfacets is accounted for
+    let mirror = materials::Material::new(
+        materials::EmissionType::NonEmissive,
+        materials::SurfaceType::Conductor {
+            roughness: 1.0,
+            anisotropy: 0.0,
+            rotation: 0.0,
+            film: None,
+        },
+        0.0,
+        1.0,
+    );
+    assert_vanishes(&furnace(vec![furnace_sphere(mirror)], 256, 5));
+}
+
+#[test]
+fn renderer_dispersion() {
+    // paths split by dispersion carry the energy of the whole wavelength bunch
+    let diamond = materials::Material::new(
+        materials::EmissionType::NonEmissive,
+        materials::SurfaceType::DielectricTransparent {
+            roughness: 0.0,
+            film: None,
+        },
+        materials::Ior::diamond(),
+        0.0,
+    );
+    assert_vanishes(&furnace(vec![furnace_sphere(diamond)], 256, 16));
+}
+
+#[test]
+fn renderer_nested_dielectrics() {
+    let transparent = |ior: materials::Ior, priority: u32| {
+        let mut material = materials::Material::new(
+            materials::EmissionType::NonEmissive,
+            materials::SurfaceType::DielectricTransparent {
+                roughness: 0.0,
+                film: None,
+            },
+            ior,
+            0.0,
+        );
+        material.priority = priority;
+        material
+    };
+    // hollow glass ball carved out by higher priority air, partially filled with water
+    // which overrides both where they overlap
+    let center = algebra::Vector::new(0.0, 2.0, 0.0);
+    let glass = primitives::Primitive::new_sphere(center, 1.0, transparent(1.5.into(), 1));
+    let air = primitives::Primitive::new_sphere(center, 0.9, transparent(1.0.into(), 2));
+    let water = primitives::Primitive::new_sphere(
+        algebra::Vector::new(0.0, 2.0, -0.35),
+        0.6,
+        transparent(materials::Ior::water(), 3),
+    );
+    assert_vanishes(&furnace(vec![glass, air, water], 256, 32));
+}
+
+#[test]
+fn renderer_media() {
+    // index matched boundary only delimits the fog, which scatters without absorbing
+    let mut fog = materials::Material::new(
+        materials::EmissionType::NonEmissive,
+        materials::SurfaceType::DielectricTransparent {
+            roughness: 0.0,
+            film: None,
+        },
+        1.0,
+        0.0,
+    );
+    fog.medium = Some(media::Medium::new(
+        spectrum::Spectrum::Constant(0.0),
+        spectrum::Spectrum::Constant(3.0),
+        0.6,
+    ));
+    assert_vanishes(&furnace(vec![furnace_sphere(fog)], 256, 64));
+    // glass absorbing red and green more than blue
+    let mut tinted = materials::Material::new(
+        materials::EmissionType::NonEmissive,
+        materials::SurfaceType::DielectricTransparent {
+            roughness: 0.0,
+            film: None,
+        },
+        1.5,
+        0.0,
+    );
+    tinted.medium = Some(media::Medium::absorbing(spectrum::Spectrum::Polynomial(
+        vec![-1.0, 4.0e6],
+    )));
+    let color = center_color(&furnace(vec![furnace_sphere(tinted)], 16, 16));
+    assert!(
+        color.2 > color.1 && color.1 > color.0,
+        "tinted glass {:?}",
+        color
+    );
+}
+
+#[test]
+fn renderer_fog_direct_light() {
+    // bank of fog around camera and sphere, lit by the same white light either sampled
+    // directly or only reached by continuing paths; shadow rays must be attenuated by the fog
+    let fog = || {
+        let grid = media::DensityGrid::from_fn(
+            algebra::Vector::new(-4.0, -1.0, -4.0),
+            algebra::Vector::new(4.0, 6.0, 4.0),
+            [4, 4, 4],
+            |p| 0.5 + 0.5 * (p.z / 4.0),
+        );
+        Some(media::Medium::heterogeneous(
+            spectrum::Spectrum::Constant(0.3),
+            spectrum::Spectrum::Constant(0.3),
+            0.0,
+            grid,
+        ))
+    };
+    let diffuse = || {
+        materials::Material::new(
+            materials::EmissionType::NonEmissive,
+            materials::SurfaceType::DielectricOpaque {
+                color: spectrum::Spectrum::Constant(1.0),
+                roughness: 0.0,
+                anisotropy: 0.0,
+                rotation: 0.0,
+            },
+            1.5,
+            0.0,
+        )
+    };
+    let (width, height) = (16, 8);
+    let sampled = scene::Background {
+        color: scene::BackgroundType::EnvironmentMap(environment::EnvironmentMap::new(
+            width,
+            height,
+            vec![(1.0, 1.0, 1.0); width * height],
+            0.0,
+        )),
+        radiance: 0.5,
+    };
+    let render = |background: scene::Background| {
+        let sc = scene::Scene {
+            camera: small_camera(16),
+            background,
+            medium: fog(),
+            objects: vec![furnace_sphere(diffuse())],
+        };
+        luminance(center_color(&render_small(sc, 256, 16)))
+    };
+    let direct = render(sampled);
+    let indirect = render(furnace_background());
+    assert!(indirect < 0.45, "fog does not dim the sphere, luminance {}", indirect);
+    assert!(
+        (direct - indirect).abs() < 0.015,
+        "luminance {} with direct light samples against {} without",
+        direct,
+        indirect
+    );
+}
+
+#[test]
+fn renderer_cloud() {
+    // procedural cloud fading out towards the edges of its bounding sphere
+    let center = algebra::Vector::new(0.0, 2.0, 0.0);
+    let radius = 1.0;
+    let extent = algebra::Vector::new(radius, radius, radius);
+    let grid = media::DensityGrid::from_fn(center - extent, center + extent, [24, 24, 24], |p| {
+        let falloff = 1.0 - (p - center).norm() / radius;
+        (media::fbm(p * 3.0, 5) * 2.0 - 0.6 + falloff).clamp(0.0, 1.0)
+    });
+    let mut cloud = materials::Material::new(
+        materials::EmissionType::NonEmissive,
+        materials::SurfaceType::DielectricTransparent {
+            roughness: 0.0,
+            film: None,
+        },
+        1.0,
+        0.0,
+    );
+    cloud.medium = Some(media::Medium::heterogeneous(
+        spectrum::Spectrum::Constant(0.0),
+        spectrum::Spectrum::Constant(12.0),
+        0.8,
+        grid,
+    ));
+    assert_vanishes(&furnace(
+        vec![primitives::Primitive::new_sphere(center, radius, cloud)],
+        256,
+        64,
+    ));
+}
+
+#[test]
+fn renderer_subsurface() {
+    let subsurface = |color: spectrum::Spectrum, mean_free_path: spectrum::Spectrum, ior: f64| {
+        let roughness = if ior == 1.0 { 0.0 } else { 0.3 };
+        materials::Material::new(
+            materials::EmissionType::NonEmissive,
+            materials::SurfaceType::Subsurface {
+                color,
+                mean_free_path,
+                roughness,
+            },
+            ior,
+            0.0,
+        )
+    };
+    // white medium with short paths loses no light, even though its random walks take far
+    // more steps than the few bounces allowed on surfaces; index matched boundary keeps
+    // internal reflections, which do count as bounces, out of the way
+    let white = subsurface(
+        spectrum::Spectrum::Constant(1.0),
+        spectrum::Spectrum::Constant(0.1),
+        1.0,
+    );
+    assert_vanishes(&furnace(vec![furnace_sphere(white)], 256, 5));
+    // wax scatters red light deeper than blue and keeps its color, without getting brighter
+    // than its surroundings
+    let wax = subsurface(
+        spectrum::Spectrum::srgb(0.9, 0.75, 0.5),
+        spectrum::Spectrum::Polynomial(vec![-0.1, 4.0e5]),
+        1.4,
+    );
+    let color = center_color(&furnace(vec![furnace_sphere(wax)], 64, 5));
+    assert!(color.0 > color.1 && color.1 > color.2, "wax {:?}", color);
+    assert!(luminance(color) < 0.5, "wax {:?}", color);
+}
+
+#[test]
+fn renderer_coated() {
+    // clear lacquer without absorption over white diffuse base
+    let lacquer = materials::Material::new(
+        materials::EmissionType::NonEmissive,
+        materials::SurfaceType::Coated {
+            base: materials::CoatBase::Diffuse {
+                color: spectrum::Spectrum::Constant(1.0),
+                roughness: 0.0,
+            },
+            roughness: 0.0,
+            thickness: 50.0e-6,
+            absorption: spectrum::Spectrum::Constant(0.0),
+        },
+        1.5,
+        0.0,
+    );
+    assert_vanishes(&furnace(vec![furnace_sphere(lacquer)], 256, 16));
+    // gold under a yellow tinted coat keeps its color
+    let (n, k) = materials::Metal::Gold.ior();
+    let lacquered_gold = materials::Material::new(
+        materials::EmissionType::NonEmissive,
+        materials::SurfaceType::Coated {
+            base: materials::CoatBase::Conductor {
+                n,
+                k,
+                roughness: 0.4,
+            },
+            roughness: 0.1,
+            thickness: 100.0e-6,
+            absorption: spectrum::Spectrum::Polynomial(vec![2.0e4, -2.4e10]),
+        },
+        1.5,
+        0.0,
+    );
+    let color = center_color(&furnace(vec![furnace_sphere(lacquered_gold)], 16, 16));
+    assert!(
+        color.0 > color.1 && color.1 > color.2,
+        "lacquered gold {:?}",
+        color
+    );
+}
+
+#[test]
+fn renderer_thin_film() {
+    // soap bubble is a water film with air on both sides, its colors are only redistributed
+    // between reflection and transmission
+    let bubble = materials::Material::new(
+        materials::EmissionType::NonEmissive,
+        materials::SurfaceType::DielectricTransparent {
+            roughness: 0.0,
+            film: Some(shaders::ThinFilm::new(380.0, 1.33)),
+        },
+        1.0,
+        0.0,
+    );
+    assert_vanishes(&furnace(vec![furnace_sphere(bubble)], 256, 16));
+}
+
+#[test]
+fn renderer_principled() {
+    let principled = |metallic: f64, transmission: f64| {
+        materials::Material::new(
+            materials::EmissionType::NonEmissive,
+            materials::SurfaceType::Principled {
+                base_color: spectrum::Spectrum::Constant(1.0),
+                metallic,
+                roughness: 0.3,
+                specular: 0.5,
+                specular_tint: 0.0,
+                sheen: 0.0,
+                clearcoat: 0.0,
+                clearcoat_roughness: 0.05,
+                transmission,
+                subsurface: 0.0,
+                subsurface_radius: spectrum::Spectrum::Constant(0.1),
+            },
+            1.5,
+            0.0,
+        )
+    };
+    // white metal and clear glass lose no energy
+    assert_vanishes(&furnace(
+        vec![furnace_sphere(principled(1.0, 0.0))],
+        256,
+        16,
+    ));
+    assert_vanishes(&furnace(
+        vec![furnace_sphere(principled(0.0, 1.0))],
+        256,
+        16,
+    ));
+}
+
+// dye absorbing violet and blue light, glowing green like a highlighter ink; it reradiates
+// up to 1.56 times `quantum_yield` at its emission peak
+fn highlighter_dye(quantum_yield: f64) -> materials::Fluorescence {
+    let absorption = spectrum::Spectrum::sampled(vec![
+        (380.0e-9, 0.0),
+        (430.0e-9, 0.9),
+        (480.0e-9, 0.9),
+        (510.0e-9, 0.0),
+    ]);
+    let emission =
+        spectrum::Spectrum::sampled(vec![(490.0e-9, 0.0), (520.0e-9, 1.0), (580.0e-9, 0.0)]);
+    materials::Fluorescence::dye(absorption, emission, quantum_yield)
+}
+
+#[test]
+fn renderer_fluorescence_furnace() {
+    // black sphere glowing under white light looks the same as diffuse one reflecting
+    // what the dye reradiates at each wavelength, which stays below one so that the diffuse
+    // reflectance is not clamped
+    let dye = highlighter_dye(0.5);
+    let reradiated = spectrum::Spectrum::sampled(
+        (0..=100)
+            .map(|k| {
+                let lambda = 300.0e-9 + k as f64 * 5.0e-9;
+                (lambda, dye.total(lambda))
+            })
+            .collect(),
+    );
+    let lambert = |color: spectrum::Spectrum| {
+        let lobe: std::sync::Arc<dyn shaders::Bxdf> =
+            std::sync::Arc::new(shaders::OrenNayar::new(0.0, color));
+        materials::Material::custom(materials::EmissionType::NonEmissive, vec![lobe], 1.5, 0.0)
+    };
+    let mut glowing = lambert(spectrum::Spectrum::Constant(0.0));
+    glowing.fluorescence = Some(dye);
+    let glow = center_color(&furnace(vec![furnace_sphere(glowing)], 1024, 5));
+    let diffuse = center_color(&furnace(vec![furnace_sphere(lambert(reradiated))], 1024, 5));
+    for (a, b) in [
+        (glow.0, diffuse.0),
+        (glow.1, diffuse.1),
+        (glow.2, diffuse.2),
+    ] {
+        assert!(
+            (a - b).abs() < 0.01,
+            "glowing {:?} against diffuse {:?}",
+            glow,
+            diffuse
+        );
+    }
+    assert!(luminance(glow) > 0.05);
+}
+
+#[test]
+fn renderer_fluorescence() {
+    // highlighter ink is greener under white light than the same yellow without the dye
+    let yellow = spectrum::Spectrum::sampled(vec![(480.0e-9, 0.05), (520.0e-9, 0.9)]);
+    let diffuse = |color: spectrum::Spectrum| {
+        materials::Material::new(
+            materials::EmissionType::NonEmissive,
+            materials::SurfaceType::DielectricOpaque {
+                color,
+                roughness: 0.8,
+                anisotropy: 0.0,
+                rotation: 0.0,
+            },
+            1.5,
+            0.0,
+        )
+    };
+    let mut ink = diffuse(yellow.clone());
+    ink.fluorescence = Some(highlighter_dye(0.8));
+    let glowing = center_color(&furnace(vec![furnace_sphere(ink)], 64, 5));
+    let plain = center_color(&furnace(vec![furnace_sphere(diffuse(yellow))], 64, 5));
+    assert!(
+        glowing.1 > 1.1 * plain.1,
+        "ink {:?} against plain {:?}",
+        glowing,
+        plain
+    );
+}
@@ -0,0 +1,34 @@
+use nilepsilon::sampler;
+
+#[test]
+fn distribution_1d_sample() {
+	let dist = sampler::Distribution1D::new(vec![1.0, 3.0, 0.0, 4.0]);
+	assert_eq!(dist.integral, 2.0);
+	let (x, pdf, index) = dist.sample(0.0625);
+	assert_eq!((x, pdf, index), (0.125, 0.5, 0));
+	let (x, pdf, index) = dist.sample(0.3);
+	assert_eq!(index, 1);
+	assert_eq!(pdf, 1.5);
+	assert!((x - (0.25 + 0.25 * (0.3 - 0.125) / 0.375)).abs() < 1e-12);
+	// zero valued segment is never sampled
+	let (_, _, index) = dist.sample(0.5);
+	assert_eq!(index, 3);
+	assert_eq!(dist.pdf(0.6), 0.0);
+}
+
+#[test]
+fn distribution_1d_degenerate() {
+	let dist = sampler::Distribution1D::new(vec![0.0, 0.0]);
+	let (x, pdf, _) = dist.sample(0.75);
+	assert_eq!((x, pdf), (0.75, 1.0));
+}
+
+#[test]
+fn distribution_2d_sample() {
+	let dist = sampler::Distribution2D::new(&[1.0, 1.0, 0.0, 2.0], 2, 2);
+	let ((u, v), pdf) = dist.sample((0.5, 0.9));
+	assert_eq!(pdf, dist.pdf((u, v)));
+	assert!(u >= 0.5 && v >= 0.5);
+	assert_eq!(dist.pdf((0.25, 0.75)), 0.0);
+	assert!((dist.pdf((0.75, 0.75)) - 2.0).abs() < 1e-12);
+}
@@ -0,0 +1,444 @@
+use nilepsilon::algebra;
+use nilepsilon::materials;
+use nilepsilon::sampler;
+use nilepsilon::shaders;
+use nilepsilon::shaders::Bxdf;
+use nilepsilon::spectrum;
+use std::sync::Arc;
+
+fn context() -> shaders::ShadingContext {
+	shaders::ShadingContext {
+		lambda: 550.0e-9,
+		eta: 1.5,
+		k: 0.0,
+	}
+}
+
+fn lobes() -> Vec<Box<dyn shaders::Bxdf>> {
+	vec![
+		Box::new(shaders::OrenNayar::new(0.4, spectrum::Spectrum::Constant(0.8))),
+		Box::new(shaders::Conductor::new(0.4)),
+		Box::new(shaders::Dielectric::new(0.4)),
+		Box::new(shaders::FresnelBlend::new(spectrum::Spectrum::Constant(0.8), 0.4)),
+		Box::new(shaders::Charlie::new(spectrum::Spectrum::Constant(0.8), 0.5)),
+		Box::new(shaders::Velvet::new(spectrum::Spectrum::Constant(0.8), 0.4)),
+	]
+}
+
+// perfect white Lambertian reflector
+struct Lambert;
+
+impl shaders::Bxdf for Lambert {
+	fn flags(&self) -> shaders::LobeFlags {
+		shaders::LobeFlags::REFLECTION | shaders::LobeFlags::DIFFUSE
+	}
+
+	fn eval(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		_context: &shaders::ShadingContext,
+	) -> f64 {
+		(incoming.z > 0.0 && outgoing.z > 0.0) as u8 as f64 / std::f64::consts::PI
+	}
+
+	fn pdf(
+		&self,
+		incoming: algebra::Vector,
+		outgoing: algebra::Vector,
+		context: &shaders::ShadingContext,
+	) -> f64 {
+		self.eval(incoming, outgoing, context) * incoming.z
+	}
+
+	fn sample(
+		&self,
+		outgoing: algebra::Vector,
+		random: (f64, f64, f64),
+		context: &shaders::ShadingContext,
+	) -> Option<shaders::BxdfSample> {
+		let phi = 2.0 * std::f64::consts::PI * random.1;
+		let r = random.0.sqrt();
+		let incoming = algebra::Vector::new(r * phi.cos(), r * phi.sin(), (1.0 - random.0).sqrt());
+		Some(shaders::BxdfSample {
+			incoming,
+			value: self.eval(incoming, outgoing, context),
+			pdf: self.pdf(incoming, outgoing, context),
+			flags: self.flags(),
+		})
+	}
+}
+
+#[test]
+fn lobe_flags() {
+	let flags = shaders::LobeFlags::REFLECTION | shaders::LobeFlags::SPECULAR;
+	assert!(flags.contains(shaders::LobeFlags::REFLECTION));
+	assert!(!flags.contains(shaders::LobeFlags::TRANSMISSION));
+	assert!(flags.is_specular());
+	assert!(shaders::Conductor::new(0.0).flags().is_specular());
+	assert!(!shaders::Conductor::new(0.5).flags().is_specular());
+	let dielectric = shaders::Dielectric::new(0.5).flags();
+	assert!(dielectric.contains(shaders::LobeFlags::REFLECTION | shaders::LobeFlags::TRANSMISSION));
+}
+
+#[test]
+fn sample_matches_eval_and_pdf() {
+	let mut sampler = sampler::Sampler {
+		rng: rand::thread_rng(),
+	};
+	let context = context();
+	for lobe in lobes() {
+		for outgoing in [
+			algebra::Vector::new(0.3, -0.2, 0.9).normalize(),
+			algebra::Vector::new(0.9, 0.1, 0.2).normalize(),
+		] {
+			let random = sampler.random_list_2d(1000, 0.0, 1.0);
+			let choice = sampler.random_list_1d(1000, 0.0, 1.0);
+			for (r, u) in random.iter().zip(choice) {
+				let sample = match lobe.sample(outgoing, (r.0, r.1, u), &context) {
+					Some(sample) => sample,
+					None => continue,
+				};
+				assert!((sample.incoming.norm() - 1.0).abs() < 1e-9);
+				let value = lobe.eval(sample.incoming, outgoing, &context);
+				let pdf = lobe.pdf(sample.incoming, outgoing, &context);
+				assert!((sample.value - value).abs() <= 1e-9 * value.max(1.0));
+				assert!((sample.pdf - pdf).abs() <= 1e-9 * pdf.max(1.0));
+				assert!(sample.value >= 0.0 && sample.pdf >= 0.0);
+			}
+		}
+	}
+}
+
+#[test]
+fn reflection_conserves_energy() {
+	let mut sampler = sampler::Sampler {
+		rng: rand::thread_rng(),
+	};
+	let context = shaders::ShadingContext {
+		lambda: 550.0e-9,
+		eta: 0.2,
+		k: 3.0,
+	};
+	let reflectors: Vec<Box<dyn shaders::Bxdf>> = vec![
+		Box::new(shaders::Conductor::new(0.0)),
+		Box::new(shaders::Conductor::new(0.3)),
+		Box::new(shaders::Conductor::new(0.8)),
+		Box::new(shaders::OrenNayar::new(0.5, spectrum::Spectrum::Constant(1.0))),
+		Box::new(shaders::FresnelBlend::new(spectrum::Spectrum::Constant(1.0), 0.5)),
+		Box::new(shaders::Velvet::new(spectrum::Spectrum::Constant(1.0), 0.0)),
+		Box::new(shaders::Velvet::new(spectrum::Spectrum::Constant(1.0), 0.5)),
+	];
+	for lobe in reflectors {
+		for theta_o in [0.1, 0.8, 1.3] {
+			let outgoing = algebra::Vector::new(f64::sin(theta_o), 0.0, f64::cos(theta_o));
+			let samples = 20000;
+			let albedo: f64 = sampler
+				.random_list_2d(samples, 0.0, 1.0)
+				.iter()
+				.zip(sampler.random_list_1d(samples, 0.0, 1.0))
+				.filter_map(|(r, u)| lobe.sample(outgoing, (r.0, r.1, u), &context))
+				.filter(|sample| sample.pdf > 0.0)
+				.map(|sample| sample.value * sample.incoming.z.abs() / sample.pdf)
+				.sum::<f64>()
+				/ samples as f64;
+			assert!(albedo <= 1.02, "theta {} albedo {}", theta_o, albedo);
+			assert!(albedo > 0.5, "theta {} albedo {}", theta_o, albedo);
+		}
+	}
+}
+
+#[test]
+fn custom_material() {
+	let lobe: Arc<dyn shaders::Bxdf> = Arc::new(Lambert);
+	let material = materials::Material::custom(
+		materials::EmissionType::NonEmissive,
+		vec![lobe.clone()],
+		1.5,
+		0.0,
+	);
+	assert!(!material.is_transmissive());
+	assert!(!material.has_delta_lobe());
+	assert!(material == material.clone());
+	let other =
+		materials::Material::custom(materials::EmissionType::NonEmissive, vec![lobe], 1.5, 0.0);
+	assert!(material == other);
+	let normal = algebra::Vector::new(0.0, 1.0, 0.0);
+	let frame = material.new_basis(normal);
+	let incoming = algebra::Vector::new(0.6, 0.8, 0.0);
+	let outgoing = algebra::Vector::new(-0.8, 0.6, 0.0);
+	let half_vec = (incoming + outgoing).normalize();
+	let value = material.return_scatter_radiance(incoming, outgoing, half_vec, &frame, 5.5e-7, 1.0);
+	assert!((value - 1.0 / std::f64::consts::PI).abs() < 1e-12);
+	// opaque materials are lit from either side of the geometry
+	let back =
+		material.return_scatter_radiance(-incoming, -outgoing, half_vec, &frame, 5.5e-7, 1.0);
+	assert!((back - value).abs() < 1e-12);
+	let pdf = material.return_pdf(incoming, outgoing, half_vec, &frame, 5.5e-7, 1.0);
+	assert!((pdf - 0.8 / std::f64::consts::PI).abs() < 1e-12);
+}
+
+#[test]
+#[should_panic]
+fn material_without_lobes() {
+	materials::Material::custom(materials::EmissionType::NonEmissive, vec![], 1.0, 0.0);
+}
+
+// integral over the sphere estimated with uniformly distributed directions
+fn sphere_integral(sampler: &mut sampler::Sampler, f: impl Fn(algebra::Vector) -> f64) -> f64 {
+	let samples = 200000;
+	let sum: f64 = sampler
+		.random_list_2d(samples, 0.0, 1.0)
+		.iter()
+		.map(|r| {
+			let cos_theta = 1.0 - 2.0 * r.0;
+			let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+			let phi = 2.0 * std::f64::consts::PI * r.1;
+			f(algebra::Vector::new(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta))
+		})
+		.sum();
+	4.0 * std::f64::consts::PI * sum / samples as f64
+}
+
+#[test]
+fn ggx_visible_normals() {
+	let mut sampler = sampler::Sampler {
+		rng: rand::thread_rng(),
+	};
+	let ggx = shaders::Ggx::new(0.3, 0.7);
+	// projected area of microfacets equals the macro surface
+	let projected = sphere_integral(&mut sampler, |h| ggx.d(h) * h.z);
+	assert!((projected - 1.0).abs() < 0.02, "{}", projected);
+	for outgoing in [
+		algebra::Vector::new(0.0, 0.0, 1.0),
+		algebra::Vector::new(0.6, 0.3, 0.4).normalize(),
+		algebra::Vector::new(-0.2, 0.9, 0.1).normalize(),
+	] {
+		let total = sphere_integral(&mut sampler, |h| ggx.pdf_visible(outgoing, h));
+		assert!((total - 1.0).abs() < 0.02, "{}", total);
+		// mean of sampled normals matches the one of the density
+		let mut mean = algebra::Vector::new(0.0, 0.0, 0.0);
+		let samples = 100000;
+		for random in sampler.random_list_2d(samples, 0.0, 1.0) {
+			let half_vec = ggx.sample_visible(outgoing, random);
+			assert!(half_vec.z >= 0.0 && outgoing * half_vec >= -1e-9);
+			mean = mean + half_vec / samples as f64;
+		}
+		let x = sphere_integral(&mut sampler, |h| h.x * ggx.pdf_visible(outgoing, h));
+		let y = sphere_integral(&mut sampler, |h| h.y * ggx.pdf_visible(outgoing, h));
+		let z = sphere_integral(&mut sampler, |h| h.z * ggx.pdf_visible(outgoing, h));
+		let expected = algebra::Vector::new(x, y, z);
+		assert!((mean - expected).norm() < 0.02, "{:?} {:?}", mean, expected);
+	}
+}
+
+#[test]
+fn anisotropic_microfacets() {
+	let context = shaders::ShadingContext {
+		lambda: 550.0e-9,
+		eta: 0.2,
+		k: 3.0,
+	};
+	let brushed = shaders::Conductor::with_microfacets(shaders::Ggx::new(0.05, 0.5));
+	let outgoing = algebra::Vector::new(0.0, 0.0, 1.0);
+	// highlight is stretched along the rougher direction
+	let along_x = algebra::Vector::new(0.5, 0.0, 0.866);
+	let along_y = algebra::Vector::new(0.0, 0.5, 0.866);
+	let x = brushed.eval(along_x, outgoing, &context);
+	let y = brushed.eval(along_y, outgoing, &context);
+	assert!(y > 10.0 * x, "{} {}", x, y);
+	// isotropic distribution has no preferred direction
+	let isotropic = shaders::Conductor::new(0.5);
+	let x = isotropic.eval(along_x, outgoing, &context);
+	let y = isotropic.eval(along_y, outgoing, &context);
+	assert!((x - y).abs() < 1e-12 * x);
+	assert!(shaders::Ggx::new(0.0, 0.0).is_smooth());
+	assert!(!shaders::Ggx::new(0.0, 0.2).is_smooth());
+}
+
+#[test]
+fn multiple_scattering_conserves_energy() {
+	let mut sampler = sampler::Sampler {
+		rng: rand::thread_rng(),
+	};
+	// conductor reflecting everything at any angle
+	let mirror = shaders::ShadingContext {
+		lambda: 550.0e-9,
+		eta: 0.0,
+		k: 1.0,
+	};
+	let glass = context();
+	for roughness in [0.3, 0.6, 1.0] {
+		let lobes: Vec<(Box<dyn shaders::Bxdf>, &shaders::ShadingContext)> = vec![
+			(Box::new(shaders::Conductor::new(roughness)), &mirror),
+			(Box::new(shaders::Dielectric::new(roughness)), &glass),
+		];
+		for (lobe, context) in lobes {
+			for outgoing in [
+				algebra::Vector::new(0.0, 0.0, 1.0),
+				algebra::Vector::new(0.8, 0.0, 0.6),
+				algebra::Vector::new(0.8, 0.0, -0.6),
+				algebra::Vector::new(0.0, 0.95, 0.31),
+			] {
+				if outgoing.z < 0.0 && !lobe.flags().contains(shaders::LobeFlags::TRANSMISSION) {
+					continue;
+				}
+				let samples = 40000;
+				// transmitted radiance is scaled by relative index of refraction, flux is not
+				let albedo: f64 = sampler
+					.random_list_2d(samples, 0.0, 1.0)
+					.iter()
+					.zip(sampler.random_list_1d(samples, 0.0, 1.0))
+					.filter_map(|(r, u)| lobe.sample(outgoing, (r.0, r.1, u), context))
+					.filter(|sample| sample.pdf > 0.0)
+					.map(|sample| {
+						let weight = sample.value * sample.incoming.z.abs() / sample.pdf;
+						if sample.incoming.z * outgoing.z > 0.0 {
+							weight
+						} else if outgoing.z > 0.0 {
+							weight * context.eta * context.eta
+						} else {
+							weight / (context.eta * context.eta)
+						}
+					})
+					.sum::<f64>()
+					/ samples as f64;
+				assert!((albedo - 1.0).abs() < 0.02, "{} {:?} {}", roughness, outgoing, albedo);
+			}
+		}
+	}
+}
+
+#[test]
+fn thin_film_interference() {
+	let lambda = 550.0e-9;
+	// vanishing or index matched film leaves Fresnel reflectance unchanged
+	for cos in [1.0, 0.6, 0.1, -0.9, -0.3] {
+		let bare = shaders::fresnel_dielectric(cos, 1.5);
+		let none = shaders::ThinFilm::new(0.0, 1.33).reflectance(cos, lambda, 1.5, 0.0);
+		let matched = shaders::ThinFilm::new(420.0, 1.0).reflectance(cos, lambda, 1.5, 0.0);
+		assert!((none - bare).abs() < 1e-9, "{} {} {}", cos, none, bare);
+		assert!((matched - bare).abs() < 1e-9, "{} {} {}", cos, matched, bare);
+	}
+	let gold = shaders::fresnel_conductor(0.7, 0.4, 2.4);
+	let none = shaders::ThinFilm::new(0.0, 2.0).reflectance(0.7, lambda, 0.4, 2.4);
+	assert!((none - gold).abs() < 1e-9);
+	// quarter wave coating cancels reflection at normal incidence
+	let ior = 1.5_f64.sqrt();
+	let coating = shaders::ThinFilm::new(550.0 / (4.0 * ior), ior);
+	assert!(coating.reflectance(1.0, lambda, 1.5, 0.0) < 1e-9);
+	assert!(coating.reflectance(1.0, 450.0e-9, 1.5, 0.0) > 1e-3);
+	// lossless film reflects the same from both sides
+	let soap = shaders::ThinFilm::new(380.0, 1.33);
+	let cos_t = f64::sqrt(1.0 - (1.0 - 0.5 * 0.5) / (1.2 * 1.2));
+	let outside = soap.reflectance(0.5, lambda, 1.2, 0.0);
+	let inside = soap.reflectance(-cos_t, lambda, 1.2, 0.0);
+	assert!((outside - inside).abs() < 1e-9, "{} {}", outside, inside);
+	// soap bubble changes color with wavelength
+	let colors: Vec<f64> = [450.0e-9, 550.0e-9, 650.0e-9]
+		.iter()
+		.map(|&lambda| soap.reflectance(1.0, lambda, 1.0, 0.0))
+		.collect();
+	let (min, max) = colors.iter().fold((1.0, 0.0), |(a, b): (f64, f64), &r| (a.min(r), b.max(r)));
+	assert!(max > 2.0 * min, "{:?}", colors);
+	// film is used inside Fresnel term of the lobes
+	let context = context();
+	let outgoing = algebra::Vector::new(0.0, 0.0, 1.0);
+	let filmed = shaders::Dielectric::new(0.0).with_film(coating);
+	let sample = filmed.sample(outgoing, (0.5, 0.5, 0.01), &context).unwrap();
+	assert!(sample.flags.contains(shaders::LobeFlags::TRANSMISSION));
+	let mirror = shaders::Conductor::new(0.0).with_film(soap);
+	let plain = shaders::Conductor::new(0.0);
+	assert!(mirror.eval(outgoing, outgoing, &context) != plain.eval(outgoing, outgoing, &context));
+}
+
+#[test]
+#[should_panic]
+fn thin_film_negative_thickness() {
+	shaders::ThinFilm::new(-1.0, 1.33);
+}
+
+// directional albedo by cosine weighted sampling of the hemisphere
+fn albedo(lobe: &dyn shaders::Bxdf, theta_o: f64) -> f64 {
+	let mut sampler = sampler::Sampler {
+		rng: rand::thread_rng(),
+	};
+	let context = context();
+	let outgoing = algebra::Vector::new(f64::sin(theta_o), 0.0, f64::cos(theta_o));
+	let samples = 20000;
+	let sum: f64 = sampler
+		.random_list_2d(samples, 0.0, 1.0)
+		.iter()
+		.map(|r| {
+			let phi = 2.0 * std::f64::consts::PI * r.1;
+			let sin_theta = r.0.sqrt();
+			let incoming = algebra::Vector::new(
+				sin_theta * phi.cos(),
+				sin_theta * phi.sin(),
+				(1.0 - r.0).sqrt(),
+			);
+			lobe.eval(incoming, outgoing, &context) * std::f64::consts::PI
+		})
+		.sum();
+	sum / samples as f64
+}
+
+// same as `albedo`, but sampling the lobe itself, for lobes too narrow for cosine sampling
+fn sampled_albedo(lobe: &dyn shaders::Bxdf, theta_o: f64) -> f64 {
+	let mut sampler = sampler::Sampler {
+		rng: rand::thread_rng(),
+	};
+	let context = context();
+	let outgoing = algebra::Vector::new(f64::sin(theta_o), 0.0, f64::cos(theta_o));
+	let samples = 20000;
+	sampler
+		.random_list_2d(samples, 0.0, 1.0)
+		.iter()
+		.zip(sampler.random_list_1d(samples, 0.0, 1.0))
+		.filter_map(|(r, u)| lobe.sample(outgoing, (r.0, r.1, u), &context))
+		.filter(|sample| sample.pdf > 0.0)
+		.map(|sample| sample.value * sample.incoming.z / sample.pdf)
+		.sum::<f64>()
+		/ samples as f64
+}
+
+#[test]
+fn charlie_sheen() {
+	let white = spectrum::Spectrum::Constant(1.0);
+	for roughness in [0.2, 0.6, 1.0] {
+		let sheen = shaders::Charlie::new(white.clone(), roughness);
+		let (normal, grazing) = (albedo(&sheen, 0.1), albedo(&sheen, 1.4));
+		// sheen is a faint glow at normal incidence, brightening towards grazing angles
+		assert!(normal > 0.0 && normal < 0.5, "{} {}", roughness, normal);
+		assert!(grazing > normal && grazing <= 1.0, "{} {} {}", roughness, normal, grazing);
+	}
+	let sheen = shaders::Charlie::new(white, 0.3);
+	let outgoing = algebra::Vector::new(0.9, 0.0, 0.2).normalize();
+	let backward = sheen.eval(outgoing, outgoing, &context());
+	let normal = sheen.eval(algebra::Vector::new(0.0, 0.0, 1.0), outgoing, &context());
+	assert!(backward > 10.0 * normal, "{} {}", backward, normal);
+}
+
+#[test]
+#[should_panic]
+fn charlie_roughness_out_of_range() {
+	shaders::Charlie::new(spectrum::Spectrum::Constant(1.0), 1.5);
+}
+
+#[test]
+fn velvet_retroreflection() {
+	let velvet = shaders::Velvet::new(spectrum::Spectrum::Constant(1.0), 0.3);
+	let outgoing = algebra::Vector::new(0.6, 0.0, 0.8);
+	let mirrored = algebra::Vector::new(-0.6, 0.0, 0.8);
+	let context = context();
+	// light goes back towards the viewer instead of to the mirror direction
+	let backward = velvet.eval(outgoing, outgoing, &context);
+	assert!(backward > 100.0 * velvet.eval(mirrored, outgoing, &context));
+	let albedo = sampled_albedo(&velvet, 0.6);
+	assert!(albedo > 0.8 && albedo <= 1.02, "{}", albedo);
+	// perfect retroreflector sends everything straight back
+	let smooth = shaders::Velvet::new(spectrum::Spectrum::Constant(0.5), 0.0);
+	let sample = smooth.sample(outgoing, (0.3, 0.7, 0.5), &context).unwrap();
+	assert!((sample.incoming - outgoing).norm() < 1e-12);
+	assert!((sample.value * sample.incoming.z / sample.pdf - 0.5).abs() < 1e-12);
+}
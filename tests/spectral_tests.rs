@@ -0,0 +1,63 @@
+use nilepsilon::renderer;
+
+fn integrate_pdf(config: &renderer::SpectralConfig) -> f64 {
+	let n = 100000;
+	let step = (config.lambda_max - config.lambda_min) / n as f64;
+	(0..n)
+		.map(|i| config.pdf(config.lambda_min + (i as f64 + 0.5) * step) * step)
+		.sum()
+}
+
+#[test]
+fn spectral_pdf_normalized() {
+	for sampling in [
+		renderer::WavelengthSampling::Uniform,
+		renderer::WavelengthSampling::Luminance,
+	] {
+		let config = renderer::SpectralConfig::new(400.0e-9, 700.0e-9, sampling);
+		assert!((integrate_pdf(&config) - 1.0).abs() < 1e-6);
+		assert_eq!(config.pdf(399.0e-9), 0.0);
+		assert_eq!(config.pdf(701.0e-9), 0.0);
+	}
+}
+
+#[test]
+fn spectral_luminance_sampling() {
+	let config = renderer::SpectralConfig::default();
+	// samples follow the pdf: cdf at sampled wavelength equals the random number
+	for u in [0.05, 0.3, 0.5, 0.77, 0.95] {
+		let lambda = config.sample(u);
+		assert!(lambda >= config.lambda_min && lambda <= config.lambda_max);
+		let n = 20000;
+		let step = (lambda - config.lambda_min) / n as f64;
+		let cdf: f64 = (0..n)
+			.map(|i| config.pdf(config.lambda_min + (i as f64 + 0.5) * step) * step)
+			.sum();
+		assert!((cdf - u).abs() < 1e-6);
+	}
+	// green is favoured over the spectrum ends
+	assert!(config.pdf(550.0e-9) > config.pdf(400.0e-9));
+	assert!(config.pdf(550.0e-9) > config.pdf(700.0e-9));
+}
+
+#[test]
+fn spectral_hero_wavelengths() {
+	let config = renderer::SpectralConfig::new(
+		400.0e-9,
+		800.0e-9,
+		renderer::WavelengthSampling::Uniform,
+	);
+	let (lambda, pdf) = config.sample_wavelengths(0.9);
+	let expected = [760.0e-9, 460.0e-9, 560.0e-9, 660.0e-9];
+	for (l, e) in [lambda.0, lambda.1, lambda.2, lambda.3].iter().zip(expected) {
+		assert!((l - e).abs() < 1e-15);
+	}
+	assert_eq!(pdf.0, 1.0 / 400.0e-9);
+	assert_eq!(pdf.3, 1.0 / 400.0e-9);
+}
+
+#[test]
+#[should_panic]
+fn spectral_invalid_range() {
+	renderer::SpectralConfig::new(700.0e-9, 400.0e-9, renderer::WavelengthSampling::Uniform);
+}
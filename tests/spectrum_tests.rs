@@ -0,0 +1,108 @@
+use nilepsilon::algebra;
+use nilepsilon::constants;
+use nilepsilon::materials;
+use nilepsilon::spectrum;
+
+#[test]
+fn spectrum_constant_and_polynomial() {
+	assert_eq!(spectrum::Spectrum::Constant(0.3).evaluate(500.0e-9), 0.3);
+	let polynomial = spectrum::Spectrum::Polynomial(vec![1.0, 2.0e6, -1.0e12]);
+	let lambda = 500.0e-9;
+	let expected = 1.0 + 2.0e6 * lambda - 1.0e12 * lambda * lambda;
+	assert!((polynomial.evaluate(lambda) - expected).abs() < 1e-12);
+}
+
+#[test]
+fn spectrum_sampled() {
+	let sampled =
+		spectrum::Spectrum::sampled(vec![(400.0e-9, 0.0), (500.0e-9, 1.0), (700.0e-9, 0.5)]);
+	assert!((sampled.evaluate(450.0e-9) - 0.5).abs() < 1e-12);
+	assert!((sampled.evaluate(600.0e-9) - 0.75).abs() < 1e-12);
+	assert_eq!(sampled.evaluate(500.0e-9), 1.0);
+	// constant extension outside of the data
+	assert_eq!(sampled.evaluate(300.0e-9), 0.0);
+	assert_eq!(sampled.evaluate(800.0e-9), 0.5);
+	let uniform = spectrum::Spectrum::sampled_uniform(400.0e-9, 700.0e-9, &[0.0, 0.3, 0.6, 0.9]);
+	assert!((uniform.evaluate(650.0e-9) - 0.75).abs() < 1e-12);
+}
+
+#[test]
+#[should_panic]
+fn spectrum_sampled_unsorted() {
+	spectrum::Spectrum::sampled(vec![(500.0e-9, 0.0), (400.0e-9, 1.0)]);
+}
+
+#[test]
+fn spectrum_blackbody() {
+	let sun = spectrum::Spectrum::BlackBody(5778.0).evaluate(500.0e-9);
+	assert!((sun / 2.6375669866e13 - 1.0).abs() < 1e-9);
+	let normalized = spectrum::Spectrum::BlackBodyNormalized(5778.0);
+	assert!((normalized.evaluate(constants::WIEN / 5778.0) - 1.0).abs() < 1e-12);
+	assert!(normalized.evaluate(400.0e-9) < 1.0);
+	assert!(normalized.evaluate(700.0e-9) < 1.0);
+}
+
+#[test]
+fn spectrum_color_checker() {
+	for patch in spectrum::ColorChecker::ALL {
+		let reflectance = patch.spectrum();
+		for nm in (380..=720).step_by(10) {
+			let value = reflectance.evaluate(nm as f64 * 1e-9);
+			assert!((0.0..=1.1).contains(&value), "{:?} at {} nm: {}", patch, nm, value);
+		}
+	}
+	let white = spectrum::ColorChecker::White.spectrum().evaluate(550.0e-9);
+	let black = spectrum::ColorChecker::Black.spectrum().evaluate(550.0e-9);
+	assert!(white > 0.85 && black < 0.04);
+	let red = spectrum::ColorChecker::Red.spectrum();
+	assert!(red.evaluate(650.0e-9) > 3.0 * red.evaluate(450.0e-9));
+}
+
+#[test]
+fn spectrum_color_checker_measured() {
+	// wavelength in nm of the highest reflectance between 400 and 700 nm
+	let peak = |patch: spectrum::ColorChecker| {
+		let reflectance = patch.spectrum();
+		(400..=700)
+			.step_by(10)
+			.max_by(|a, b| {
+				let (a, b) = (*a as f64 * 1e-9, *b as f64 * 1e-9);
+				reflectance.evaluate(a).total_cmp(&reflectance.evaluate(b))
+			})
+			.unwrap()
+	};
+	assert!((430..=470).contains(&peak(spectrum::ColorChecker::Blue)));
+	assert!((510..=550).contains(&peak(spectrum::ColorChecker::Green)));
+	assert!(peak(spectrum::ColorChecker::Red) >= 650);
+	// neutral patches are flat and get darker along the row
+	let neutrals = &spectrum::ColorChecker::ALL[18..];
+	for pair in neutrals.windows(2) {
+		for nm in (420..=700).step_by(10) {
+			let (lighter, darker) = (pair[0].spectrum(), pair[1].spectrum());
+			assert!(lighter.evaluate(nm as f64 * 1e-9) > darker.evaluate(nm as f64 * 1e-9));
+		}
+	}
+	let gray = spectrum::ColorChecker::Neutral5.spectrum();
+	assert!((gray.evaluate(450.0e-9) - gray.evaluate(650.0e-9)).abs() < 0.02);
+}
+
+#[test]
+fn spectrum_emission() {
+	let material = materials::Material::new(
+		materials::EmissionType::Spectral {
+			spectrum: spectrum::Spectrum::sampled(vec![(400.0e-9, 1.0), (700.0e-9, 3.0)]),
+			power: 2.0,
+		},
+		materials::SurfaceType::DielectricOpaque {
+			color: spectrum::Spectrum::Constant(0.5),
+			roughness: 0.5,
+			anisotropy: 0.0,
+			rotation: 0.0,
+		},
+		1.5,
+		0.0,
+	);
+	let up = algebra::Vector::new(0.0, 0.0, 1.0);
+	let radiance = material.return_emission_radiance(up, up, up, up, 550.0e-9);
+	assert!((radiance - 4.0).abs() < 1e-12);
+}
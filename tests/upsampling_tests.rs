@@ -0,0 +1,68 @@
+use nilepsilon::algebra;
+use nilepsilon::materials;
+use nilepsilon::spectrum;
+use nilepsilon::upsampling;
+
+#[test]
+fn upsampling_white_and_black() {
+	for lambda in [350.0e-9, 400.0e-9, 550.0e-9, 700.0e-9, 800.0e-9] {
+		assert!((upsampling::linear_rgb_to_spectrum((1.0, 1.0, 1.0), lambda) - 1.0).abs() < 1e-12);
+		assert_eq!(upsampling::linear_rgb_to_spectrum((0.0, 0.0, 0.0), lambda), 0.0);
+	}
+}
+
+#[test]
+fn upsampling_primaries() {
+	let red = |lambda| upsampling::linear_rgb_to_spectrum((1.0, 0.0, 0.0), lambda);
+	let green = |lambda| upsampling::linear_rgb_to_spectrum((0.0, 1.0, 0.0), lambda);
+	let blue = |lambda| upsampling::linear_rgb_to_spectrum((0.0, 0.0, 1.0), lambda);
+	assert!(red(650.0e-9) > 0.9 && red(530.0e-9) < 0.1);
+	assert!(green(530.0e-9) > 0.9 && green(450.0e-9) < 0.1 && green(680.0e-9) < 0.1);
+	assert!(blue(420.0e-9) > 0.9 && blue(600.0e-9) < 0.1);
+}
+
+#[test]
+fn upsampling_srgb_transfer() {
+	assert_eq!(upsampling::srgb_to_linear(0.0), 0.0);
+	assert!((upsampling::srgb_to_linear(1.0) - 1.0).abs() < 1e-12);
+	assert!((upsampling::srgb_to_linear(0.5) - 0.214041).abs() < 1e-6);
+	assert!((upsampling::srgb_to_linear(0.04) - 0.04 / 12.92).abs() < 1e-12);
+	let grey = upsampling::srgb_to_spectrum((0.5, 0.5, 0.5), 550.0e-9);
+	assert!((grey - 0.214041).abs() < 1e-6);
+}
+
+#[test]
+fn upsampling_material_matches_polynomial() {
+	let polynomial = materials::Material::new(
+		materials::EmissionType::NonEmissive,
+		materials::SurfaceType::DielectricOpaque {
+			color: spectrum::Spectrum::Constant(1.0),
+			roughness: 0.5,
+			anisotropy: 0.0,
+			rotation: 0.0,
+		},
+		1.5,
+		0.0,
+	);
+	let rgb = materials::Material::new(
+		materials::EmissionType::NonEmissive,
+		materials::SurfaceType::DielectricOpaque {
+			color: spectrum::Spectrum::srgb(1.0, 1.0, 1.0),
+			roughness: 0.5,
+			anisotropy: 0.0,
+			rotation: 0.0,
+		},
+		1.5,
+		0.0,
+	);
+	let frame = rgb.new_basis(algebra::Vector::new(0.0, 0.0, 1.0));
+	let incoming = algebra::Vector::new(0.6, 0.0, 0.8);
+	let outgoing = algebra::Vector::new(0.0, 0.6, 0.8);
+	let half_vec = (incoming + outgoing).normalize();
+	for lambda in [450.0e-9, 550.0e-9, 650.0e-9] {
+		let a =
+			polynomial.return_scatter_radiance(incoming, outgoing, half_vec, &frame, lambda, 1.0);
+		let b = rgb.return_scatter_radiance(incoming, outgoing, half_vec, &frame, lambda, 1.0);
+		assert!((a - b).abs() < 1e-12);
+	}
+}